@@ -0,0 +1,144 @@
+//! Benchmarks of `new`, `add`, `le`, `merge`, and `payload` for every `CvRDT`, across sizes
+//!
+//! Run with `cargo bench`; pass a filter to run only some, e.g. `cargo bench -- g_set/merge`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use cvrdt_exposition::causality::{Dot, DottedVersionVector, VersionVector};
+use cvrdt_exposition::{GCounter, GSet, Grow, LWWRegister, OneWayBoolean, PNCounter, TwoPhaseSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::time::{Duration, UNIX_EPOCH};
+
+static SIZES: [usize; 4] = [10, 100, 1_000, 10_000];
+
+/// Benchmark every operation on a `G` of each size
+///
+/// # Parameters
+///
+/// - `c`: the benchmark harness
+/// - `name`: the name of the benchmark group
+/// - `sizes`: the sizes to benchmark
+/// - `payload`: given a size `n` and `0` or `1`, a payload of size `n`; the two payloads should
+///   overlap but differ, so that neither `le` nor `merge` is trivial
+/// - `update`: given a size, an update to `add`
+fn bench<G: Grow>(
+    c: &mut Criterion,
+    name: &str,
+    sizes: &[usize],
+    payload: impl Fn(usize, usize) -> G::Payload,
+    update: impl Fn(usize) -> G::Update,
+) where
+    G::Payload: Clone,
+{
+    let mut group = c.benchmark_group(name);
+    for &n in sizes {
+        let (px, py) = (payload(n, 0), payload(n, 1));
+        let (x, y) = (G::new(px.clone()), G::new(py));
+        group.bench_with_input(BenchmarkId::new("new", n), &px, |b, p| {
+            b.iter_batched(|| p.clone(), G::new, BatchSize::SmallInput);
+        });
+        group.bench_with_input(BenchmarkId::new("add", n), &x, |b, x| {
+            b.iter_batched(
+                || (x.clone(), update(n)),
+                |(mut x, u)| {
+                    x.add(u);
+                    x
+                },
+                BatchSize::SmallInput,
+            );
+        });
+        group.bench_with_input(BenchmarkId::new("le", n), &(&x, &y), |b, (x, y)| {
+            b.iter(|| black_box(*x).le(black_box(*y)));
+        });
+        group.bench_with_input(BenchmarkId::new("merge", n), &(&x, &y), |b, (x, y)| {
+            b.iter(|| black_box(*x).merge(black_box(*y)));
+        });
+        group.bench_with_input(BenchmarkId::new("payload", n), &x, |b, x| {
+            b.iter(|| black_box(x).payload());
+        });
+    }
+    group.finish();
+}
+
+/// `n` counts, where the `k`th of a pair mostly agrees with the other
+fn counts(n: usize, k: usize) -> Vec<u64> {
+    (0..n as u64).map(|i| i + (i + k as u64) % 2).collect()
+}
+
+/// `n` values, half of which the other of a pair shares
+fn values(n: usize, k: usize) -> HashSet<u64> {
+    let start = (k * n / 2) as u64;
+    (start..start + n as u64).collect()
+}
+
+fn counters(c: &mut Criterion) {
+    bench::<GCounter>(c, "g_counter", &SIZES, |n, k| (k, counts(n, k)), |_| ());
+    bench::<PNCounter>(
+        c,
+        "pn_counter",
+        &SIZES,
+        |n, k| (k, counts(n, k), counts(n, 1 - k)),
+        |_| (),
+    );
+}
+
+fn sets(c: &mut Criterion) {
+    bench::<GSet<u64>>(c, "g_set", &SIZES, values, |n| n as u64 * 2);
+    bench::<TwoPhaseSet<u64>>(
+        c,
+        "two_phase_set",
+        &SIZES,
+        |n, k| {
+            let added = values(n, k);
+            let removed = added.iter().copied().filter(|x| x % 3 == 0).collect();
+            (added, removed)
+        },
+        |n| n as u64 * 2,
+    );
+}
+
+fn registers(c: &mut Criterion) {
+    // the size of a register is the size of its value
+    bench::<LWWRegister<Vec<u64>>>(
+        c,
+        "lww_register",
+        &SIZES,
+        |n, k| (counts(n, k), UNIX_EPOCH + Duration::from_secs(k as u64)),
+        |n| counts(n, 0),
+    );
+    bench::<OneWayBoolean>(c, "one_way_boolean", &[1], |_, k| k == 1, |_| ());
+}
+
+fn causality(c: &mut Criterion) {
+    bench::<VersionVector<usize>>(
+        c,
+        "version_vector",
+        &SIZES,
+        |n, k| (0..n).zip(counts(n, k)).collect(),
+        |_| 0,
+    );
+    bench::<DottedVersionVector<usize>>(
+        c,
+        "dotted_version_vector",
+        &SIZES,
+        |n, k| {
+            let clock: BTreeMap<_, _> = (0..n).zip(counts(n, k)).collect();
+            // every tenth replica has a gap, then an isolated event
+            let cloud: BTreeSet<_> = clock
+                .iter()
+                .step_by(10)
+                .map(|(&replica, &c)| Dot {
+                    replica,
+                    counter: c + 2 + k as u64,
+                })
+                .collect();
+            (clock, cloud)
+        },
+        |n| Dot {
+            replica: n / 2,
+            counter: n as u64 * 2,
+        },
+    );
+}
+
+criterion_group!(benches, counters, sets, registers, causality);
+criterion_main!(benches);
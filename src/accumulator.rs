@@ -0,0 +1,586 @@
+//! Grow-only accumulators of non-negative amounts
+//!
+//! An [`Accumulator`](struct.Accumulator.html) is a [`GCounter`](../g_counter/struct.GCounter.html)
+//! whose updates add an arbitrary non-negative [`Amount`](trait.Amount.html) rather than one, e.g.
+//! cumulative spend in currency, or a sum of latencies in seconds. Each replica adds to its own
+//! slot, and merging takes the maximum of each slot, just like a `GCounter`.
+//!
+//! Amounts come in two flavours:
+//!
+//! - [`Decimal`](struct.Decimal.html)s are fixed-point, with a number of decimal places that's
+//!   part of the type. Sums are exact, and overflowing panics.
+//! - [`Float`](struct.Float.html)s are opt-in floating-point amounts, for when range matters more
+//!   than exactness. See its documentation for how NaNs and infinities are handled.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::accumulator::{Accumulator, Decimal};
+//! use cvrdt_exposition::Grow;
+//! let mut x: Accumulator<Decimal<2>> = Accumulator::new((0, vec![Decimal::ZERO; 2]));
+//! x.add("12.34".parse().unwrap());
+//! x.add("0.66".parse().unwrap());
+//! let mut y = Accumulator::new((1, vec![Decimal::ZERO; 2]));
+//! y.add("100".parse().unwrap());
+//! assert_eq!(x.merge(&y).query(&()).to_string(), "113.00");
+//! ```
+
+use crate::traits::Grow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// A non-negative amount that can be accumulated
+pub trait Amount: Copy + Debug + Eq + Hash + Ord + 'static {
+    /// Zero, which every slot starts from
+    const ZERO: Self;
+
+    /// Add, returning `None` on overflow
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// This amount as an `f64`, rounding if need be
+    fn to_f64(self) -> f64;
+}
+
+/// A non-negative fixed-point decimal with `PLACES` digits after the decimal point
+///
+/// Stored as a whole number of `units`, each worth `10^-PLACES`, so a `Decimal<2>` counts cents.
+///
+/// # Panics
+///
+/// Displaying or parsing a `Decimal` with more than 19 `PLACES` panics, since `10^PLACES` doesn't
+/// fit in a `u64`.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::accumulator::Decimal;
+/// let x: Decimal<2> = "3.5".parse().unwrap();
+/// assert_eq!(x.units(), 350);
+/// assert_eq!(x.to_string(), "3.50");
+/// assert_eq!(Decimal::<3>::from_units(1234).to_string(), "1.234");
+/// assert!("3.141".parse::<Decimal<2>>().is_err());
+/// assert!("-1".parse::<Decimal<2>>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Decimal<const PLACES: u32> {
+    units: u64,
+}
+
+impl<const PLACES: u32> Decimal<PLACES> {
+    /// Zero
+    pub const ZERO: Self = Decimal { units: 0 };
+
+    /// The decimal worth `units * 10^-PLACES`
+    #[must_use]
+    pub fn from_units(units: u64) -> Self {
+        Decimal { units }
+    }
+
+    /// The number of `10^-PLACES` units this decimal is worth
+    #[must_use]
+    pub fn units(self) -> u64 {
+        self.units
+    }
+}
+
+impl<const PLACES: u32> Amount for Decimal<PLACES> {
+    const ZERO: Self = Decimal { units: 0 };
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        self.units.checked_add(other.units).map(Decimal::from_units)
+    }
+    fn to_f64(self) -> f64 {
+        self.units as f64 / 10_f64.powi(PLACES as i32)
+    }
+}
+
+impl<const PLACES: u32> Display for Decimal<PLACES> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = 10_u64.pow(PLACES);
+        write!(f, "{}", self.units / scale)?;
+        if PLACES > 0 {
+            write!(
+                f,
+                ".{:0width$}",
+                self.units % scale,
+                width = PLACES as usize
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<const PLACES: u32> FromStr for Decimal<PLACES> {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+        let (whole, fraction) = match s.split_once('.') {
+            Some((whole, fraction)) if digits(fraction) => (whole, fraction),
+            Some(_) => return Err("Invalid decimal"),
+            None => (s, ""),
+        };
+        if !digits(whole) {
+            return Err("Invalid decimal");
+        }
+        let places = u32::try_from(fraction.len()).map_err(|_| "Too many decimal places")?;
+        if places > PLACES {
+            return Err("Too many decimal places");
+        }
+        let parse = |part: &str| part.parse::<u64>().map_err(|_| "Decimal too large");
+        let fraction = if fraction.is_empty() {
+            0
+        } else {
+            parse(fraction)?
+        };
+        parse(whole)?
+            .checked_mul(10_u64.pow(PLACES))
+            .and_then(|units| units.checked_add(fraction * 10_u64.pow(PLACES - places)))
+            .map(Decimal::from_units)
+            .ok_or("Decimal too large")
+    }
+}
+
+/// A non-negative floating-point amount
+///
+/// # NaNs and infinities
+///
+/// Merging takes the maximum of each slot, which needs amounts to be totally ordered, so a `Float`
+/// is never NaN: converting a NaN (or a negative number) into a `Float` fails. Positive infinity
+/// is allowed, and is what a total overflows to after enough finite additions (so `checked_add`
+/// never fails). An infinite total stays infinite for good: it's the largest `Float`, so merges
+/// keep it too, and queries report it as such rather than panicking.
+///
+/// Negative zero is normalized to zero, so that equal amounts are `==`. Note that JSON has no
+/// infinity, so infinite amounts can't round-trip through `serde_json`.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::accumulator::{Amount, Float};
+/// let x = Float::try_from(0.25).unwrap();
+/// assert_eq!(x.get(), 0.25);
+/// assert!(Float::try_from(f64::NAN).is_err());
+/// assert!(Float::try_from(-1.0).is_err());
+/// assert!(Float::try_from(f64::NEG_INFINITY).is_err());
+/// let big = Float::try_from(f64::MAX).unwrap();
+/// assert_eq!(big.checked_add(big).unwrap().get(), f64::INFINITY);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "f64", into = "f64")
+)]
+pub struct Float(f64);
+
+impl Float {
+    /// Zero
+    pub const ZERO: Self = Float(0.0);
+
+    /// This amount as an `f64`, which is never NaN or negative (but may be infinite)
+    #[must_use]
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for Float {
+    type Error = &'static str;
+
+    fn try_from(x: f64) -> Result<Self, Self::Error> {
+        if x.is_nan() {
+            Err("NaN amount")
+        } else if x < 0.0 {
+            Err("Negative amount")
+        } else {
+            Ok(Float(x + 0.0)) // normalizes -0.0
+        }
+    }
+}
+
+impl From<Float> for f64 {
+    fn from(x: Float) -> Self {
+        x.0
+    }
+}
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Float {}
+
+impl PartialOrd for Float {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for Float {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Amount for Float {
+    const ZERO: Self = Float(0.0);
+
+    /// Add, overflowing to infinity rather than failing
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(Float(self.0 + other.0))
+    }
+    fn to_f64(self) -> f64 {
+        self.0
+    }
+}
+
+/// A vectorized accumulator of non-negative amounts, which can only grow
+///
+/// # Panics
+///
+/// Like a [`GCounter`](../g_counter/struct.GCounter.html), `le` and `merge` panic if the `totals`
+/// vectors are not the same length. `add` (and `query`) panic if a total overflows, which can
+/// only happen with [`Decimal`](struct.Decimal.html) amounts:
+///
+/// ```should_panic
+/// // this will panic
+/// use cvrdt_exposition::accumulator::{Accumulator, Decimal};
+/// use cvrdt_exposition::Grow;
+/// let mut x: Accumulator<Decimal<0>> = Accumulator::new((0, vec![Decimal::from_units(u64::MAX)]));
+/// x.add(Decimal::from_units(1));
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::accumulator::{Accumulator, Float};
+/// use cvrdt_exposition::Grow;
+/// let seconds = |x: f64| Float::try_from(x).unwrap();
+/// let mut x = Accumulator::new((0, vec![Float::ZERO; 2]));
+/// x.add(seconds(0.5));
+/// let mut y = Accumulator::new((1, vec![Float::ZERO; 2]));
+/// y.add(seconds(1.25));
+/// let z = x.merge(&y);
+/// assert_eq!(z.query(&()).get(), 1.75);
+/// assert!(x.le(&z) && y.le(&z));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedAccumulator<A>"))]
+pub struct Accumulator<A: Amount> {
+    /// The index for this local `Accumulator` where all additions occur
+    pub id: usize,
+    /// The vector of totals, one per replica
+    pub totals: Vec<A>,
+}
+
+impl<A: Amount> Accumulator<A> {
+    fn compatible_len(&self, other: &Self) -> usize {
+        assert_eq!(
+            self.totals.len(),
+            other.totals.len(),
+            "Incompatible lengths"
+        );
+        self.totals.len()
+    }
+}
+
+impl<A: Amount> Grow for Accumulator<A> {
+    type Payload = (usize, Vec<A>);
+    type PayloadRef<'a> = (usize, &'a [A]);
+    type Update = A;
+    type Query = ();
+    type Value = A;
+
+    fn new(payload: Self::Payload) -> Self {
+        Accumulator {
+            id: payload.0,
+            totals: payload.1,
+        }
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.id, self.totals.clone())
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.id, &self.totals)
+    }
+    fn add(&mut self, update: Self::Update) {
+        let total = &mut self.totals[self.id];
+        *total = total.checked_add(update).expect("Amount overflow");
+    }
+    fn le(&self, other: &Self) -> bool {
+        let n = self.compatible_len(other);
+        (0..n).all(|i| self.totals[i] <= other.totals[i])
+    }
+    fn compatible(&self, other: &Self) -> bool {
+        self.totals.len() == other.totals.len()
+    }
+    fn merge(&self, other: &Self) -> Self {
+        let n = self.compatible_len(other);
+        Accumulator {
+            id: self.id.min(other.id), // arbitrary, as for `GCounter`
+            totals: (0..n)
+                .map(|i| self.totals[i].max(other.totals[i]))
+                .collect(),
+        }
+    }
+    fn query(&self, _query: &Self::Query) -> Self::Value {
+        self.totals.iter().fold(A::ZERO, |sum, &x| {
+            sum.checked_add(x).expect("Amount overflow")
+        })
+    }
+}
+
+/// An `Accumulator` as it comes off the wire, before we've checked that its `id` is valid
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedAccumulator<A> {
+    id: usize,
+    totals: Vec<A>,
+}
+
+#[cfg(feature = "serde")]
+impl<A: Amount> TryFrom<UncheckedAccumulator<A>> for Accumulator<A> {
+    type Error = &'static str;
+
+    fn try_from(unchecked: UncheckedAccumulator<A>) -> Result<Self, Self::Error> {
+        if unchecked.id < unchecked.totals.len() {
+            Ok(Accumulator {
+                id: unchecked.id,
+                totals: unchecked.totals,
+            })
+        } else {
+            Err("ID too large")
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+static MAX_SIZE: usize = 100;
+
+/// Arbitrary `Decimal`s, small enough that sums of them won't overflow
+#[cfg(any(test, feature = "testing"))]
+impl<const PLACES: u32> proptest::arbitrary::Arbitrary for Decimal<PLACES> {
+    type Parameters = ();
+    type Strategy = proptest::strategy::Map<std::ops::RangeInclusive<u64>, fn(u64) -> Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (0..=u64::from(u32::MAX)).prop_map(Decimal::from_units)
+    }
+}
+
+/// Arbitrary `Float`s, mostly finite
+#[cfg(any(test, feature = "testing"))]
+impl proptest::arbitrary::Arbitrary for Float {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        prop_oneof![
+            1 => Just(0.0),
+            4 => 0.0..1e9,
+            4 => 0.0..f64::MAX,
+            1 => Just(f64::INFINITY),
+        ]
+        .prop_map(|x| Float::try_from(x).unwrap())
+        .boxed()
+    }
+}
+
+/// A strategy for arbitrary `Accumulator`s with exactly `n` totals
+///
+/// # Panics
+///
+/// When generating values, if `n` is zero (there must be a valid `id`)
+#[cfg(any(test, feature = "testing"))]
+pub fn sized<A: Amount + proptest::arbitrary::Arbitrary>(
+    n: usize,
+) -> impl proptest::strategy::Strategy<Value = Accumulator<A>> {
+    use proptest::prelude::*;
+    prop::collection::vec(any::<A>(), n)
+        .prop_flat_map(|totals| {
+            let len = totals.len();
+            (0..len, Just(totals))
+        })
+        .prop_map(|(id, totals)| Accumulator { id, totals })
+}
+
+/// A strategy for arbitrary pairs of `Accumulator`s of the same size, i.e. pairs that can be
+/// merged
+#[cfg(any(test, feature = "testing"))]
+pub fn two<A: Amount + proptest::arbitrary::Arbitrary>(
+) -> impl proptest::strategy::Strategy<Value = (Accumulator<A>, Accumulator<A>)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n)))
+}
+
+/// A strategy for arbitrary triples of `Accumulator`s of the same size, i.e. triples that can be
+/// merged
+#[cfg(any(test, feature = "testing"))]
+pub fn three<A: Amount + proptest::arbitrary::Arbitrary>(
+) -> impl proptest::strategy::Strategy<Value = (Accumulator<A>, Accumulator<A>, Accumulator<A>)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n), sized(n)))
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<A: Amount + proptest::arbitrary::Arbitrary> proptest::arbitrary::Arbitrary for Accumulator<A> {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (1..MAX_SIZE).prop_flat_map(sized).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::properties::grow;
+    use proptest::prelude::*;
+
+    mod decimal {
+        use super::*;
+
+        type Cents = Accumulator<Decimal<2>>;
+
+        fn two_cents() -> impl Strategy<Value = (Cents, Cents)> {
+            two()
+        }
+
+        fn three_cents() -> impl Strategy<Value = (Cents, Cents, Cents)> {
+            three()
+        }
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (Cents, Decimal<2>)> {
+            (any::<Cents>(), any::<Decimal<2>>())
+        }
+
+        grow!(two_cents, three_cents, cvrdt_and_update);
+
+        proptest! {
+            #[test]
+            fn display_and_parse_round_trip(x in any::<u64>()) {
+                let x = Decimal::<2>::from_units(x);
+                prop_assert_eq!(x.to_string().parse::<Decimal<2>>(), Ok(x));
+                let y = Decimal::<0>::from_units(x.units());
+                prop_assert_eq!(y.to_string().parse::<Decimal<0>>(), Ok(y));
+            }
+
+            #[test]
+            fn query_is_exact(amounts in prop::collection::vec(any::<Decimal<2>>(), 0..100)) {
+                let mut x: Cents = Accumulator::new((0, vec![Decimal::ZERO; 2]));
+                for &amount in &amounts {
+                    x.add(amount);
+                }
+                let expected: u64 = amounts.iter().map(|x| x.units()).sum();
+                prop_assert_eq!(x.query(&()).units(), expected);
+            }
+        }
+
+        #[test]
+        fn parse_rejects_malformed() {
+            for s in [
+                "", ".", "1.", ".5", "1.2.3", "1,5", "+1", "-1", "1.234", "1e3",
+            ] {
+                assert!(s.parse::<Decimal<2>>().is_err(), "{s}");
+            }
+            assert!("18446744073709551616".parse::<Decimal<0>>().is_err());
+            assert!("184467440737095516.16".parse::<Decimal<2>>().is_err());
+            assert_eq!(
+                "184467440737095516.15".parse(),
+                Ok(Decimal::<2>::from_units(u64::MAX))
+            );
+        }
+    }
+
+    mod float {
+        use super::*;
+
+        fn two_floats() -> impl Strategy<Value = (Accumulator<Float>, Accumulator<Float>)> {
+            two()
+        }
+
+        fn three_floats(
+        ) -> impl Strategy<Value = (Accumulator<Float>, Accumulator<Float>, Accumulator<Float>)>
+        {
+            three()
+        }
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (Accumulator<Float>, Float)> {
+            (any::<Accumulator<Float>>(), any::<Float>())
+        }
+
+        grow!(two_floats, three_floats, cvrdt_and_update);
+
+        #[test]
+        fn totals_saturate_at_infinity() {
+            let max = Float::try_from(f64::MAX).unwrap();
+            let mut x = Accumulator::new((0, vec![Float::ZERO; 2]));
+            x.add(max);
+            x.add(max);
+            assert_eq!(x.query(&()).get(), f64::INFINITY);
+            let mut y = Accumulator::new((1, vec![Float::ZERO; 2]));
+            y.add(max);
+            let z = x.merge(&y);
+            assert_eq!(z.totals[0].get(), f64::INFINITY);
+            assert_eq!(z.query(&()).get(), f64::INFINITY);
+        }
+
+        #[test]
+        fn negative_zero_is_zero() {
+            assert_eq!(Float::try_from(-0.0), Ok(Float::ZERO));
+            assert_eq!(
+                Float::try_from(-0.0).unwrap().get().to_bits(),
+                0.0_f64.to_bits()
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<Accumulator<Decimal<2>>>(), y in any::<Accumulator<Float>>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Accumulator<Decimal<2>>>(&json).unwrap(), x);
+            // JSON has no infinity
+            let finite = |t: &Float| if t.get().is_finite() { *t } else { Float::ZERO };
+            let y = Accumulator { totals: y.totals.iter().map(finite).collect(), ..y };
+            let json = serde_json::to_string(&y).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Accumulator<Float>>(&json).unwrap(), y);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_invalid() {
+        for json in [r#"{"id":1,"totals":[0.5]}"#, r#"{"id":0,"totals":[-0.5]}"#] {
+            assert!(serde_json::from_str::<Accumulator<Float>>(json).is_err());
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! An interactive playground for `CvRDTs`: create named replicas, update and merge them, and watch
+//! what happens to their payloads
+//!
+//! Reads one command per line from standard input (type `help` for a list), so it can be scripted
+//! too:
+//!
+//! ```text
+//! $ printf 'new x gset\nnew y gset\nadd x a\nadd y b\nmerge x y\n' | cvrdt-repl
+//! x = {}
+//! y = {}
+//! x = {"a"}
+//! y = {"b"}
+//! x = {"a", "b"}
+//! ```
+
+#![forbid(unsafe_code)]
+
+use cvrdt_exposition::traits::partial_cmp;
+use cvrdt_exposition::{
+    GCounter, GSet, Grow, LWWRegister, OneWayBoolean, PNCounter, Shrink, TwoPhaseSet,
+};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static HELP: &str = "commands:
+    new NAME gcounter ID SIZE     a grow-only counter
+    new NAME pncounter ID SIZE    a positive-negative counter
+    new NAME gset                 a grow-only set of strings
+    new NAME 2pset                a two-phase set of strings
+    new NAME lww VALUE            a last-writer-wins register of strings
+    new NAME flag                 a one-way boolean
+    add NAME [VALUE]              add to (increment, set, ...) a replica
+    del NAME [VALUE]              delete from (decrement, remove) a replica
+    merge NAME OTHER              merge OTHER into NAME
+    query NAME [VALUE]            query a replica (for sets, whether VALUE is in it)
+    le NAME OTHER                 is NAME <= OTHER?
+    compare NAME OTHER            how do NAME and OTHER compare?
+    show [NAME]                   show one replica, or all of them
+    help                          show this message
+    quit                          leave";
+
+/// A replica of any of our `CvRDTs`, over strings where they hold values
+#[derive(Debug, Clone)]
+enum Replica {
+    GCounter(GCounter),
+    PNCounter(PNCounter),
+    GSet(GSet<String>),
+    TwoPhaseSet(TwoPhaseSet<String>),
+    LWWRegister(LWWRegister<String>),
+    OneWayBoolean(OneWayBoolean),
+}
+
+/// Sets, sorted so that the output is deterministic
+fn sorted(xs: &HashSet<String>) -> BTreeSet<&String> {
+    xs.iter().collect()
+}
+
+fn counter_args(args: &[&str]) -> Result<(usize, usize), String> {
+    let [id, size] = args else {
+        return Err("expected ID SIZE".to_string());
+    };
+    let id = id.parse().map_err(|e| format!("invalid ID {id:?} ({e})"))?;
+    let size = size
+        .parse()
+        .map_err(|e| format!("invalid SIZE {size:?} ({e})"))?;
+    if id >= size {
+        return Err("ID must be less than SIZE".to_string());
+    }
+    Ok((id, size))
+}
+
+fn no_value(value: Option<&str>) -> Result<(), String> {
+    value.map_or(Ok(()), |v| Err(format!("unexpected value {v:?}")))
+}
+
+fn value(value: Option<&str>) -> Result<String, String> {
+    value
+        .map(str::to_string)
+        .ok_or_else(|| "expected a VALUE".to_string())
+}
+
+impl Replica {
+    fn new(kind: &str, args: &[&str]) -> Result<Self, String> {
+        match (kind, args) {
+            ("gcounter", _) => {
+                let (id, size) = counter_args(args)?;
+                Ok(Replica::GCounter(GCounter::new((id, vec![0; size]))))
+            }
+            ("pncounter", _) => {
+                let (id, size) = counter_args(args)?;
+                Ok(Replica::PNCounter(PNCounter::new((
+                    id,
+                    vec![0; size],
+                    vec![0; size],
+                ))))
+            }
+            ("gset", []) => Ok(Replica::GSet(GSet::new(HashSet::new()))),
+            ("2pset", []) => Ok(Replica::TwoPhaseSet(TwoPhaseSet::new((
+                HashSet::new(),
+                HashSet::new(),
+            )))),
+            ("lww", [value]) => Ok(Replica::LWWRegister(LWWRegister::new((
+                (*value).to_string(),
+                SystemTime::now(),
+            )))),
+            ("flag", []) => Ok(Replica::OneWayBoolean(OneWayBoolean::new(false))),
+            ("gset" | "2pset" | "lww" | "flag", _) => {
+                Err(format!("wrong arguments for {kind}; try `help`"))
+            }
+            _ => Err(format!("unknown type {kind:?}; try `help`")),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Replica::GCounter(_) => "gcounter",
+            Replica::PNCounter(_) => "pncounter",
+            Replica::GSet(_) => "gset",
+            Replica::TwoPhaseSet(_) => "2pset",
+            Replica::LWWRegister(_) => "lww",
+            Replica::OneWayBoolean(_) => "flag",
+        }
+    }
+
+    fn payload(&self) -> String {
+        match self {
+            Replica::GCounter(x) => format!("{:?}", x.payload()),
+            Replica::PNCounter(x) => format!("{:?}", x.payload()),
+            Replica::GSet(x) => format!("{:?}", sorted(&x.values)),
+            Replica::TwoPhaseSet(x) => {
+                format!("({:?}, {:?})", sorted(&x.added), sorted(&x.removed))
+            }
+            Replica::LWWRegister(x) => {
+                let since = x.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+                format!("({:?}, {:?} since the epoch)", x.value, since)
+            }
+            Replica::OneWayBoolean(x) => format!("{:?}", x.payload()),
+        }
+    }
+
+    fn add(&mut self, v: Option<&str>) -> Result<(), String> {
+        match self {
+            Replica::GCounter(x) => no_value(v).map(|()| x.add(())),
+            Replica::PNCounter(x) => no_value(v).map(|()| x.add(())),
+            Replica::GSet(x) => value(v).map(|v| x.add(v)),
+            Replica::TwoPhaseSet(x) => value(v).map(|v| x.add(v)),
+            Replica::LWWRegister(x) => value(v).map(|v| x.add(v)),
+            Replica::OneWayBoolean(x) => no_value(v).map(|()| x.add(())),
+        }
+    }
+
+    fn del(&mut self, v: Option<&str>) -> Result<(), String> {
+        match self {
+            Replica::PNCounter(x) => no_value(v).map(|()| x.del(())),
+            Replica::TwoPhaseSet(x) => {
+                let v = value(v)?;
+                if !x.query(&v) {
+                    return Err(format!("{v:?} isn't in the set"));
+                }
+                x.del(v);
+                Ok(())
+            }
+            _ => Err(format!("a {} can't delete", self.kind())),
+        }
+    }
+
+    fn query(&self, v: Option<&str>) -> Result<String, String> {
+        match self {
+            Replica::GCounter(x) => no_value(v).map(|()| format!("{}", x.query(&()))),
+            Replica::PNCounter(x) => {
+                no_value(v)?;
+                // concurrent deletions (or our own) can take the count below zero
+                x.checked_query()
+                    .map(|n| format!("{n}"))
+                    .ok_or_else(|| "the count is negative".to_string())
+            }
+            Replica::GSet(x) => value(v).map(|v| format!("{}", x.query(&v))),
+            Replica::TwoPhaseSet(x) => value(v).map(|v| format!("{}", x.query(&v))),
+            Replica::LWWRegister(x) => no_value(v).map(|()| format!("{:?}", x.query(&()))),
+            Replica::OneWayBoolean(x) => no_value(v).map(|()| format!("{}", x.query(&()))),
+        }
+    }
+
+    /// Check that `self` and `other` can be merged or compared
+    fn compatible(&self, other: &Self) -> Result<(), String> {
+        let lengths = match (self, other) {
+            (Replica::GCounter(x), Replica::GCounter(y)) => (x.counts.len(), y.counts.len()),
+            (Replica::PNCounter(x), Replica::PNCounter(y)) => (x.positive.len(), y.positive.len()),
+            _ if self.kind() == other.kind() => return Ok(()),
+            _ => {
+                return Err(format!(
+                    "can't mix a {} and a {}",
+                    self.kind(),
+                    other.kind()
+                ))
+            }
+        };
+        if lengths.0 == lengths.1 {
+            Ok(())
+        } else {
+            Err(format!(
+                "counters have different sizes ({} and {})",
+                lengths.0, lengths.1
+            ))
+        }
+    }
+
+    fn merge(&self, other: &Self) -> Result<Self, String> {
+        self.compatible(other)?;
+        Ok(match (self, other) {
+            (Replica::GCounter(x), Replica::GCounter(y)) => Replica::GCounter(x.merge(y)),
+            (Replica::PNCounter(x), Replica::PNCounter(y)) => Replica::PNCounter(x.merge(y)),
+            (Replica::GSet(x), Replica::GSet(y)) => Replica::GSet(x.merge(y)),
+            (Replica::TwoPhaseSet(x), Replica::TwoPhaseSet(y)) => Replica::TwoPhaseSet(x.merge(y)),
+            (Replica::LWWRegister(x), Replica::LWWRegister(y)) => Replica::LWWRegister(x.merge(y)),
+            (Replica::OneWayBoolean(x), Replica::OneWayBoolean(y)) => {
+                Replica::OneWayBoolean(x.merge(y))
+            }
+            _ => unreachable!("checked by `compatible`"),
+        })
+    }
+
+    fn compare(&self, other: &Self) -> Result<Option<Ordering>, String> {
+        self.compatible(other)?;
+        Ok(match (self, other) {
+            (Replica::GCounter(x), Replica::GCounter(y)) => partial_cmp(x, y),
+            (Replica::PNCounter(x), Replica::PNCounter(y)) => partial_cmp(x, y),
+            (Replica::GSet(x), Replica::GSet(y)) => partial_cmp(x, y),
+            (Replica::TwoPhaseSet(x), Replica::TwoPhaseSet(y)) => partial_cmp(x, y),
+            (Replica::LWWRegister(x), Replica::LWWRegister(y)) => partial_cmp(x, y),
+            (Replica::OneWayBoolean(x), Replica::OneWayBoolean(y)) => partial_cmp(x, y),
+            _ => unreachable!("checked by `compatible`"),
+        })
+    }
+}
+
+/// Every replica we've created, by name
+#[derive(Debug, Default)]
+struct Repl {
+    replicas: BTreeMap<String, Replica>,
+}
+
+/// Whether to keep reading commands
+enum Next {
+    Continue,
+    Quit,
+}
+
+impl Repl {
+    fn get(&self, name: &str) -> Result<&Replica, String> {
+        self.replicas
+            .get(name)
+            .ok_or_else(|| format!("no replica named {name:?}"))
+    }
+
+    fn get_mut(&mut self, name: &str) -> Result<&mut Replica, String> {
+        self.replicas
+            .get_mut(name)
+            .ok_or_else(|| format!("no replica named {name:?}"))
+    }
+
+    fn show(&self, name: &str) -> Result<String, String> {
+        Ok(format!("{name} = {}", self.get(name)?.payload()))
+    }
+
+    /// Run one line of input, returning what to print
+    fn run(&mut self, line: &str) -> Result<(Option<String>, Next), String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let output = match words.as_slice() {
+            [] => None,
+            ["quit" | "exit"] => return Ok((None, Next::Quit)),
+            ["help"] => Some(HELP.to_string()),
+            ["new", name, kind, args @ ..] => {
+                let replica = Replica::new(kind, args)?;
+                self.replicas.insert((*name).to_string(), replica);
+                Some(self.show(name)?)
+            }
+            ["add", name, v @ ..] if v.len() <= 1 => {
+                self.get_mut(name)?.add(v.first().copied())?;
+                Some(self.show(name)?)
+            }
+            ["del", name, v @ ..] if v.len() <= 1 => {
+                self.get_mut(name)?.del(v.first().copied())?;
+                Some(self.show(name)?)
+            }
+            ["merge", name, other] => {
+                let merged = self.get(name)?.merge(self.get(other)?)?;
+                self.replicas.insert((*name).to_string(), merged);
+                Some(self.show(name)?)
+            }
+            ["query", name, v @ ..] if v.len() <= 1 => {
+                Some(self.get(name)?.query(v.first().copied())?)
+            }
+            ["le", name, other] => {
+                let ordering = self.get(name)?.compare(self.get(other)?)?;
+                Some(format!(
+                    "{}",
+                    matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+                ))
+            }
+            ["compare", name, other] => Some(
+                match self.get(name)?.compare(self.get(other)?)? {
+                    Some(Ordering::Less) => "less",
+                    Some(Ordering::Equal) => "equal",
+                    Some(Ordering::Greater) => "greater",
+                    None => "incomparable",
+                }
+                .to_string(),
+            ),
+            ["show", name] => Some(self.show(name)?),
+            ["show"] => Some(
+                self.replicas
+                    .iter()
+                    .map(|(name, r)| format!("{name}: {} = {}", r.kind(), r.payload()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            [command, ..] => return Err(format!("can't parse {command:?} command; try `help`")),
+        };
+        Ok((output.filter(|s| !s.is_empty()), Next::Continue))
+    }
+}
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut stdout = io::stdout();
+    let mut repl = Repl::default();
+    if interactive {
+        println!("cvrdt-repl: type `help` for a list of commands");
+    }
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("> ");
+            stdout.flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        match repl.run(&line) {
+            Ok((output, next)) => {
+                if let Some(output) = output {
+                    println!("{output}");
+                }
+                if let Next::Quit = next {
+                    break;
+                }
+            }
+            Err(e) => println!("error: {e}"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,425 @@
+//! Inspect, merge, and compare `CvRDT` states saved with the
+//! [`wire`](../cvrdt_exposition/wire/index.html) format
+//!
+//! ```text
+//! cvrdt show FILE [QUERY]         print the type, payload, and query value of a state
+//! cvrdt merge OUTPUT INPUT...     merge states into one, written to OUTPUT
+//! cvrdt compare FILE FILE         compare two states by `le`
+//! ```
+//!
+//! The type of each file is read from its header. `QUERY` is needed for types whose queries take
+//! an argument: an element for `GSet`/`TwoPhaseSet`/`CollectableTwoPhaseSet`, a replica for
+//! `VersionVector`, `replica:counter` for `DottedVersionVector`, and a quantile between 0 and 1
+//! for `Histogram`.
+//!
+//! Exits with 0 on success, 1 if `compare` finds the states incomparable, and 2 on any error.
+
+#![forbid(unsafe_code)]
+
+use cvrdt_exposition::accumulator::{Amount, Decimal, Float};
+use cvrdt_exposition::causality::{Dot, DottedVersionVector, VersionVector};
+use cvrdt_exposition::count::Count;
+use cvrdt_exposition::mean_counter::Mean;
+use cvrdt_exposition::traits::partial_cmp;
+use cvrdt_exposition::wire::{self, Element, Wire};
+use cvrdt_exposition::{
+    Accumulator, CollectableTwoPhaseSet, GCounter, GSet, Grow, Histogram, LWWRegister, MeanCounter,
+    OneWayBoolean, PNCounter, TwoPhaseSet,
+};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::fs;
+use std::process::ExitCode;
+
+static USAGE: &str = "usage:
+    cvrdt show FILE [QUERY]
+    cvrdt merge OUTPUT INPUT...
+    cvrdt compare FILE FILE";
+
+/// Element types that we can name, and parse from the command line
+trait Arg: Sized {
+    /// The name of this type
+    const NAME: &'static str;
+
+    /// Parse a command line argument
+    fn parse(s: &str) -> Result<Self, String>;
+
+    /// The query to use when none is given, if there's only one possibility
+    fn implicit() -> Option<Self> {
+        None
+    }
+}
+
+impl Arg for () {
+    const NAME: &'static str = "()";
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "" | "()" => Ok(()),
+            _ => Err(format!("expected (), found {s:?}")),
+        }
+    }
+    fn implicit() -> Option<Self> {
+        Some(())
+    }
+}
+
+macro_rules! arg {
+    ($($t:ty),*) => {
+        $(
+            impl Arg for $t {
+                const NAME: &'static str = stringify!($t);
+                fn parse(s: &str) -> Result<Self, String> {
+                    s.parse()
+                        .map_err(|e| format!("invalid {}: {s:?} ({e})", Self::NAME))
+                }
+            }
+        )*
+    };
+}
+
+arg!(bool, u8, u16, u32, u64, u128, i8, i16, i32, i64, f64, char, String);
+
+impl<R: Arg> Arg for Dot<R> {
+    const NAME: &'static str = "Dot";
+    fn parse(s: &str) -> Result<Self, String> {
+        let (replica, counter) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected replica:counter, found {s:?}"))?;
+        Ok(Dot {
+            replica: R::parse(replica)?,
+            counter: u64::parse(counter)?,
+        })
+    }
+}
+
+/// `CvRDTs` that we can name and query
+trait Named: Grow {
+    /// The name of this type, including its element type
+    fn name() -> String;
+
+    /// The value of this `CvRDT` for `query`, or an error where `query` would panic
+    fn value(&self, query: &Self::Query) -> Result<Self::Value, String> {
+        Ok(self.query(query))
+    }
+}
+
+macro_rules! named {
+    ($($t:ident),*) => {
+        $(
+            impl Named for $t {
+                fn name() -> String {
+                    stringify!($t).to_string()
+                }
+            }
+        )*
+    };
+    ($($t:ident<X: $($bound:path),*>),*) => {
+        $(
+            impl<X: Arg $(+ $bound)*> Named for $t<X> {
+                fn name() -> String {
+                    format!("{}<{}>", stringify!($t), X::NAME)
+                }
+            }
+        )*
+    };
+}
+
+named!(OneWayBoolean);
+named!(
+    GCounter<X: Count>,
+    GSet<X: Clone, Eq, std::hash::Hash>,
+    TwoPhaseSet<X: Clone, Eq, std::hash::Hash>,
+    LWWRegister<X: Clone, Eq>,
+    VersionVector<X: Clone, Ord>,
+    DottedVersionVector<X: Clone, Ord>
+);
+
+impl<R: Arg + Clone + Ord, X: Arg + Clone + Eq + std::hash::Hash> Named
+    for CollectableTwoPhaseSet<R, X>
+{
+    fn name() -> String {
+        format!("CollectableTwoPhaseSet<{}, {}>", R::NAME, X::NAME)
+    }
+}
+
+impl<X: Arg + Clone + Ord> Named for Histogram<X> {
+    fn name() -> String {
+        format!("Histogram<{}>", X::NAME)
+    }
+    fn value(&self, query: &f64) -> Result<Self::Value, String> {
+        if (0.0..=1.0).contains(query) {
+            Ok(self.query(query))
+        } else {
+            Err(format!("quantile {query} is not between 0 and 1"))
+        }
+    }
+}
+
+/// Amounts that we can name
+trait AmountName: Amount {
+    /// The name of this type
+    fn name() -> String;
+}
+
+impl AmountName for Float {
+    fn name() -> String {
+        "Float".to_string()
+    }
+}
+
+impl<const PLACES: u32> AmountName for Decimal<PLACES> {
+    fn name() -> String {
+        format!("Decimal<{PLACES}>")
+    }
+}
+
+/// The sum of `totals`, or an error where `query` would panic on overflow
+fn total<A: Amount>(totals: &[A]) -> Result<A, String> {
+    totals
+        .iter()
+        .try_fold(A::ZERO, |sum, &x| sum.checked_add(x))
+        .ok_or_else(|| "value overflows".to_string())
+}
+
+impl<A: AmountName> Named for Accumulator<A> {
+    fn name() -> String {
+        format!("Accumulator<{}>", A::name())
+    }
+    fn value(&self, _query: &()) -> Result<A, String> {
+        total(&self.totals)
+    }
+}
+
+impl<A: AmountName> Named for MeanCounter<A> {
+    fn name() -> String {
+        format!("MeanCounter<{}>", A::name())
+    }
+    fn value(&self, _query: &()) -> Result<Mean<A>, String> {
+        Ok(Mean {
+            sum: total(&self.sum.totals)?,
+            count: self.count.query(&()),
+        })
+    }
+}
+
+impl<T: Arg + Count> Named for PNCounter<T> {
+    fn name() -> String {
+        format!("PNCounter<{}>", T::NAME)
+    }
+    fn value(&self, _query: &()) -> Result<T, String> {
+        self.checked_query()
+            .ok_or_else(|| "value is negative or overflows".to_string())
+    }
+}
+
+/// A parsed command line
+enum Command {
+    Show { file: String, query: Option<String> },
+    Merge { output: String, inputs: Vec<String> },
+    Compare { x: String, y: String },
+}
+
+impl Command {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        match args {
+            [cmd, file] if cmd == "show" => Ok(Command::Show {
+                file: file.clone(),
+                query: None,
+            }),
+            [cmd, file, query] if cmd == "show" => Ok(Command::Show {
+                file: file.clone(),
+                query: Some(query.clone()),
+            }),
+            [cmd, output, inputs @ ..] if cmd == "merge" && !inputs.is_empty() => {
+                Ok(Command::Merge {
+                    output: output.clone(),
+                    inputs: inputs.to_vec(),
+                })
+            }
+            [cmd, x, y] if cmd == "compare" => Ok(Command::Compare {
+                x: x.clone(),
+                y: y.clone(),
+            }),
+            _ => Err(USAGE.to_string()),
+        }
+    }
+
+    /// The file whose header decides which type we decode
+    fn first_file(&self) -> &str {
+        match self {
+            Command::Show { file, .. } => file,
+            Command::Merge { inputs, .. } => &inputs[0],
+            Command::Compare { x, .. } => x,
+        }
+    }
+}
+
+fn load<T: Wire>(path: &str) -> Result<T, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    wire::decode(&bytes).map_err(|e| format!("{path}: {e}"))
+}
+
+/// Load the state in `path`, checking that it can be merged with or compared to `first`
+fn load_compatible<T: Wire + Grow>(first: &T, path: &str) -> Result<T, String> {
+    let x = load(path)?;
+    if first.compatible(&x) {
+        Ok(x)
+    } else {
+        Err(format!("{path}: incompatible with the first state"))
+    }
+}
+
+/// Run `cmd` on states of type `T`
+fn run<T>(cmd: &Command) -> Result<ExitCode, String>
+where
+    T: Wire + PartialEq + Named,
+    T::Payload: Debug,
+    T::Query: Arg,
+    T::Value: Debug,
+{
+    match cmd {
+        Command::Show { file, query } => {
+            let x: T = load(file)?;
+            println!("type: {}", T::name());
+            println!("payload: {:?}", x.payload());
+            let query = match query {
+                Some(q) => Some(T::Query::parse(q)?),
+                None => T::Query::implicit(),
+            };
+            if let Some(q) = query {
+                println!("value: {:?}", x.value(&q)?);
+            }
+        }
+        Command::Merge { output, inputs } => {
+            let mut merged: T = load(&inputs[0])?;
+            for input in &inputs[1..] {
+                merged = merged.merge(&load_compatible(&merged, input)?);
+            }
+            fs::write(output, wire::encode(&merged)).map_err(|e| format!("{output}: {e}"))?;
+            println!("payload: {:?}", merged.payload());
+        }
+        Command::Compare { x, y } => {
+            let x: T = load(x)?;
+            let ordering = partial_cmp(&x, &load_compatible(&x, y)?);
+            println!(
+                "{}",
+                match ordering {
+                    Some(Ordering::Less) => "less",
+                    Some(Ordering::Equal) => "equal",
+                    Some(Ordering::Greater) => "greater",
+                    None => "incomparable",
+                }
+            );
+            if ordering.is_none() {
+                return Ok(ExitCode::from(1));
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Evaluate `$run` with `$x` standing for whichever of the listed types is tagged `$element`
+macro_rules! with_type {
+    ($what:literal, $element:expr, $x:ident => $run:expr; $($t:ty),*) => {
+        match $element {
+            $(e if e == <$t as Element>::TAG => {
+                type $x = $t;
+                $run
+            })*
+            e => Err(format!("unknown {} tag {e}", $what)),
+        }
+    };
+}
+
+/// Evaluate `$run` with `$t` standing for the count type tagged `$element`
+macro_rules! with_count {
+    ($element:expr, $t:ident => $run:expr) => {
+        with_type!("count", $element, $t => $run; u8, u16, u32, u64, u128)
+    };
+}
+
+/// Evaluate `$run` with `$x` standing for the element type tagged `$element`
+macro_rules! with_element {
+    ($element:expr, $x:ident => $run:expr) => {
+        with_type!(
+            "element", $element, $x => $run;
+            (), bool, u8, u16, u32, u64, i8, i16, i32, i64, char, String
+        )
+    };
+}
+
+/// Evaluate `$run` with `$a` standing for the amount type tagged `$element`: a `Float`, or a
+/// `Decimal` with any number of places that it supports
+macro_rules! with_amount {
+    ($element:expr, $a:ident => $run:expr) => {
+        with_type!(
+            "amount", $element, $a => $run;
+            Float, Decimal<0>, Decimal<1>, Decimal<2>, Decimal<3>, Decimal<4>, Decimal<5>,
+            Decimal<6>, Decimal<7>, Decimal<8>, Decimal<9>, Decimal<10>, Decimal<11>, Decimal<12>,
+            Decimal<13>, Decimal<14>, Decimal<15>, Decimal<16>, Decimal<17>, Decimal<18>,
+            Decimal<19>
+        )
+    };
+}
+
+/// Run `cmd` on a `CollectableTwoPhaseSet` with replicas of type `R`, and elements of the type
+/// tagged `element`
+fn run_collectable<R>(element: u8, cmd: &Command) -> Result<ExitCode, String>
+where
+    R: Arg + Element + Clone + Ord + Debug,
+{
+    with_element!(element, X => run::<CollectableTwoPhaseSet<R, X>>(cmd))
+}
+
+fn dispatch(cmd: &Command) -> Result<ExitCode, String> {
+    let path = cmd.first_file();
+    let bytes = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    let (kind, element) = wire::tags(&bytes).map_err(|e| format!("{path}: {e}"))?;
+    match kind {
+        k if k == GCounter::<u64>::KIND => with_count!(element, T => run::<GCounter<T>>(cmd)),
+        k if k == PNCounter::<u64>::KIND => with_count!(element, T => run::<PNCounter<T>>(cmd)),
+        k if k == OneWayBoolean::KIND => run::<OneWayBoolean>(cmd),
+        k if k == GSet::<()>::KIND => with_element!(element, X => run::<GSet<X>>(cmd)),
+        k if k == TwoPhaseSet::<()>::KIND => {
+            with_element!(element, X => run::<TwoPhaseSet<X>>(cmd))
+        }
+        k if k == LWWRegister::<()>::KIND => {
+            with_element!(element, X => run::<LWWRegister<X>>(cmd))
+        }
+        k if k == VersionVector::<()>::KIND => {
+            with_element!(element, R => run::<VersionVector<R>>(cmd))
+        }
+        k if k == DottedVersionVector::<()>::KIND => {
+            with_element!(element, R => run::<DottedVersionVector<R>>(cmd))
+        }
+        k if k == CollectableTwoPhaseSet::<(), ()>::KIND => {
+            with_element!(element >> 4, R => run_collectable::<R>(element & 0xf, cmd))
+        }
+        k if k == Accumulator::<Float>::KIND => {
+            with_amount!(element, A => run::<Accumulator<A>>(cmd))
+        }
+        k if k == MeanCounter::<Float>::KIND => {
+            with_amount!(element, A => run::<MeanCounter<A>>(cmd))
+        }
+        k if k == Histogram::<()>::KIND => with_element!(element, X => run::<Histogram<X>>(cmd)),
+        k => Err(format!("{path}: unknown kind tag {k}")),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(
+        args.first().map(String::as_str),
+        Some("help" | "-h" | "--help")
+    ) {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    match Command::parse(&args).and_then(|cmd| dispatch(&cmd)) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("cvrdt: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
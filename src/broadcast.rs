@@ -0,0 +1,281 @@
+use crate::causality::VersionVector;
+use crate::traits::Grow;
+
+/// An operation tagged with its sender and the sender's version vector at the time it was sent
+///
+/// The `clock` counts the message itself, so `clock.get(&sender)` is this message's sequence
+/// number among all messages from `sender`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "R: serde::Serialize, Op: serde::Serialize",
+        deserialize = "R: serde::Deserialize<'de>, Op: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Message<R: Clone + Ord, Op> {
+    /// The replica that broadcast this message
+    pub sender: R,
+    /// Every message the sender had delivered (or sent) before this one, plus this one
+    pub clock: VersionVector<R>,
+    /// The operation itself
+    pub op: Op,
+}
+
+/// A causal broadcast buffer: tags outgoing operations with version vectors, and delivers incoming
+/// ones in causal order
+///
+/// Operation-based CRDTs (see the [`operation` module](../operation/index.html)) need every
+/// operation to be delivered exactly once at every replica, after every operation that its source
+/// had already seen. `CausalBroadcast` provides the "exactly once" and "causal order" parts on top
+/// of any transport that eventually delivers every message at least once, in any order; it holds
+/// back messages that arrive before their dependencies, and drops duplicates.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::broadcast::CausalBroadcast;
+/// let mut a = CausalBroadcast::new('a');
+/// let mut b = CausalBroadcast::new('b');
+/// let mut c = CausalBroadcast::new('c');
+/// let first = a.broadcast("first");
+/// assert_eq!(b.receive(first.clone()), vec!["first"]);
+/// let second = b.broadcast("second");
+/// // `c` hears about `second` before `first`, so holds it back
+/// assert!(c.receive(second.clone()).is_empty());
+/// assert_eq!(c.pending(), 1);
+/// assert_eq!(c.receive(first.clone()), vec!["first", "second"]);
+/// // duplicates are dropped
+/// assert!(c.receive(first).is_empty());
+/// assert!(c.receive(second).is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CausalBroadcast<R: Clone + Ord, Op> {
+    id: R,
+    clock: VersionVector<R>,
+    held: Vec<Message<R, Op>>,
+}
+
+impl<R: Clone + Ord, Op: Clone> CausalBroadcast<R, Op> {
+    /// Create a buffer for the replica `id`, which hasn't sent or delivered anything yet
+    #[must_use]
+    pub fn new(id: R) -> Self {
+        CausalBroadcast {
+            id,
+            clock: VersionVector::default(),
+            held: Vec::new(),
+        }
+    }
+
+    /// This replica's identifier
+    #[must_use]
+    pub fn id(&self) -> &R {
+        &self.id
+    }
+
+    /// Every message this replica has sent or delivered
+    #[must_use]
+    pub fn clock(&self) -> &VersionVector<R> {
+        &self.clock
+    }
+
+    /// How many received messages are waiting for their dependencies
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Tag a local operation for broadcast
+    ///
+    /// The operation counts as delivered here, so apply its effect locally before (or
+    /// immediately after) calling this.
+    ///
+    /// # Parameters
+    ///
+    /// - a mutably borrowed reference to `self`
+    /// - the operation to send
+    ///
+    /// # Returns
+    ///
+    /// The message to send to every other replica
+    pub fn broadcast(&mut self, op: Op) -> Message<R, Op> {
+        self.clock.increment(self.id.clone());
+        Message {
+            sender: self.id.clone(),
+            clock: self.clock.clone(),
+            op,
+        }
+    }
+
+    /// Is this message one we've already delivered or are already holding?
+    fn seen(&self, msg: &Message<R, Op>) -> bool {
+        let n = msg.clock.get(&msg.sender);
+        n <= self.clock.get(&msg.sender)
+            || self
+                .held
+                .iter()
+                .any(|h| h.sender == msg.sender && h.clock.get(&h.sender) == n)
+    }
+
+    /// Has every message this one depends on been delivered?
+    fn deliverable(&self, msg: &Message<R, Op>) -> bool {
+        msg.clock.get(&msg.sender) == self.clock.get(&msg.sender) + 1
+            && msg
+                .clock
+                .counts
+                .iter()
+                .all(|(r, &n)| *r == msg.sender || n <= self.clock.get(r))
+    }
+
+    /// Receive a message from the transport
+    ///
+    /// # Parameters
+    ///
+    /// - a mutably borrowed reference to `self`
+    /// - a message, which may be a duplicate or arrive before its dependencies
+    ///
+    /// # Returns
+    ///
+    /// Every operation that can now be delivered, in causal order: possibly none (if `msg` is a
+    /// duplicate or must wait for its dependencies), possibly several (if `msg` was the missing
+    /// dependency of held-back messages)
+    pub fn receive(&mut self, msg: Message<R, Op>) -> Vec<Op> {
+        if self.seen(&msg) {
+            return Vec::new();
+        }
+        self.held.push(msg);
+        let mut delivered = Vec::new();
+        while let Some(i) = self.held.iter().position(|m| self.deliverable(m)) {
+            let msg = self.held.swap_remove(i);
+            self.clock = self.clock.merge(&msg.clock);
+            delivered.push(msg.op);
+        }
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::causality::Dot;
+    use crate::{OpTwoPhaseSet, Operation, TwoPhaseSetOp};
+    use proptest::prelude::*;
+    use std::collections::{BTreeMap, HashSet};
+
+    static REPLICAS: u8 = 4;
+
+    type Messages = Vec<Message<u8, Dot<u8>>>;
+
+    /// Replicas take turns either broadcasting a new operation or catching up on every message
+    /// sent so far, building up causal dependencies between messages. Each operation is the
+    /// `Dot` of its message, so we can look its clock up later.
+    fn history(events: &[(u8, bool)]) -> Messages {
+        let mut replicas: Vec<_> = (0..REPLICAS).map(CausalBroadcast::new).collect();
+        let mut sent = Vec::new();
+        for &(r, send) in events {
+            let replica = &mut replicas[usize::from(r)];
+            if send {
+                let dot = Dot {
+                    replica: r,
+                    counter: replica.clock().get(&r) + 1,
+                };
+                sent.push(replica.broadcast(dot));
+            } else {
+                for msg in &sent {
+                    replica.receive(msg.clone());
+                }
+            }
+        }
+        sent
+    }
+
+    fn events() -> impl Strategy<Value = Vec<(u8, bool)>> {
+        prop::collection::vec((0..REPLICAS, any::<bool>()), 0..64)
+    }
+
+    /// Every message at least once, with some duplicates, in an arbitrary order
+    fn scramble(sent: Messages) -> impl Strategy<Value = Messages> {
+        let n = sent.len();
+        prop::collection::vec(any::<prop::sample::Index>(), 0..=n)
+            .prop_map(move |dupes| {
+                let mut stream = sent.clone();
+                stream.extend(dupes.iter().map(|i| sent[i.index(sent.len())].clone()));
+                stream
+            })
+            .prop_shuffle()
+    }
+
+    fn sent_and_stream() -> impl Strategy<Value = (Messages, Messages)> {
+        events()
+            .prop_map(|e| history(&e))
+            .prop_filter("need something to send", |sent| !sent.is_empty())
+            .prop_flat_map(|sent| (Just(sent.clone()), scramble(sent)))
+    }
+
+    proptest! {
+        #[test]
+        fn delivers_everything_exactly_once((sent, stream) in sent_and_stream()) {
+            let mut observer = CausalBroadcast::new(REPLICAS);
+            let delivered: Vec<_> = stream.into_iter().flat_map(|m| observer.receive(m)).collect();
+            prop_assert_eq!(delivered.len(), sent.len());
+            let unique: HashSet<_> = delivered.iter().collect();
+            prop_assert_eq!(unique.len(), sent.len());
+            prop_assert_eq!(observer.pending(), 0);
+        }
+
+        #[test]
+        fn delivers_in_causal_order((sent, stream) in sent_and_stream()) {
+            let clocks: BTreeMap<_, _> = sent.iter().map(|m| (m.op.clone(), m.clock.clone())).collect();
+            let mut observer = CausalBroadcast::new(REPLICAS);
+            let delivered: Vec<_> = stream.into_iter().flat_map(|m| observer.receive(m)).collect();
+            for (i, later) in delivered.iter().enumerate() {
+                for earlier in &delivered[i + 1..] {
+                    // nothing delivered after `later` may have happened before it
+                    prop_assert!(!clocks[earlier].happened_before(&clocks[later]));
+                }
+            }
+        }
+
+        #[test]
+        fn replicas_converge((sent, stream) in sent_and_stream()) {
+            let mut observer = CausalBroadcast::new(REPLICAS);
+            for m in stream {
+                observer.receive(m);
+            }
+            let everything = sent.iter().fold(VersionVector::default(), |acc, m| acc.merge(&m.clock));
+            prop_assert_eq!(observer.clock(), &everything);
+        }
+    }
+
+    #[test]
+    fn two_phase_set_removes_wait_for_adds() {
+        let empty = || OpTwoPhaseSet::new((HashSet::new(), HashSet::new()));
+        let (mut a, mut b, mut c) = (empty(), empty(), empty());
+        let (mut ca, mut cb, mut cc) = (
+            CausalBroadcast::new('a'),
+            CausalBroadcast::new('b'),
+            CausalBroadcast::new('c'),
+        );
+        let add = ca.broadcast(a.update(TwoPhaseSetOp::Add("x")));
+        for op in cb.receive(add.clone()) {
+            b.effect(&op);
+        }
+        let remove = cb.broadcast(b.update(TwoPhaseSetOp::Remove("x")));
+        // `c` receives the remove first, and must not apply it until it has seen the add
+        assert!(cc.receive(remove.clone()).is_empty());
+        for op in cc
+            .receive(add)
+            .into_iter()
+            .chain(cc.receive(remove.clone()))
+        {
+            c.effect(&op);
+        }
+        for op in ca.receive(remove) {
+            a.effect(&op);
+        }
+        assert_eq!(a.payload(), b.payload());
+        assert_eq!(b.payload(), c.payload());
+        assert!(c.added.contains("x") && c.removed.contains("x"));
+    }
+}
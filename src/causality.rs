@@ -0,0 +1,679 @@
+use crate::traits::Grow;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A single event: the `counter`-th event at `replica`
+///
+/// Dots are numbered from 1, so `Dot { replica, counter: 1 }` is the first event at `replica`.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::causality::{Dot, VersionVector};
+/// use cvrdt_exposition::Grow;
+/// let mut v = VersionVector::new(Default::default());
+/// assert_eq!(v.increment('a'), Dot { replica: 'a', counter: 1 });
+/// assert_eq!(v.increment('a'), Dot { replica: 'a', counter: 2 });
+/// assert!(v.contains(&Dot { replica: 'a', counter: 1 }));
+/// assert!(!v.contains(&Dot { replica: 'b', counter: 1 }));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dot<R> {
+    /// The replica at which this event occurred
+    pub replica: R,
+    /// The position of this event among all events at `replica`, starting from 1
+    pub counter: u64,
+}
+
+/// A version vector: for each replica, how many of its events we've seen
+///
+/// A version vector summarizes a causal history made up of contiguous runs of events per replica,
+/// e.g. `{a: 2, b: 1}` is the history containing the dots `(a, 1)`, `(a, 2)`, and `(b, 1)`.
+/// Replicas that are missing from `counts` have had no events seen.
+///
+/// Note that a [`GCounter`](../g_counter/struct.GCounter.html) is a version vector keyed by
+/// replica index (plus an `id`), and the `Grow` implementation here is the same: adding increments
+/// a replica's count, and merging takes the pointwise maximum.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::causality::VersionVector;
+/// use cvrdt_exposition::Grow;
+/// let mut x = VersionVector::new(Default::default());
+/// x.add("a");
+/// let mut y = x.clone();
+/// y.add("b");
+/// assert!(x.happened_before(&y));
+/// x.add("a");
+/// assert!(x.concurrent(&y));
+/// let z = x.merge(&y);
+/// assert_eq!(z.query(&"a"), 2);
+/// assert_eq!(z.query(&"b"), 1);
+/// assert!(x.happened_before(&z) && y.happened_before(&z));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        from = "UncheckedVersionVector<R>",
+        bound(deserialize = "R: serde::Deserialize<'de>")
+    )
+)]
+pub struct VersionVector<R: Clone + Ord> {
+    /// The number of events seen from each replica; never zero
+    pub counts: BTreeMap<R, u64>,
+}
+
+impl<R: Clone + Ord> VersionVector<R> {
+    /// How many events from `replica` this version vector has seen
+    #[must_use]
+    pub fn get(&self, replica: &R) -> u64 {
+        self.counts.get(replica).copied().unwrap_or(0)
+    }
+
+    /// Record a new event at `replica`
+    ///
+    /// # Returns
+    ///
+    /// The [`Dot`](struct.Dot.html) for the new event
+    ///
+    /// # Panics
+    ///
+    /// If `replica` has already seen `u64::MAX` events
+    pub fn increment(&mut self, replica: R) -> Dot<R> {
+        let counter = self.counts.entry(replica.clone()).or_insert(0);
+        *counter = counter.checked_add(1).expect("Count overflow");
+        Dot {
+            replica,
+            counter: *counter,
+        }
+    }
+
+    /// Is the event `dot` part of the history summarized by this version vector?
+    #[must_use]
+    pub fn contains(&self, dot: &Dot<R>) -> bool {
+        dot.counter <= self.get(&dot.replica)
+    }
+
+    /// Is this version vector's history a strict subset of `other`'s?
+    #[must_use]
+    pub fn happened_before(&self, other: &Self) -> bool {
+        self.le(other) && self != other
+    }
+
+    /// Has each version vector seen events that the other hasn't?
+    #[must_use]
+    pub fn concurrent(&self, other: &Self) -> bool {
+        !self.le(other) && !other.le(self)
+    }
+}
+
+impl<R: Clone + Ord> Default for VersionVector<R> {
+    fn default() -> Self {
+        VersionVector {
+            counts: BTreeMap::new(),
+        }
+    }
+}
+
+impl<R: Clone + Ord> Grow for VersionVector<R> {
+    type Payload = BTreeMap<R, u64>;
+    type PayloadRef<'a>
+        = &'a BTreeMap<R, u64>
+    where
+        R: 'a;
+    type Update = R;
+    type Query = R;
+    type Value = u64;
+
+    fn new(mut payload: Self::Payload) -> Self {
+        payload.retain(|_, n| *n > 0);
+        VersionVector { counts: payload }
+    }
+    fn payload(&self) -> Self::Payload {
+        self.counts.clone()
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        &self.counts
+    }
+    fn add(&mut self, update: Self::Update) {
+        self.increment(update);
+    }
+    fn le(&self, other: &Self) -> bool {
+        self.counts.iter().all(|(r, &n)| n <= other.get(r))
+    }
+    fn merge(&self, other: &Self) -> Self {
+        let mut counts = self.counts.clone();
+        for (r, &n) in &other.counts {
+            let m = counts.entry(r.clone()).or_insert(0);
+            *m = (*m).max(n);
+        }
+        VersionVector { counts }
+    }
+    fn query(&self, query: &Self::Query) -> Self::Value {
+        self.get(query)
+    }
+}
+
+/// A dotted version vector, a.k.a. a causal context: a version vector plus a "cloud" of
+/// individual dots that aren't (yet) contiguous with it
+///
+/// Plain version vectors can only summarize histories where each replica's events are a contiguous
+/// run starting from 1. Op-based CRDTs (and state-based ones like the OR-Set or ORSWOT) often know
+/// about isolated events, e.g. `(a, 3)` without `(a, 2)`; these live in the `cloud` until the gap
+/// is filled, at which point they're folded into the `clock`. See [Dotted Version
+/// Vectors](https://arxiv.org/abs/1011.5808) and [Delta State Replicated Data
+/// Types](https://arxiv.org/abs/1603.01529) for more.
+///
+/// # Invariants
+///
+/// The `cloud` never contains a dot already covered by the `clock`, nor a dot that directly
+/// follows the `clock` (it would have been folded in). [`new`](#method.new),
+/// [`add`](#method.add), and [`merge`](#method.merge) all maintain this, and
+/// [`le`](#method.le) relies on it.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::causality::{Dot, DottedVersionVector};
+/// use cvrdt_exposition::Grow;
+/// let mut x = DottedVersionVector::new(Default::default());
+/// x.add(Dot { replica: 'a', counter: 1 });
+/// x.add(Dot { replica: 'a', counter: 3 });
+/// assert_eq!(x.clock.get(&'a'), 1);
+/// assert_eq!(x.cloud.len(), 1);
+/// x.add(Dot { replica: 'a', counter: 2 });
+/// assert_eq!(x.clock.get(&'a'), 3);
+/// assert!(x.cloud.is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        from = "UncheckedDottedVersionVector<R>",
+        bound(deserialize = "R: serde::Deserialize<'de>")
+    )
+)]
+pub struct DottedVersionVector<R: Clone + Ord> {
+    /// The contiguous part of the history
+    pub clock: VersionVector<R>,
+    /// Isolated dots not covered by the `clock`
+    pub cloud: BTreeSet<Dot<R>>,
+}
+
+impl<R: Clone + Ord> DottedVersionVector<R> {
+    /// Fold every dot in the `cloud` that is covered by or contiguous with the `clock` into it
+    fn compact(&mut self) {
+        let cloud = std::mem::take(&mut self.cloud);
+        // dots are ordered by replica and then by counter, so a single pass suffices
+        for dot in cloud {
+            let n = self.clock.get(&dot.replica);
+            if dot.counter == n + 1 {
+                self.clock.counts.insert(dot.replica, dot.counter);
+            } else if dot.counter > n {
+                self.cloud.insert(dot);
+            }
+        }
+    }
+
+    /// Is the event `dot` part of this causal history?
+    #[must_use]
+    pub fn contains(&self, dot: &Dot<R>) -> bool {
+        self.clock.contains(dot) || self.cloud.contains(dot)
+    }
+
+    /// Record a new event at `replica`, after every event from `replica` we've seen so far
+    ///
+    /// # Returns
+    ///
+    /// The [`Dot`](struct.Dot.html) for the new event
+    pub fn event(&mut self, replica: R) -> Dot<R> {
+        let counter = self
+            .cloud
+            .iter()
+            .filter(|d| d.replica == replica)
+            .map(|d| d.counter)
+            .max()
+            .unwrap_or_else(|| self.clock.get(&replica))
+            + 1;
+        let dot = Dot { replica, counter };
+        self.add(dot.clone());
+        dot
+    }
+
+    /// Is this causal history a strict subset of `other`'s?
+    #[must_use]
+    pub fn happened_before(&self, other: &Self) -> bool {
+        self.le(other) && self != other
+    }
+
+    /// Has each causal history seen events that the other hasn't?
+    #[must_use]
+    pub fn concurrent(&self, other: &Self) -> bool {
+        !self.le(other) && !other.le(self)
+    }
+}
+
+impl<R: Clone + Ord> Default for DottedVersionVector<R> {
+    fn default() -> Self {
+        DottedVersionVector {
+            clock: VersionVector::default(),
+            cloud: BTreeSet::new(),
+        }
+    }
+}
+
+impl<R: Clone + Ord> Grow for DottedVersionVector<R> {
+    type Payload = (BTreeMap<R, u64>, BTreeSet<Dot<R>>);
+    type PayloadRef<'a>
+        = (&'a BTreeMap<R, u64>, &'a BTreeSet<Dot<R>>)
+    where
+        R: 'a;
+    type Update = Dot<R>;
+    type Query = Dot<R>;
+    type Value = bool;
+
+    fn new(payload: Self::Payload) -> Self {
+        let mut dvv = DottedVersionVector {
+            clock: VersionVector::new(payload.0),
+            cloud: payload.1,
+        };
+        dvv.compact();
+        dvv
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.clock.payload(), self.cloud.clone())
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (&self.clock.counts, &self.cloud)
+    }
+    fn add(&mut self, update: Self::Update) {
+        if !self.contains(&update) {
+            self.cloud.insert(update);
+            self.compact();
+        }
+    }
+    fn le(&self, other: &Self) -> bool {
+        // `other` is compact, so if our clock is ahead of its clock for some replica, we've seen
+        // the dot just after its clock, which it can't have in its cloud
+        self.clock.le(&other.clock) && self.cloud.iter().all(|d| other.contains(d))
+    }
+    fn merge(&self, other: &Self) -> Self {
+        let mut dvv = DottedVersionVector {
+            clock: self.clock.merge(&other.clock),
+            cloud: self.cloud.union(&other.cloud).cloned().collect(),
+        };
+        dvv.compact();
+        dvv
+    }
+    fn query(&self, query: &Self::Query) -> Self::Value {
+        self.contains(query)
+    }
+}
+
+/// A causal-stability oracle: which events every known replica has seen
+///
+/// Each known replica [`ack`](#method.ack)s the version vector of the events it has seen. An event
+/// is _causally stable_ once every known replica has acknowledged it: no known replica still holds
+/// state from before it, so metadata kept only to order later updates against it (like the
+/// tombstones of a [`CollectableTwoPhaseSet`](../two_phase_set/struct.CollectableTwoPhaseSet.html))
+/// can be dropped. The [`stable`](#method.stable) events are the pointwise minimum of the
+/// acknowledgements, which only ever grows.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::causality::{Dot, Stability, VersionVector};
+/// use cvrdt_exposition::Grow;
+/// let mut oracle = Stability::new(['a', 'b']);
+/// let mut a = VersionVector::default();
+/// let dot = a.increment('a');
+/// oracle.ack(&'a', &a);
+/// assert!(!oracle.is_stable(&dot));
+/// let b = VersionVector::default().merge(&a);
+/// oracle.ack(&'b', &b);
+/// assert!(oracle.is_stable(&dot));
+/// // a replica that hasn't seen every stable event can't join
+/// assert!(!oracle.join('c', &VersionVector::default()));
+/// assert!(oracle.join('c', &b));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stability<R: Clone + Ord> {
+    /// The events acknowledged by each known replica
+    pub acks: BTreeMap<R, VersionVector<R>>,
+}
+
+impl<R: Clone + Ord> Stability<R> {
+    /// An oracle for the given replicas, none of which has acknowledged anything yet
+    pub fn new(replicas: impl IntoIterator<Item = R>) -> Self {
+        Stability {
+            acks: replicas
+                .into_iter()
+                .map(|r| (r, VersionVector::default()))
+                .collect(),
+        }
+    }
+
+    /// Record that `replica` has seen every event in `seen`
+    ///
+    /// # Panics
+    ///
+    /// If `replica` isn't known; see [`join`](#method.join)
+    pub fn ack(&mut self, replica: &R, seen: &VersionVector<R>) {
+        let acked = self.acks.get_mut(replica).expect("Unknown replica");
+        *acked = acked.merge(seen);
+    }
+
+    /// Add `replica`, which has seen the events in `seen`, to the known replicas
+    ///
+    /// A replica that hasn't seen every [`stable`](#method.stable) event may hold state that others
+    /// have since forgotten, so it can't join; it must start over from a known replica's state.
+    ///
+    /// # Returns
+    ///
+    /// Whether `replica` joined
+    #[must_use]
+    pub fn join(&mut self, replica: R, seen: &VersionVector<R>) -> bool {
+        if !self.stable().le(seen) {
+            return false;
+        }
+        let acked = self.acks.entry(replica).or_default();
+        *acked = acked.merge(seen);
+        true
+    }
+
+    /// Forget `replica`, e.g. after it has permanently failed, so that it no longer holds back
+    /// stability
+    ///
+    /// # Returns
+    ///
+    /// Whether `replica` was known
+    pub fn leave(&mut self, replica: &R) -> bool {
+        self.acks.remove(replica).is_some()
+    }
+
+    /// The events that every known replica has seen; empty if no replicas are known
+    #[must_use]
+    pub fn stable(&self) -> VersionVector<R> {
+        let mut acks = self.acks.values();
+        let Some(first) = acks.next() else {
+            return VersionVector::default();
+        };
+        let mut stable = first.counts.clone();
+        for ack in acks {
+            stable.retain(|r, n| {
+                *n = (*n).min(ack.get(r));
+                *n > 0
+            });
+        }
+        VersionVector { counts: stable }
+    }
+
+    /// Has every known replica seen `dot`?
+    #[must_use]
+    pub fn is_stable(&self, dot: &Dot<R>) -> bool {
+        !self.acks.is_empty() && self.acks.values().all(|ack| ack.contains(dot))
+    }
+}
+
+/// A `VersionVector` as it comes off the wire, possibly with zero counts
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedVersionVector<R: Ord> {
+    counts: BTreeMap<R, u64>,
+}
+
+#[cfg(feature = "serde")]
+impl<R: Clone + Ord> From<UncheckedVersionVector<R>> for VersionVector<R> {
+    fn from(unchecked: UncheckedVersionVector<R>) -> Self {
+        VersionVector::new(unchecked.counts)
+    }
+}
+
+/// A `DottedVersionVector` as it comes off the wire, possibly not compact
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(bound(deserialize = "R: serde::Deserialize<'de>"))]
+struct UncheckedDottedVersionVector<R: Clone + Ord> {
+    clock: VersionVector<R>,
+    cloud: BTreeSet<Dot<R>>,
+}
+
+#[cfg(feature = "serde")]
+impl<R: Clone + Ord> From<UncheckedDottedVersionVector<R>> for DottedVersionVector<R> {
+    fn from(unchecked: UncheckedDottedVersionVector<R>) -> Self {
+        DottedVersionVector::new((unchecked.clock.counts, unchecked.cloud))
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<R: proptest::arbitrary::Arbitrary> proptest::arbitrary::Arbitrary for Dot<R> {
+    type Parameters = R::Parameters;
+    type Strategy = proptest::strategy::Map<
+        (
+            proptest::arbitrary::StrategyFor<R>,
+            std::ops::RangeInclusive<u64>,
+        ),
+        fn((R, u64)) -> Dot<R>,
+    >;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (any_with::<R>(args), 1..=u64::MAX).prop_map(|(replica, counter)| Dot { replica, counter })
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<R: proptest::arbitrary::Arbitrary + Clone + Ord> proptest::arbitrary::Arbitrary
+    for VersionVector<R>
+{
+    type Parameters = <BTreeMap<R, u64> as proptest::arbitrary::Arbitrary>::Parameters;
+    type Strategy = proptest::strategy::Map<
+        proptest::arbitrary::StrategyFor<BTreeMap<R, u64>>,
+        fn(BTreeMap<R, u64>) -> VersionVector<R>,
+    >;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any_with::<BTreeMap<R, u64>>(args).prop_map(VersionVector::new)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<R: proptest::arbitrary::Arbitrary + Clone + Ord> proptest::arbitrary::Arbitrary
+    for DottedVersionVector<R>
+{
+    type Parameters =
+        <(BTreeMap<R, u64>, BTreeSet<Dot<R>>) as proptest::arbitrary::Arbitrary>::Parameters;
+    type Strategy = proptest::strategy::Map<
+        proptest::arbitrary::StrategyFor<(BTreeMap<R, u64>, BTreeSet<Dot<R>>)>,
+        fn((BTreeMap<R, u64>, BTreeSet<Dot<R>>)) -> DottedVersionVector<R>,
+    >;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any_with::<(BTreeMap<R, u64>, BTreeSet<Dot<R>>)>(args).prop_map(DottedVersionVector::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::properties::grow;
+    use proptest::prelude::*;
+
+    // A handful of replicas and small counters, so that histories often overlap
+    fn dot() -> impl Strategy<Value = Dot<u8>> {
+        (0..4u8, 1..8u64).prop_map(|(replica, counter)| Dot { replica, counter })
+    }
+
+    fn dots() -> impl Strategy<Value = BTreeSet<Dot<u8>>> {
+        prop::collection::btree_set(dot(), 0..16)
+    }
+
+    fn vv() -> impl Strategy<Value = VersionVector<u8>> {
+        prop::collection::btree_map(0..4u8, 0..8u64, 0..4).prop_map(VersionVector::new)
+    }
+
+    fn dvv() -> impl Strategy<Value = DottedVersionVector<u8>> {
+        dots().prop_map(|ds| {
+            let mut d = DottedVersionVector::default();
+            for dot in ds {
+                d.add(dot);
+            }
+            d
+        })
+    }
+
+    /// Every dot in a causal history, spelled out
+    fn history(d: &DottedVersionVector<u8>) -> BTreeSet<Dot<u8>> {
+        d.clock
+            .counts
+            .iter()
+            .flat_map(|(&replica, &n)| (1..=n).map(move |counter| Dot { replica, counter }))
+            .chain(d.cloud.iter().cloned())
+            .collect()
+    }
+
+    mod version_vector {
+        use super::*;
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (VersionVector<u8>, u8)> {
+            (vv(), 0..4u8)
+        }
+
+        grow!(vv, cvrdt_and_update);
+    }
+
+    mod dotted_version_vector {
+        use super::*;
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (DottedVersionVector<u8>, Dot<u8>)> {
+            (dvv(), dot())
+        }
+
+        grow!(dvv, cvrdt_and_update);
+    }
+
+    proptest! {
+        #[test]
+        fn exactly_one_causal_relation(x in vv(), y in vv()) {
+            let relations = [
+                x == y,
+                x.happened_before(&y),
+                y.happened_before(&x),
+                x.concurrent(&y),
+            ];
+            prop_assert_eq!(relations.iter().filter(|&&r| r).count(), 1);
+        }
+        #[test]
+        fn happened_before_is_irreflexive(x in vv()) {
+            prop_assert!(!x.happened_before(&x));
+        }
+        #[test]
+        fn happened_before_is_transitive(x in vv(), y in vv(), z in vv()) {
+            let y = x.merge(&y);
+            let z = y.merge(&z);
+            if x.happened_before(&y) && y.happened_before(&z) {
+                prop_assert!(x.happened_before(&z));
+            }
+        }
+        #[test]
+        fn increment_happens_after(x in vv(), r in 0..4u8) {
+            let mut y = x.clone();
+            let d = y.increment(r);
+            prop_assert!(x.happened_before(&y));
+            prop_assert!(!x.contains(&d) && y.contains(&d));
+        }
+        #[test]
+        fn concurrent_increments(x in vv(), r in 0..4u8, s in 0..4u8) {
+            let (mut y, mut z) = (x.clone(), x.clone());
+            y.increment(r);
+            z.increment(s);
+            prop_assert_eq!(y.concurrent(&z), r != s);
+        }
+        #[test]
+        fn dvv_is_compact(d in dvv()) {
+            for dot in &d.cloud {
+                prop_assert!(dot.counter > d.clock.get(&dot.replica) + 1);
+            }
+        }
+        #[test]
+        fn dvv_le_is_history_subset(x in dvv(), y in dvv()) {
+            prop_assert_eq!(x.le(&y), history(&x).is_subset(&history(&y)));
+            prop_assert_eq!(history(&x.merge(&y)), history(&x).union(&history(&y)).cloned().collect());
+        }
+        #[test]
+        fn dvv_new_compacts(clock in prop::collection::btree_map(0..4u8, 0..8u64, 0..4), cloud in dots()) {
+            let mut expected = DottedVersionVector::new((clock.clone(), BTreeSet::new()));
+            for dot in cloud.iter().cloned() {
+                expected.add(dot);
+            }
+            prop_assert_eq!(DottedVersionVector::new((clock, cloud)), expected);
+        }
+        #[test]
+        fn dvv_causal_relations(x in dvv(), y in dvv()) {
+            let (hx, hy) = (history(&x), history(&y));
+            prop_assert_eq!(x.happened_before(&y), hx.is_subset(&hy) && hx != hy);
+            prop_assert_eq!(x.concurrent(&y), !hx.is_subset(&hy) && !hy.is_subset(&hx));
+        }
+        #[test]
+        fn dvv_event_is_new(x in dvv(), r in 0..4u8) {
+            let mut y = x.clone();
+            let d = y.event(r);
+            prop_assert!(!x.contains(&d) && y.contains(&d));
+            prop_assert!(x.happened_before(&y));
+        }
+        #[test]
+        fn stable_is_what_everyone_has_seen(acks in prop::collection::vec(vv(), 1..4), d in dot()) {
+            let mut oracle = Stability::new(0..acks.len() as u8);
+            for (r, ack) in (0..).zip(&acks) {
+                oracle.ack(&r, ack);
+            }
+            let stable = oracle.stable();
+            prop_assert_eq!(oracle.is_stable(&d), acks.iter().all(|a| a.contains(&d)));
+            prop_assert_eq!(oracle.is_stable(&d), stable.contains(&d));
+            for ack in &acks {
+                prop_assert!(stable.le(ack));
+            }
+        }
+        #[test]
+        fn stability_only_grows(acks in prop::collection::vec((0..3u8, vv()), 0..8), joiner in vv()) {
+            let mut oracle = Stability::new(0..3u8);
+            let mut stable = oracle.stable();
+            for (r, ack) in &acks {
+                oracle.ack(r, ack);
+                prop_assert!(stable.le(&oracle.stable()));
+                stable = oracle.stable();
+            }
+            prop_assert_eq!(oracle.clone().join(3, &joiner), stable.le(&joiner));
+            if oracle.join(3, &joiner) {
+                prop_assert_eq!(oracle.stable(), stable.clone());
+            }
+            oracle.leave(&0);
+            prop_assert!(stable.le(&oracle.stable()));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Count overflow")]
+    fn increment_panics_on_overflow() {
+        let mut x = VersionVector::new(BTreeMap::from([('a', u64::MAX)]));
+        x.increment('a');
+    }
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<VersionVector<String>>(), y in any::<DottedVersionVector<String>>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            prop_assert_eq!(serde_json::from_str::<VersionVector<String>>(&json).unwrap(), x);
+            let json = serde_json::to_string(&y).unwrap();
+            prop_assert_eq!(serde_json::from_str::<DottedVersionVector<String>>(&json).unwrap(), y);
+        }
+    }
+}
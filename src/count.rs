@@ -0,0 +1,91 @@
+//! Unsigned integer types for the counts in our counters
+//!
+//! [`GCounter`](../g_counter/struct.GCounter.html) and
+//! [`PNCounter`](../pn_counter/struct.PNCounter.html) (and their fixed-size variants) are generic
+//! over a [`Count`](trait.Count.html), defaulting to `u64`. Pick a `u32` (or smaller) to save
+//! memory on constrained replicas, or a `u128` for very high-volume counts.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::{GCounter, Grow};
+//! let mut x = GCounter::<u128>::new((0, vec![u128::from(u64::MAX); 2]));
+//! x.add(());
+//! assert_eq!(x.query(&()), 2 * u128::from(u64::MAX) + 1);
+//! ```
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// An unsigned integer count
+pub trait Count: Copy + Debug + Default + Eq + Hash + Ord + 'static {
+    /// Zero, which every count starts from
+    const ZERO: Self;
+
+    /// One, the amount a local update adds
+    const ONE: Self;
+
+    /// The largest representable count
+    const MAX: Self;
+
+    /// Add, returning `None` on overflow
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// Add, stopping at `MAX` on overflow
+    #[must_use]
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// Subtract, returning `None` if `other` is larger
+    fn checked_sub(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! count {
+    ($($t:ty),*) => {
+        $(
+            impl Count for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$t>::MAX;
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+                fn saturating_add(self, other: Self) -> Self {
+                    <$t>::saturating_add(self, other)
+                }
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+            }
+        )*
+    };
+}
+
+count!(u8, u16, u32, u64, u128, usize);
+
+/// Increment `count` by one
+///
+/// # Panics
+///
+/// If `count` is already `MAX`
+pub(crate) fn increment<T: Count>(count: &mut T) {
+    *count = count.checked_add(T::ONE).expect("Count overflow");
+}
+
+/// The sum of `counts`, stopping at `MAX`
+pub(crate) fn saturating_sum<'a, T: Count + 'a>(counts: impl IntoIterator<Item = &'a T>) -> T {
+    counts
+        .into_iter()
+        .fold(T::ZERO, |sum, &c| sum.saturating_add(c))
+}
+
+/// The sum of `counts`
+///
+/// # Panics
+///
+/// If the sum overflows
+pub(crate) fn sum<'a, T: Count + 'a>(counts: impl IntoIterator<Item = &'a T>) -> T {
+    counts.into_iter().fold(T::ZERO, |sum, &c| {
+        sum.checked_add(c).expect("Count overflow")
+    })
+}
@@ -0,0 +1,407 @@
+//! Reconciling sets by exchanging Merkle digests, then only the elements that differ
+//!
+//! Merging whole states is simple, but wasteful when two replicas of a large set mostly agree.
+//! Instead, each replica hashes its elements into `2^depth` buckets (by the top bits of each
+//! element's hash), and builds a binary [Merkle tree](https://en.wikipedia.org/wiki/Merkle_tree)
+//! whose leaves are the XOR of the hashes in each bucket and whose inner nodes are the XOR of
+//! their children. Two replicas compare their trees from the root down, descending only into
+//! subtrees that differ, to find the buckets that differ; then each sends the other a _delta_:
+//! a (small) state holding only its elements in those buckets. Merging a delta is just an
+//! ordinary [`merge`](../traits/trait.Grow.html#tymethod.merge), since a delta is a state that
+//! the sender's state dominates.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::digest::reconcile;
+//! use cvrdt_exposition::{GSet, Grow};
+//! let mut x = GSet::<u32>::new((0..1_000).collect());
+//! let mut y = x.clone();
+//! let expected = GSet::new((0..1_001u32).collect());
+//! x.add(1_000);
+//! let report = reconcile(&mut x, &mut y, 8);
+//! assert_eq!(x, expected);
+//! assert_eq!(y, expected);
+//! assert!(report.bytes < report.full_state_bytes / 10);
+//! ```
+
+use crate::set::Set;
+use crate::traits::Grow;
+use crate::wire::{self, Element, Wire};
+use crate::{GSet, TwoPhaseSet};
+use std::collections::BTreeSet;
+use std::hash::Hash;
+
+/// The deepest tree we'll build, with a million or so leaves
+pub const MAX_DEPTH: u8 = 20;
+
+static FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+static FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hash `bytes`: 64-bit [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/), then a finalizer
+/// (from `SplitMix64`) so that the top bits, which choose buckets, are well mixed
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut h = bytes.iter().fold(FNV_OFFSET, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    });
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// A stable hash of `x`, computed from its [wire](../wire/index.html) encoding so that every
+/// replica (on every platform) agrees on it
+pub fn hash<X: Element>(x: &X) -> u64 {
+    let mut buf = Vec::new();
+    x.encode(&mut buf);
+    hash_bytes(&buf)
+}
+
+/// The hash of `x` tagged with which part of a state it's in (e.g. added or removed), so that the
+/// same element in different parts contributes differently to a digest
+fn tagged_hash<X: Element>(tag: u8, x: &X) -> u64 {
+    let mut buf = vec![tag];
+    x.encode(&mut buf);
+    hash_bytes(&buf)
+}
+
+/// Which of the `2^depth` buckets `x` belongs in
+pub fn bucket<X: Element>(x: &X, depth: u8) -> usize {
+    if depth == 0 {
+        0
+    } else {
+        (hash(x) >> (64 - u32::from(depth))) as usize
+    }
+}
+
+/// A Merkle tree of XOR-ed element hashes
+///
+/// Nodes are stored in heap order: the root is `nodes[0]`, the children of `nodes[i]` are
+/// `nodes[2 * i + 1]` and `nodes[2 * i + 2]`, and bucket `b`'s leaf is `nodes[2^depth - 1 + b]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Digest {
+    /// The height of the tree; there are `2^depth` buckets
+    pub depth: u8,
+    /// Every node of the tree
+    pub nodes: Vec<u64>,
+}
+
+/// The result of comparing two [`Digest`](struct.Digest.html)s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    /// The buckets whose contents differ, in increasing order
+    pub buckets: Vec<usize>,
+    /// How many node hashes had to be compared to find them, i.e. how many each side sends when
+    /// descending the trees level by level
+    pub hashes: usize,
+}
+
+impl Digest {
+    /// An empty tree with `2^depth` buckets
+    ///
+    /// # Panics
+    ///
+    /// If `depth` exceeds [`MAX_DEPTH`](constant.MAX_DEPTH.html)
+    #[must_use]
+    pub fn new(depth: u8) -> Self {
+        assert!(depth <= MAX_DEPTH, "Depth too large");
+        Digest {
+            depth,
+            nodes: vec![0; (1 << (depth + 1)) - 1],
+        }
+    }
+
+    /// Add a hash to bucket `b`, updating every node on the path to the root
+    ///
+    /// # Panics
+    ///
+    /// If `b` isn't one of our buckets
+    pub fn insert(&mut self, b: usize, h: u64) {
+        assert!(b < 1 << self.depth, "Bucket out of range");
+        let mut i = (1 << self.depth) - 1 + b;
+        loop {
+            self.nodes[i] ^= h;
+            if i == 0 {
+                break;
+            }
+            i = (i - 1) / 2;
+        }
+    }
+
+    /// The root hash, which summarizes the whole state
+    #[must_use]
+    pub fn root(&self) -> u64 {
+        self.nodes[0]
+    }
+
+    /// Find the buckets in which `self` and `other` differ, descending only into differing
+    /// subtrees
+    ///
+    /// # Panics
+    ///
+    /// If `self` and `other` have different depths
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Diff {
+        assert_eq!(self.depth, other.depth, "Incompatible depths");
+        let first_leaf = (1 << self.depth) - 1;
+        let mut level = vec![0];
+        let mut hashes = 1;
+        let mut buckets = Vec::new();
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            for i in level {
+                if self.nodes[i] == other.nodes[i] {
+                    continue;
+                }
+                if i >= first_leaf {
+                    buckets.push(i - first_leaf);
+                } else {
+                    hashes += 2;
+                    next.extend([2 * i + 1, 2 * i + 2]);
+                }
+            }
+            level = next;
+        }
+        Diff { buckets, hashes }
+    }
+}
+
+/// `CvRDTs` that can be reconciled by exchanging digests and deltas
+pub trait Reconcile: Grow {
+    /// A Merkle digest of this state with `2^depth` buckets
+    ///
+    /// # Panics
+    ///
+    /// If `depth` exceeds [`MAX_DEPTH`](constant.MAX_DEPTH.html)
+    fn digest(&self, depth: u8) -> Digest;
+
+    /// The part of this state in the given `buckets` (out of `2^depth`)
+    ///
+    /// # Returns
+    ///
+    /// A state that `self` dominates, and which contains everything in `self` that falls in
+    /// `buckets`
+    fn delta(&self, buckets: &[usize], depth: u8) -> Self;
+}
+
+impl<X: Element + Clone + Eq, S: Set<Element = X>> Reconcile for GSet<X, S> {
+    fn digest(&self, depth: u8) -> Digest {
+        let mut d = Digest::new(depth);
+        for x in self.values.iter() {
+            d.insert(bucket(x, depth), tagged_hash(0, x));
+        }
+        d
+    }
+    fn delta(&self, buckets: &[usize], depth: u8) -> Self {
+        let buckets: BTreeSet<_> = buckets.iter().collect();
+        GSet::new(in_buckets(&self.values, &buckets, depth))
+    }
+}
+
+impl<X: Element + Clone + Eq, S: Set<Element = X>> Reconcile for TwoPhaseSet<X, S> {
+    fn digest(&self, depth: u8) -> Digest {
+        let mut d = Digest::new(depth);
+        for x in self.added.iter() {
+            d.insert(bucket(x, depth), tagged_hash(0, x));
+        }
+        for x in self.removed.iter() {
+            d.insert(bucket(x, depth), tagged_hash(1, x));
+        }
+        d
+    }
+    fn delta(&self, buckets: &[usize], depth: u8) -> Self {
+        let buckets: BTreeSet<_> = buckets.iter().collect();
+        TwoPhaseSet::new((
+            in_buckets(&self.added, &buckets, depth),
+            in_buckets(&self.removed, &buckets, depth),
+        ))
+    }
+}
+
+fn in_buckets<S: Set>(xs: &S, buckets: &BTreeSet<&usize>, depth: u8) -> S
+where
+    S::Element: Element,
+{
+    xs.iter()
+        .filter(|x| buckets.contains(&bucket(*x, depth)))
+        .cloned()
+        .collect()
+}
+
+/// How much a [`reconcile`](fn.reconcile.html) sent, compared to exchanging full states
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The buckets that differed
+    pub buckets: usize,
+    /// Bytes sent in both directions: digest hashes, the list of differing buckets, and both
+    /// deltas
+    pub bytes: usize,
+    /// Bytes that sending both full states would have taken
+    pub full_state_bytes: usize,
+}
+
+/// Bring `x` and `y` up to date with each other, sending only what differs
+///
+/// The two replicas descend their digests together to find differing buckets, `y` sends `x` the
+/// list of those buckets and its delta for them, and `x` replies with its own delta. Afterwards
+/// both equal `x.merge(y)` (as they were before).
+///
+/// # Parameters
+///
+/// - mutably borrowed references to two replicas
+/// - `depth`, the height of the digest trees; deeper trees pinpoint differences more precisely,
+///   at the cost of more hashes to compare when many buckets differ
+///
+/// # Returns
+///
+/// A [`Report`](struct.Report.html) of the bytes sent
+///
+/// # Panics
+///
+/// If `depth` exceeds [`MAX_DEPTH`](constant.MAX_DEPTH.html)
+pub fn reconcile<R: Reconcile + Wire>(x: &mut R, y: &mut R, depth: u8) -> Report {
+    let full_state_bytes = wire::encode(x).len() + wire::encode(y).len();
+    let diff = x.digest(depth).diff(&y.digest(depth));
+    let mut bytes = 8 * diff.hashes;
+    let mut list = Vec::new();
+    wire::write_varint(&mut list, diff.buckets.len() as u64);
+    for &b in &diff.buckets {
+        wire::write_varint(&mut list, b as u64);
+    }
+    bytes += list.len();
+    if !diff.buckets.is_empty() {
+        let from_y = y.delta(&diff.buckets, depth);
+        let from_x = x.delta(&diff.buckets, depth);
+        bytes += wire::encode(&from_y).len() + wire::encode(&from_x).len();
+        *x = x.merge(&from_y);
+        *y = y.merge(&from_x);
+    }
+    Report {
+        buckets: diff.buckets.len(),
+        bytes,
+        full_state_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Shrink;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    static DEPTH: u8 = 8;
+
+    proptest! {
+        #[test]
+        fn equal_states_have_equal_digests(xs in any::<HashSet<u16>>()) {
+            let x = GSet::new(xs);
+            let d = x.digest(DEPTH);
+            prop_assert_eq!(d.clone(), x.clone().digest(DEPTH));
+            prop_assert_eq!(d.diff(&x.digest(DEPTH)), Diff { buckets: vec![], hashes: 1 });
+        }
+
+        #[test]
+        fn root_is_order_independent(xs in any::<Vec<u16>>()) {
+            let forward = GSet::<u16>::new(xs.iter().copied().collect());
+            let mut backward = GSet::new(HashSet::new());
+            for &x in xs.iter().rev() {
+                backward.add(x);
+            }
+            prop_assert_eq!(forward.digest(DEPTH), backward.digest(DEPTH));
+        }
+
+        #[test]
+        fn diff_finds_every_differing_bucket(xs in any::<HashSet<u16>>(), ys in any::<HashSet<u16>>(), depth in 0..=10u8) {
+            let (x, y) = (GSet::new(xs.clone()), GSet::new(ys.clone()));
+            let expected: Vec<_> = xs
+                .symmetric_difference(&ys)
+                .map(|v| bucket(v, depth))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            let found = x.digest(depth).diff(&y.digest(depth)).buckets;
+            // XOR-ed hashes could in principle cancel out, but with 64 bits they won't here
+            prop_assert_eq!(found, expected);
+        }
+
+        #[test]
+        fn g_set_reconciles(
+            base in any::<HashSet<u32>>(),
+            xs in any::<HashSet<u32>>(),
+            ys in any::<HashSet<u32>>(),
+            depth in 0..=12u8,
+        ) {
+            let mut x = GSet::<u32>::new(base.union(&xs).copied().collect());
+            let mut y = GSet::new(base.union(&ys).copied().collect());
+            let merged = x.merge(&y);
+            reconcile(&mut x, &mut y, depth);
+            prop_assert_eq!(&x, &merged);
+            prop_assert_eq!(&y, &merged);
+        }
+
+        #[test]
+        fn two_phase_set_reconciles(
+            base in any::<HashSet<u16>>(),
+            xs in any::<Vec<(bool, u16)>>(),
+            ys in any::<Vec<(bool, u16)>>(),
+            depth in 0..=12u8,
+        ) {
+            let base = TwoPhaseSet::new((base, HashSet::new()));
+            let (mut x, mut y) = (base.clone(), base);
+            for (t, updates) in [(&mut x, xs), (&mut y, ys)] {
+                for (remove, v) in updates {
+                    if remove && t.query(&v) {
+                        t.del(v);
+                    } else {
+                        t.add(v);
+                    }
+                }
+            }
+            let merged = x.merge(&y);
+            reconcile(&mut x, &mut y, depth);
+            prop_assert_eq!(&x, &merged);
+            prop_assert_eq!(&y, &merged);
+        }
+    }
+
+    #[test]
+    fn removal_changes_the_digest() {
+        let mut x = TwoPhaseSet::new(((0..100u8).collect(), HashSet::new()));
+        let y = x.clone();
+        x.del(42);
+        let diff = x.digest(DEPTH).diff(&y.digest(DEPTH));
+        assert_eq!(diff.buckets, vec![bucket(&42u8, DEPTH)]);
+    }
+
+    #[test]
+    fn agreeing_replicas_send_almost_nothing() {
+        let mut x = GSet::<u64>::new((0..10_000).collect());
+        let mut y = x.clone();
+        let report = reconcile(&mut x, &mut y, 12);
+        assert_eq!(report.buckets, 0);
+        assert_eq!(report.bytes, 8 + 1);
+        assert!(report.full_state_bytes > 2 * 10_000);
+    }
+
+    #[test]
+    fn small_differences_send_little() {
+        let base: HashSet<u64> = (0..10_000).collect();
+        let mut x = GSet::new(base.clone());
+        let mut y = TwoPhaseSet::new((base, HashSet::new()));
+        let mut y2 = y.clone();
+        for k in 0..10 {
+            x.add(1_000_000 + k);
+            y.del(k);
+            y2.add(2_000_000 + k);
+        }
+        let mut x2 = GSet::new((0..10_000).collect());
+        let report = reconcile(&mut x, &mut x2, 12);
+        assert!(0 < report.buckets && report.buckets <= 10);
+        assert!(report.bytes * 10 < report.full_state_bytes);
+        let report = reconcile(&mut y, &mut y2, 12);
+        assert!(report.buckets <= 20);
+        assert!(report.bytes * 10 < report.full_state_bytes);
+        assert_eq!(y, y2);
+    }
+}
@@ -1,18 +1,20 @@
+use crate::count::{increment, saturating_sum, Count};
+use crate::operation::Operation;
 use crate::traits::Grow;
 
 /// A vectorized counter that can only grow
 ///
+/// Counts are `u64`s by default; any other [`Count`](../count/trait.Count.html) works too.
+///
 /// # Panics
 ///
-/// Any function involving two or more `GCounter`s (viz. `le` and `merge`) will panic (via
-/// `assert_eq!`) if their counts vectors are not the same length. I'd prefer to check this at
-/// compile time (as much as possible) instead, but
+/// Incrementing a count that's already at its maximum (via `add`) panics, rather than wrapping
+/// around; `query` instead stops at the maximum.
 ///
-/// - avoiding C++'s template mess is part of what makes Rust great
-/// - Rust doesn't have [const generics](https://rust-lang.github.io/rfcs/2000-const-generics.html)
-/// yet
-/// - this library is meant to be as simple and expository as possible, so I'd like to avoid
-/// fancier things like [`generic_array`](https://docs.rs/generic-array/0.14.4/generic_array/)
+/// Any function involving two or more `GCounter`s (viz. `le` and `merge`) will panic (via
+/// `assert_eq!`) if their counts vectors are not the same length. When the number of replicas is
+/// known at compile time, a [`GCounterN`](struct.GCounterN.html) checks this at compile time
+/// instead, via [const generics](https://rust-lang.github.io/rfcs/2000-const-generics.html).
 ///
 /// As mentioned above, operations panic when trying dealing with two or more `GCounter`s of
 /// incompatible sizes:
@@ -36,6 +38,9 @@ use crate::traits::Grow;
 /// choice that when merging two `GCounter`s, we take the minimum of their two `id`s as the new
 /// one.
 ///
+/// Wrap a `GCounter` in a [`Replica`](../replica/struct.Replica.html) handle to keep the local
+/// `id` stable across merges instead.
+///
 /// # Examples
 ///
 /// Example usage, including demonstrating some properties:
@@ -55,15 +60,28 @@ use crate::traits::Grow;
 /// assert_eq!(x.merge(&y).merge(&z).payload(), (0, vec![1, 2, 3]));
 /// assert_eq!(x.merge(&y.merge(&z)).payload(), x.merge(&y).merge(&z).payload());
 /// ```
-#[derive(Debug, Clone)]
-pub struct GCounter {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedGCounter<T>"))]
+pub struct GCounter<T: Count = u64> {
     /// The index for this local `GCounter` where all increments occur
     pub id: usize,
     /// The vector of counts
-    pub counts: Vec<u64>,
+    pub counts: Vec<T>,
 }
 
 impl GCounter {
+    /// Create a `GCounter` of `u64` counts, as with [`Grow::new`](../traits/trait.Grow.html#tymethod.new)
+    ///
+    /// Integer literals in `payload` would otherwise need their type spelled out; for other count
+    /// types, name the type instead, e.g. `GCounter::<u8>::new((0, vec![0; 3]))`.
+    #[must_use]
+    pub fn new(payload: (usize, Vec<u64>)) -> Self {
+        <Self as Grow>::new(payload)
+    }
+}
+
+impl<T: Count> GCounter<T> {
     fn compatible_len(&self, other: &Self) -> usize {
         assert_eq!(
             self.counts.len(),
@@ -74,11 +92,12 @@ impl GCounter {
     }
 }
 
-impl Grow for GCounter {
-    type Payload = (usize, Vec<u64>);
+impl<T: Count> Grow for GCounter<T> {
+    type Payload = (usize, Vec<T>);
+    type PayloadRef<'a> = (usize, &'a [T]);
     type Update = ();
     type Query = ();
-    type Value = u64;
+    type Value = T;
 
     fn new(payload: Self::Payload) -> Self {
         GCounter {
@@ -89,13 +108,19 @@ impl Grow for GCounter {
     fn payload(&self) -> Self::Payload {
         (self.id, self.counts.clone())
     }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.id, &self.counts)
+    }
     fn add(&mut self, _update: Self::Update) {
-        self.counts[self.id] += 1;
+        increment(&mut self.counts[self.id]);
     }
     fn le(&self, other: &Self) -> bool {
         let n = self.compatible_len(other);
         (0..n).all(|i| self.counts[i] <= other.counts[i])
     }
+    fn compatible(&self, other: &Self) -> bool {
+        self.counts.len() == other.counts.len()
+    }
     fn merge(&self, other: &Self) -> Self {
         let n = self.compatible_len(other);
         GCounter {
@@ -106,7 +131,288 @@ impl Grow for GCounter {
         }
     }
     fn query(&self, _query: &Self::Query) -> Self::Value {
-        self.counts.iter().sum()
+        saturating_sum(&self.counts)
+    }
+}
+
+/// A [`GCounter`](struct.GCounter.html) for a fixed number `N` of replicas
+///
+/// The number of replicas is part of the type, so there are no lengths to check: comparing or
+/// merging counters for different numbers of replicas doesn't compile.
+///
+/// ```compile_fail
+/// use cvrdt_exposition::{GCounterN, Grow};
+/// let x = GCounterN::<2>::new((0, [0; 2]));
+/// let y = GCounterN::<3>::new((1, [0; 3]));
+/// x.merge(&y);
+/// ```
+///
+/// # Panics
+///
+/// `new` panics if `id` isn't a valid index, i.e. if it isn't less than `N`:
+///
+/// ```should_panic
+/// // this will panic
+/// use cvrdt_exposition::{GCounterN, Grow};
+/// let x = GCounterN::<3>::new((3, [0; 3]));
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::{GCounter, GCounterN, Grow};
+/// let mut x = GCounterN::<3>::new((0, [0; 3]));
+/// x.add(());
+/// let y = GCounterN::new((1, [0, 2, 0]));
+/// assert_eq!(x.merge(&y).payload(), (0, [1, 2, 0]));
+/// assert_eq!(x.merge(&y).query(&()), 3);
+/// // converting to and from a `GCounter` checks the length
+/// assert_eq!(GCounter::from(y).payload(), (1, vec![0, 2, 0]));
+/// assert!(GCounterN::<2>::try_from(GCounter::new((0, vec![0; 3]))).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "GCounter<T>", try_from = "GCounter<T>")
+)]
+pub struct GCounterN<const N: usize, T: Count = u64> {
+    /// The index for this local `GCounterN` where all increments occur
+    pub id: usize,
+    /// The array of counts
+    pub counts: [T; N],
+}
+
+impl<const N: usize, T: Count> Grow for GCounterN<N, T> {
+    type Payload = (usize, [T; N]);
+    type PayloadRef<'a> = (usize, &'a [T; N]);
+    type Update = ();
+    type Query = ();
+    type Value = T;
+
+    fn new(payload: Self::Payload) -> Self {
+        assert!(payload.0 < N, "ID too large");
+        GCounterN {
+            id: payload.0,
+            counts: payload.1,
+        }
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.id, self.counts)
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.id, &self.counts)
+    }
+    fn add(&mut self, _update: Self::Update) {
+        increment(&mut self.counts[self.id]);
+    }
+    fn le(&self, other: &Self) -> bool {
+        self.counts.iter().zip(&other.counts).all(|(x, y)| x <= y)
+    }
+    fn merge(&self, other: &Self) -> Self {
+        GCounterN {
+            id: self.id.min(other.id), // arbitrary, as for `GCounter`
+            counts: std::array::from_fn(|i| self.counts[i].max(other.counts[i])),
+        }
+    }
+    fn query(&self, _query: &Self::Query) -> Self::Value {
+        saturating_sum(&self.counts)
+    }
+}
+
+impl<const N: usize, T: Count> From<GCounterN<N, T>> for GCounter<T> {
+    fn from(g: GCounterN<N, T>) -> Self {
+        GCounter {
+            id: g.id,
+            counts: g.counts.to_vec(),
+        }
+    }
+}
+
+impl<const N: usize, T: Count> TryFrom<GCounter<T>> for GCounterN<N, T> {
+    type Error = &'static str;
+
+    fn try_from(g: GCounter<T>) -> Result<Self, Self::Error> {
+        let counts = g.counts.try_into().map_err(|_| "Incompatible lengths")?;
+        if g.id < N {
+            Ok(GCounterN { id: g.id, counts })
+        } else {
+            Err("ID too large")
+        }
+    }
+}
+
+/// The operation-based counterpart of a [`GCounter`](struct.GCounter.html)
+///
+/// # Panics
+///
+/// Like a `GCounter`, `update` panics if the local `id` isn't a valid index, and `effect` panics
+/// if an operation's `replica` isn't a valid index.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::{GCounterOp, OpGCounter, Operation};
+/// let mut x = OpGCounter::new((0, vec![0; 3]));
+/// assert_eq!(x.update(()), GCounterOp { replica: 0 });
+/// x.effect(&GCounterOp { replica: 2 });
+/// assert_eq!(x.payload(), (0, vec![1, 0, 1]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpGCounter<T: Count = u64> {
+    /// The index for this local `OpGCounter` where all increments originate
+    pub id: usize,
+    /// The vector of counts
+    pub counts: Vec<T>,
+}
+
+/// An operation on an [`OpGCounter`](struct.OpGCounter.html): increment the count for `replica`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GCounterOp {
+    /// The index of the replica where this increment originated
+    pub replica: usize,
+}
+
+impl OpGCounter {
+    /// Create an `OpGCounter` of `u64` counts, as with
+    /// [`Operation::new`](../operation/trait.Operation.html#tymethod.new)
+    ///
+    /// As with [`GCounter::new`](struct.GCounter.html#method.new), name the type for other counts.
+    #[must_use]
+    pub fn new(payload: (usize, Vec<u64>)) -> Self {
+        <Self as Operation>::new(payload)
+    }
+}
+
+impl<T: Count> Operation for OpGCounter<T> {
+    type Payload = (usize, Vec<T>);
+    type Update = ();
+    type Op = GCounterOp;
+
+    fn new(payload: Self::Payload) -> Self {
+        OpGCounter {
+            id: payload.0,
+            counts: payload.1,
+        }
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.id, self.counts.clone())
+    }
+    fn prepare(&self, _update: Self::Update) -> Self::Op {
+        GCounterOp { replica: self.id }
+    }
+    fn effect(&mut self, op: &Self::Op) {
+        increment(&mut self.counts[op.replica]);
+    }
+}
+
+impl<T: Count> From<GCounter<T>> for OpGCounter<T> {
+    fn from(g: GCounter<T>) -> Self {
+        OpGCounter {
+            id: g.id,
+            counts: g.counts,
+        }
+    }
+}
+
+impl<T: Count> From<OpGCounter<T>> for GCounter<T> {
+    fn from(g: OpGCounter<T>) -> Self {
+        GCounter {
+            id: g.id,
+            counts: g.counts,
+        }
+    }
+}
+
+/// A `GCounter` as it comes off the wire, before we've checked that its `id` is valid
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedGCounter<T> {
+    id: usize,
+    counts: Vec<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Count> TryFrom<UncheckedGCounter<T>> for GCounter<T> {
+    type Error = &'static str;
+
+    fn try_from(unchecked: UncheckedGCounter<T>) -> Result<Self, Self::Error> {
+        if unchecked.id < unchecked.counts.len() {
+            Ok(GCounter {
+                id: unchecked.id,
+                counts: unchecked.counts,
+            })
+        } else {
+            Err("ID too large")
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+static MAX_SIZE: usize = 100;
+
+/// A strategy for arbitrary `GCounter`s with exactly `n` counts
+///
+/// # Panics
+///
+/// When generating values, if `n` is zero (there must be a valid `id`)
+#[cfg(any(test, feature = "testing"))]
+pub fn sized<T: Count + proptest::arbitrary::Arbitrary>(
+    n: usize,
+) -> impl proptest::strategy::Strategy<Value = GCounter<T>> {
+    use proptest::prelude::*;
+    prop::collection::vec(any::<T>(), n)
+        .prop_flat_map(|counts| {
+            let len = counts.len();
+            (0..len, Just(counts))
+        })
+        .prop_map(|(id, counts)| GCounter { id, counts })
+}
+
+/// A strategy for arbitrary pairs of `GCounter`s of the same size, i.e. pairs that can be merged
+#[cfg(any(test, feature = "testing"))]
+pub fn two() -> impl proptest::strategy::Strategy<Value = (GCounter, GCounter)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n)))
+}
+
+/// A strategy for arbitrary triples of `GCounter`s of the same size, i.e. triples that can be
+/// merged
+#[cfg(any(test, feature = "testing"))]
+pub fn three() -> impl proptest::strategy::Strategy<Value = (GCounter, GCounter, GCounter)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n), sized(n)))
+}
+
+/// Arbitrary `GCounterN`s
+///
+/// # Panics
+///
+/// When generating values, if `N` is zero (there must be a valid `id`)
+#[cfg(any(test, feature = "testing"))]
+impl<const N: usize, T: Count + proptest::arbitrary::Arbitrary> proptest::arbitrary::Arbitrary
+    for GCounterN<N, T>
+{
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        sized(N)
+            .prop_map(|g| GCounterN::try_from(g).unwrap())
+            .boxed()
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<T: Count + proptest::arbitrary::Arbitrary> proptest::arbitrary::Arbitrary for GCounter<T> {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (1..MAX_SIZE).prop_flat_map(sized).boxed()
     }
 }
 
@@ -116,26 +422,99 @@ mod tests {
     use crate::properties::grow;
     use proptest::prelude::*;
 
-    static MAX_SIZE: usize = 100;
+    fn cvrdt_and_update() -> impl Strategy<Value = (GCounter, ())> {
+        (any::<GCounter>(), Just(()))
+    }
 
-    fn sized(n: usize) -> impl Strategy<Value = GCounter> {
-        prop::collection::vec(any::<u64>(), n)
-            .prop_flat_map(|counts| {
-                let len = counts.len();
-                (0..len, Just(counts))
-            })
-            .prop_map(|(id, counts)| GCounter { id, counts })
+    grow!(two, three, cvrdt_and_update);
+
+    mod narrow {
+        use super::*;
+
+        fn any_narrow() -> impl Strategy<Value = GCounterN<4, u32>> {
+            any::<GCounterN<4, u32>>()
+        }
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (GCounterN<4, u32>, ())> {
+            (any_narrow(), Just(()))
+        }
+
+        grow!(any_narrow, cvrdt_and_update);
     }
 
-    fn two() -> impl Strategy<Value = (GCounter, GCounter)> {
-        (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n)))
+    #[test]
+    #[should_panic(expected = "Count overflow")]
+    fn add_panics_on_overflow() {
+        let mut x = GCounter::<u8>::new((0, vec![u8::MAX]));
+        x.add(());
     }
-    fn three() -> impl Strategy<Value = (GCounter, GCounter, GCounter)> {
-        (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n), sized(n)))
+
+    #[test]
+    #[should_panic(expected = "Count overflow")]
+    fn effect_panics_on_overflow() {
+        let mut x = OpGCounter::<u8>::new((0, vec![0, u8::MAX]));
+        x.effect(&GCounterOp { replica: 1 });
     }
-    fn cvrdt_and_update() -> impl Strategy<Value = (GCounter, ())> {
-        (1..MAX_SIZE).prop_flat_map(sized).prop_map(|g| (g, ()))
+
+    #[test]
+    fn query_saturates() {
+        let x = GCounter::<u8>::new((0, vec![200, 100]));
+        assert_eq!(x.query(&()), u8::MAX);
     }
 
-    grow!(two, three, cvrdt_and_update);
+    mod fixed {
+        use super::*;
+
+        fn any_fixed() -> impl Strategy<Value = GCounterN<4>> {
+            any::<GCounterN<4>>()
+        }
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (GCounterN<4>, ())> {
+            (any_fixed(), Just(()))
+        }
+
+        grow!(any_fixed, cvrdt_and_update);
+
+        proptest! {
+            #[test]
+            fn agrees_with_g_counter(x in any_fixed(), y in any_fixed()) {
+                let (gx, gy) = (GCounter::from(x.clone()), GCounter::from(y.clone()));
+                prop_assert_eq!(GCounter::from(x.merge(&y)), gx.merge(&gy));
+                prop_assert_eq!(x.le(&y), gx.le(&gy));
+                prop_assert_eq!(GCounterN::try_from(gx), Ok(x));
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        proptest! {
+            #[test]
+            fn serde_round_trip(x in any_fixed()) {
+                let json = serde_json::to_string(&x).unwrap();
+                prop_assert_eq!(&json, &serde_json::to_string(&GCounter::from(x.clone())).unwrap());
+                prop_assert_eq!(serde_json::from_str::<GCounterN<4>>(&json).unwrap(), x);
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn serde_rejects_wrong_length() {
+            assert!(serde_json::from_str::<GCounterN<2>>(r#"{"id":0,"counts":[0,0,0]}"#).is_err());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<GCounter>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            let y: GCounter = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(x.payload(), y.payload());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_invalid_id() {
+        assert!(serde_json::from_str::<GCounter>(r#"{"id":3,"counts":[0,0,0]}"#).is_err());
+    }
 }
@@ -1,3 +1,5 @@
+use crate::operation::Operation;
+use crate::set::{Backed, Set};
 use crate::traits::Grow;
 use std::collections::HashSet;
 use std::hash::Hash;
@@ -23,14 +25,34 @@ use std::hash::Hash;
 /// let z = GSet::new("8675309abcdefg".chars().collect());
 /// assert_eq!(x.merge(&y.merge(&z)).payload(), x.merge(&y).merge(&z).payload());
 /// ```
-#[derive(Debug, Clone)]
-pub struct GSet<X: Clone + Eq + Hash> {
+///
+/// The contents live in a [`HashSet`] by default; any other [`Set`](../set/trait.Set.html) works
+/// too, e.g. a `BTreeSet` for elements that aren't `Hash`, or to iterate in order:
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use cvrdt_exposition::{Grow, GSet};
+/// let mut y = GSet::<String, BTreeSet<_>>::new(BTreeSet::new());
+/// y.add("b".to_string());
+/// y.add("a".to_string());
+/// assert_eq!(y.values.iter().collect::<Vec<_>>(), ["a", "b"]);
+/// // struct literals work too
+/// let z = GSet { values: BTreeSet::from(["a".to_string()]) };
+/// assert!(z.le(&y));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GSet<X: Clone + Eq, S: Set<Element = X> = HashSet<X>> {
     /// The contents of this set
-    pub values: HashSet<X>,
+    pub values: <X as Backed<S>>::Set,
 }
 
-impl<X: Clone + Eq + Hash> Grow for GSet<X> {
-    type Payload = HashSet<X>;
+impl<X: Clone + Eq, S: Set<Element = X>> Grow for GSet<X, S> {
+    type Payload = S;
+    type PayloadRef<'a>
+        = &'a S
+    where
+        Self: 'a;
     type Update = X;
     type Query = X;
     type Value = bool;
@@ -41,6 +63,9 @@ impl<X: Clone + Eq + Hash> Grow for GSet<X> {
     fn payload(&self) -> Self::Payload {
         self.values.clone()
     }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        &self.values
+    }
     fn add(&mut self, update: Self::Update) {
         self.values.insert(update);
     }
@@ -48,25 +73,143 @@ impl<X: Clone + Eq + Hash> Grow for GSet<X> {
         self.values.is_subset(&other.values)
     }
     fn merge(&self, other: &Self) -> Self {
-        GSet {
-            values: self.values.union(&other.values).cloned().collect(),
-        }
+        GSet::new(self.values.union(&other.values))
     }
     fn query(&self, query: &Self::Query) -> Self::Value {
         self.values.contains(query)
     }
 }
 
+impl<X: Clone + Eq, S: Set<Element = X>> GSet<X, S> {
+    /// Iterate over the elements of this set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cvrdt_exposition::GSet;
+    /// let x: GSet<u8> = [1, 2, 2].into_iter().collect();
+    /// assert_eq!(x.iter().sum::<u8>(), 3);
+    /// ```
+    pub fn iter(&self) -> S::Iter<'_> {
+        self.values.iter()
+    }
+
+    /// The number of elements in this set
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Is this set empty?
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl<'a, X: Clone + Eq, S: Set<Element = X>> IntoIterator for &'a GSet<X, S> {
+    type Item = &'a X;
+    type IntoIter = S::Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> IntoIterator for GSet<X, S> {
+    type Item = X;
+    type IntoIter = S::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> FromIterator<X> for GSet<X, S> {
+    fn from_iter<I: IntoIterator<Item = X>>(iter: I) -> Self {
+        GSet::new(iter.into_iter().collect())
+    }
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> Extend<X> for GSet<X, S> {
+    fn extend<I: IntoIterator<Item = X>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+/// The operation-based counterpart of a [`GSet`](struct.GSet.html), whose operations are the
+/// elements to add
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use cvrdt_exposition::{OpGSet, Operation};
+/// let mut x = OpGSet::new(HashSet::new());
+/// assert_eq!(x.update('a'), 'a');
+/// x.effect(&'b');
+/// assert_eq!(x.payload(), "ab".chars().collect());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpGSet<X: Clone + Eq + Hash> {
+    /// The contents of this set
+    pub values: HashSet<X>,
+}
+
+impl<X: Clone + Eq + Hash> Operation for OpGSet<X> {
+    type Payload = HashSet<X>;
+    type Update = X;
+    type Op = X;
+
+    fn new(payload: Self::Payload) -> Self {
+        OpGSet { values: payload }
+    }
+    fn payload(&self) -> Self::Payload {
+        self.values.clone()
+    }
+    fn prepare(&self, update: Self::Update) -> Self::Op {
+        update
+    }
+    fn effect(&mut self, op: &Self::Op) {
+        self.values.insert(op.clone());
+    }
+}
+
+impl<X: Clone + Eq + Hash> From<GSet<X>> for OpGSet<X> {
+    fn from(g: GSet<X>) -> Self {
+        OpGSet { values: g.values }
+    }
+}
+
+impl<X: Clone + Eq + Hash> From<OpGSet<X>> for GSet<X> {
+    fn from(g: OpGSet<X>) -> Self {
+        GSet::new(g.values)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<X: std::fmt::Debug + Clone + Eq, S: Set<Element = X> + proptest::arbitrary::Arbitrary>
+    proptest::arbitrary::Arbitrary for GSet<X, S>
+{
+    type Parameters = S::Parameters;
+    type Strategy = proptest::strategy::Map<proptest::arbitrary::StrategyFor<S>, fn(S) -> Self>;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any_with::<S>(args).prop_map(GSet::new)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::properties::grow;
     use proptest::prelude::*;
 
-    static MAX_SIZE: usize = 100;
-
     fn cvrdt() -> impl Strategy<Value = GSet<String>> {
-        prop::collection::hash_set(any::<String>(), 0..MAX_SIZE).prop_map(|values| GSet { values })
+        any::<GSet<String>>()
     }
 
     fn cvrdt_and_update() -> impl Strategy<Value = (GSet<String>, String)> {
@@ -74,4 +217,65 @@ mod tests {
     }
 
     grow!(cvrdt, cvrdt_and_update);
+
+    /// The same laws, for each of the other backends
+    macro_rules! backend {
+        ($name:ident, $($set:ident)::+) => {
+            mod $name {
+                use super::*;
+                use $($set)::+ as Backend;
+
+                fn cvrdt() -> impl Strategy<Value = GSet<i8, Backend<i8>>> {
+                    any::<GSet<i8, Backend<i8>>>()
+                }
+
+                fn cvrdt_and_update() -> impl Strategy<Value = (GSet<i8, Backend<i8>>, i8)> {
+                    (cvrdt(), any::<i8>())
+                }
+
+                grow!(cvrdt, cvrdt_and_update);
+            }
+        };
+    }
+
+    backend!(b_tree_set, std::collections::BTreeSet);
+    backend!(sorted_vec, crate::set::SortedVec);
+
+    proptest! {
+        #[test]
+        fn iteration_agrees_with_query(x in any::<GSet<i8>>(), ys in any::<Vec<i8>>()) {
+            let live: HashSet<i8> = x.iter().copied().collect();
+            prop_assert_eq!(live.len(), x.len());
+            prop_assert_eq!(live.is_empty(), x.is_empty());
+            for y in x.values.iter().chain(&ys) {
+                prop_assert_eq!(live.contains(y), x.query(y));
+            }
+            for y in &x {
+                prop_assert!(x.query(y));
+            }
+            prop_assert_eq!(x.into_iter().collect::<HashSet<_>>(), live);
+        }
+        #[test]
+        fn bulk_updates_agree_with_add(x in any::<GSet<i8>>(), ys in any::<Vec<i8>>()) {
+            let mut extended = x.clone();
+            extended.extend(ys.iter().copied());
+            let mut added = x;
+            for &y in &ys {
+                added.add(y);
+            }
+            prop_assert_eq!(extended, added);
+            let collected: GSet<i8> = ys.iter().copied().collect();
+            prop_assert_eq!(collected, GSet::new(ys.into_iter().collect()));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<GSet<String>>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            let y: GSet<String> = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(x.payload(), y.payload());
+        }
+    }
 }
@@ -0,0 +1,411 @@
+use crate::count::saturating_sum;
+use crate::traits::Grow;
+use crate::GCounter;
+
+/// A replicated histogram with fixed bucket boundaries, for estimating quantiles
+///
+/// The `bounds` split observations into buckets: the first bucket holds observations at most
+/// `bounds[0]`, the next those above `bounds[0]` and at most `bounds[1]`, and so on, with a final
+/// bucket for anything above the last bound. Each bucket is a
+/// [`GCounter`](../g_counter/struct.GCounter.html) with a slot per replica, and merging merges the
+/// buckets pairwise, just as for a `GCounter`.
+///
+/// Querying with a quantile `q` between 0 and 1 returns the [`Bucket`](struct.Bucket.html) holding
+/// the `q`-quantile of all observations (by the nearest-rank method), or `None` if there are no
+/// observations. This is only as precise as the buckets are narrow, but it never needs to keep the
+/// observations themselves.
+///
+/// # Panics
+///
+/// `new` panics if the `bounds` aren't strictly increasing, if there isn't exactly one more bucket
+/// than bounds, if the buckets don't all have the same `id` and number of slots, or if the `id`
+/// isn't a valid index:
+///
+/// ```should_panic
+/// // this will panic
+/// use cvrdt_exposition::{Grow, Histogram};
+/// let x = Histogram::new((0, vec![100, 10], vec![vec![0; 2]; 3]));
+/// ```
+///
+/// `le` and `merge` panic if two `Histogram`s have different `bounds` or numbers of slots, and
+/// `query` panics if the quantile isn't between 0 and 1 (inclusive).
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::histogram::Bucket;
+/// use cvrdt_exposition::{Grow, Histogram};
+/// // latencies in milliseconds
+/// let bounds = vec![10, 50, 100];
+/// let mut x = Histogram::with_bounds(0, 2, bounds.clone());
+/// let mut y = Histogram::with_bounds(1, 2, bounds);
+/// for ms in [3, 8, 20, 45] {
+///     x.add(ms);
+/// }
+/// for ms in [60, 250] {
+///     y.add(ms);
+/// }
+/// let z = x.merge(&y);
+/// assert_eq!(z.counts(), [2, 2, 1, 1]);
+/// assert_eq!(z.query(&0.5), Some(Bucket { lower: Some(10), upper: Some(50) }));
+/// assert_eq!(z.query(&0.99), Some(Bucket { lower: Some(100), upper: None }));
+/// assert_eq!(Histogram::<u32>::with_bounds(0, 2, vec![10]).query(&0.5), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedHistogram<X>"))]
+pub struct Histogram<X: Clone + Ord> {
+    /// The strictly increasing (inclusive) upper bounds of every bucket but the last
+    pub bounds: Vec<X>,
+    /// The per-replica counts of observations in each bucket
+    pub buckets: Vec<GCounter>,
+}
+
+/// A bucket of a [`Histogram`](struct.Histogram.html), holding the observations above `lower`
+/// and at most `upper`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bucket<X> {
+    /// The (exclusive) lower bound of this bucket, or `None` for the first bucket
+    pub lower: Option<X>,
+    /// The (inclusive) upper bound of this bucket, or `None` for the last bucket
+    pub upper: Option<X>,
+}
+
+impl<X: Clone + Ord> Histogram<X> {
+    /// An empty histogram for replica `id` of `replicas`, with the given `bounds`
+    ///
+    /// # Panics
+    ///
+    /// As for `new`
+    #[must_use]
+    pub fn with_bounds(id: usize, replicas: usize, bounds: Vec<X>) -> Self {
+        let buckets = vec![vec![0; replicas]; bounds.len() + 1];
+        Histogram::new((id, bounds, buckets))
+    }
+
+    /// The number of observations in each bucket
+    #[must_use]
+    pub fn counts(&self) -> Vec<u64> {
+        self.buckets.iter().map(|b| b.query(&())).collect()
+    }
+
+    /// The total number of observations, stopping at `u64::MAX`
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        saturating_sum(&self.counts())
+    }
+
+    /// The bucket with index `i`
+    ///
+    /// # Panics
+    ///
+    /// If there is no such bucket
+    #[must_use]
+    pub fn bucket(&self, i: usize) -> Bucket<X> {
+        assert!(i < self.buckets.len(), "No such bucket");
+        Bucket {
+            lower: i.checked_sub(1).map(|j| self.bounds[j].clone()),
+            upper: self.bounds.get(i).cloned(),
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), &'static str> {
+        let (id, replicas) = match self.buckets.first() {
+            Some(b) => (b.id, b.counts.len()),
+            None => return Err("Incompatible bounds & buckets lengths"),
+        };
+        if !self.bounds.windows(2).all(|w| w[0] < w[1]) {
+            Err("Bounds not strictly increasing")
+        } else if self.buckets.len() != self.bounds.len() + 1 {
+            Err("Incompatible bounds & buckets lengths")
+        } else if self
+            .buckets
+            .iter()
+            .any(|b| b.id != id || b.counts.len() != replicas)
+        {
+            Err("Incompatible buckets")
+        } else if id >= replicas {
+            Err("ID too large")
+        } else {
+            Ok(())
+        }
+    }
+    fn consistent(&self) {
+        if let Err(msg) = self.check() {
+            panic!("{msg}");
+        }
+    }
+    fn compatible_bounds(&self, other: &Self) {
+        assert!(self.bounds == other.bounds, "Incompatible bounds");
+    }
+}
+
+impl<X: Clone + Ord> Grow for Histogram<X> {
+    type Payload = (usize, Vec<X>, Vec<Vec<u64>>);
+    type PayloadRef<'a>
+        = (usize, &'a [X], &'a [GCounter])
+    where
+        X: 'a;
+    type Update = X;
+    type Query = f64;
+    type Value = Option<Bucket<X>>;
+
+    fn new(payload: Self::Payload) -> Self {
+        let h = Histogram {
+            bounds: payload.1,
+            buckets: payload
+                .2
+                .into_iter()
+                .map(|counts| GCounter::new((payload.0, counts)))
+                .collect(),
+        };
+        h.consistent();
+        h
+    }
+    fn payload(&self) -> Self::Payload {
+        (
+            self.buckets[0].id,
+            self.bounds.clone(),
+            self.buckets.iter().map(|b| b.counts.clone()).collect(),
+        )
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.buckets[0].id, &self.bounds, &self.buckets)
+    }
+    fn add(&mut self, update: Self::Update) {
+        let i = self.bounds.partition_point(|b| *b < update);
+        self.buckets[i].add(());
+    }
+    fn le(&self, other: &Self) -> bool {
+        self.compatible_bounds(other);
+        self.buckets
+            .iter()
+            .zip(&other.buckets)
+            .all(|(x, y)| x.le(y))
+    }
+    fn compatible(&self, other: &Self) -> bool {
+        self.bounds == other.bounds
+            && self
+                .buckets
+                .iter()
+                .zip(&other.buckets)
+                .all(|(x, y)| x.compatible(y))
+    }
+    fn merge(&self, other: &Self) -> Self {
+        self.compatible_bounds(other);
+        Histogram {
+            bounds: self.bounds.clone(),
+            buckets: self
+                .buckets
+                .iter()
+                .zip(&other.buckets)
+                .map(|(x, y)| x.merge(y))
+                .collect(),
+        }
+    }
+    fn query(&self, query: &Self::Query) -> Self::Value {
+        assert!((0.0..=1.0).contains(query), "Quantile out of range");
+        let counts = self.counts();
+        let total = saturating_sum(&counts);
+        if total == 0 {
+            return None;
+        }
+        // nearest rank: the smallest observation with at least `q` of all observations at or below
+        let rank = ((query * total as f64).ceil() as u64).clamp(1, total);
+        let mut seen = 0u64;
+        let i = counts
+            .iter()
+            .position(|&c| {
+                seen = seen.saturating_add(c);
+                seen >= rank
+            })
+            .expect("rank is at most total");
+        Some(self.bucket(i))
+    }
+}
+
+/// A `Histogram` as it comes off the wire, before we've checked that it's consistent
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedHistogram<X> {
+    bounds: Vec<X>,
+    buckets: Vec<GCounter>,
+}
+
+#[cfg(feature = "serde")]
+impl<X: Clone + Ord> TryFrom<UncheckedHistogram<X>> for Histogram<X> {
+    type Error = &'static str;
+
+    fn try_from(unchecked: UncheckedHistogram<X>) -> Result<Self, Self::Error> {
+        let h = Histogram {
+            bounds: unchecked.bounds,
+            buckets: unchecked.buckets,
+        };
+        h.check()?;
+        Ok(h)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+static MAX_SIZE: usize = 20;
+
+/// A strategy for arbitrary `Histogram`s with the given `bounds` and exactly `n` replicas
+///
+/// # Panics
+///
+/// When generating values, if `n` is zero (there must be a valid `id`) or the `bounds` aren't
+/// strictly increasing
+#[cfg(any(test, feature = "testing"))]
+pub fn sized<X: Clone + Ord + std::fmt::Debug>(
+    bounds: Vec<X>,
+    n: usize,
+) -> impl proptest::strategy::Strategy<Value = Histogram<X>> {
+    use proptest::prelude::*;
+    let buckets = bounds.len() + 1;
+    (
+        0..n,
+        prop::collection::vec(prop::collection::vec(0..=u64::from(u32::MAX), n), buckets),
+    )
+        .prop_map(move |(id, counts)| Histogram::new((id, bounds.clone(), counts)))
+}
+
+#[cfg(any(test, feature = "testing"))]
+fn bounds_and_size<X: proptest::arbitrary::Arbitrary + Clone + Ord>(
+) -> impl proptest::strategy::Strategy<Value = (Vec<X>, usize)> {
+    use proptest::prelude::*;
+    (
+        prop::collection::btree_set(any::<X>(), 0..MAX_SIZE),
+        1..MAX_SIZE,
+    )
+        .prop_map(|(bounds, n)| (bounds.into_iter().collect(), n))
+}
+
+/// A strategy for arbitrary pairs of `Histogram`s with the same bounds and size, i.e. pairs that
+/// can be merged
+#[cfg(any(test, feature = "testing"))]
+pub fn two<X: proptest::arbitrary::Arbitrary + Clone + Ord>(
+) -> impl proptest::strategy::Strategy<Value = (Histogram<X>, Histogram<X>)> {
+    use proptest::prelude::*;
+    bounds_and_size().prop_flat_map(|(bounds, n)| (sized(bounds.clone(), n), sized(bounds, n)))
+}
+
+/// A strategy for arbitrary triples of `Histogram`s with the same bounds and size, i.e. triples
+/// that can be merged
+#[cfg(any(test, feature = "testing"))]
+pub fn three<X: proptest::arbitrary::Arbitrary + Clone + Ord>(
+) -> impl proptest::strategy::Strategy<Value = (Histogram<X>, Histogram<X>, Histogram<X>)> {
+    use proptest::prelude::*;
+    bounds_and_size().prop_flat_map(|(bounds, n)| {
+        (
+            sized(bounds.clone(), n),
+            sized(bounds.clone(), n),
+            sized(bounds, n),
+        )
+    })
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<X: proptest::arbitrary::Arbitrary + Clone + Ord + 'static> proptest::arbitrary::Arbitrary
+    for Histogram<X>
+{
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        bounds_and_size()
+            .prop_flat_map(|(bounds, n)| sized(bounds, n))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::properties::grow;
+    use proptest::prelude::*;
+
+    fn two_u16() -> impl Strategy<Value = (Histogram<u16>, Histogram<u16>)> {
+        two()
+    }
+
+    fn three_u16() -> impl Strategy<Value = (Histogram<u16>, Histogram<u16>, Histogram<u16>)> {
+        three()
+    }
+
+    fn cvrdt_and_update() -> impl Strategy<Value = (Histogram<u16>, u16)> {
+        (any::<Histogram<u16>>(), any::<u16>())
+    }
+
+    grow!(two_u16, three_u16, cvrdt_and_update);
+
+    proptest! {
+        #[test]
+        fn quantiles_bracket_the_exact_quantile(
+            bounds in prop::collection::btree_set(any::<u16>(), 0..10),
+            observations in prop::collection::vec((0..3_usize, any::<u16>()), 1..100),
+            q in 0.0..=1.0_f64,
+        ) {
+            let bounds: Vec<_> = bounds.into_iter().collect();
+            let mut replicas: Vec<_> =
+                (0..3).map(|id| Histogram::with_bounds(id, 3, bounds.clone())).collect();
+            for &(id, x) in &observations {
+                replicas[id].add(x);
+            }
+            let merged = replicas.iter().fold(replicas[0].clone(), |x, y| x.merge(y));
+            prop_assert_eq!(merged.count(), observations.len() as u64);
+
+            let mut sorted: Vec<_> = observations.iter().map(|&(_, x)| x).collect();
+            sorted.sort_unstable();
+            let n = sorted.len();
+            let rank = ((q * n as f64).ceil() as usize).clamp(1, n);
+            let exact = sorted[rank - 1];
+            let bucket = merged.query(&q).unwrap();
+            prop_assert!(bucket.lower.is_none_or(|lower| lower < exact));
+            prop_assert!(bucket.upper.is_none_or(|upper| exact <= upper));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Quantile out of range")]
+    fn query_rejects_nan() {
+        let _ = Histogram::with_bounds(0, 1, vec![1, 2]).query(&f64::NAN);
+    }
+
+    #[test]
+    fn huge_counts_saturate() {
+        let h = Histogram::new((0, vec![1u8], vec![vec![u64::MAX], vec![u64::MAX]]));
+        assert_eq!(h.count(), u64::MAX);
+        assert_eq!(h.query(&1.0).unwrap().upper, Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Incompatible bounds")]
+    fn merge_rejects_different_bounds() {
+        let x = Histogram::with_bounds(0, 1, vec![1, 2]);
+        let y = Histogram::with_bounds(0, 1, vec![1, 3]);
+        let _ = x.merge(&y);
+    }
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<Histogram<u16>>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Histogram<u16>>(&json).unwrap(), x);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_inconsistent() {
+        for json in [
+            r#"{"bounds":[2,1],"buckets":[{"id":0,"counts":[0]},{"id":0,"counts":[0]},{"id":0,"counts":[0]}]}"#,
+            r#"{"bounds":[1],"buckets":[{"id":0,"counts":[0]}]}"#,
+            r#"{"bounds":[1],"buckets":[{"id":0,"counts":[0]},{"id":0,"counts":[0,0]}]}"#,
+            r#"{"bounds":[],"buckets":[]}"#,
+        ] {
+            assert!(serde_json::from_str::<Histogram<u16>>(json).is_err());
+        }
+    }
+}
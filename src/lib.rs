@@ -57,10 +57,11 @@
 //! ## How `cvrdt-exposition` verifies properties
 //!
 //! In the absence of using formal methods like [TLA+](https://learntla.com/introduction/) (see also Hillel Wayne's [excellent book!](https://learntla.com/book/)), we resort to property-based testing via the [`proptest` crate](https://crates.io/crates/proptest).
-//! This excellent crate is listed as a `dev-dependency` in [`cvrdt-exposition`'s `Cargo.tml` file](https://github.com/genos/cvrdt-exposition/blob/main/Cargo.toml), so if you can just use the stuff in this library (although [you shouldn't!](do-not-use-this)) without pulling in an extra dependency.
+//! This excellent crate is listed as a `dev-dependency` (and an optional dependency, enabled by the `testing` feature) in [`cvrdt-exposition`'s `Cargo.tml` file](https://github.com/genos/cvrdt-exposition/blob/main/Cargo.toml), so if you can just use the stuff in this library (although [you shouldn't!](do-not-use-this)) without pulling in an extra dependency.
 //! That said, I highly recommend learning to use `proptest`, [`quickcheck`](https://crates.io/crates/quickcheck), or some other [property testing framework](https://crates.io/search?q=property%20testing) for Rust.
 //!
-//! In the `cfg(test)`-only [`properties` module](https://github.com/genos/cvrdt/blob/main/src/properties.rs), `cvrdt-exposition` defines macros for automating checking `CvRDT` properties.
+//! In the [`properties` module](https://github.com/genos/cvrdt/blob/main/src/properties.rs), `cvrdt-exposition` defines macros for automating checking `CvRDT` properties.
+//! The module is public behind the `testing` feature, so downstream crates can check their own `CvRDTs` with a single line like `grow!(arb_cvrdt, arb_cvrdt_and_addend);`.
 //! For instance, given a function `arb_cvrdt2` that yields an arbitrary pair of elements of our `CvRDT` type, `proptest`'s `proptest!` macro allows us to test that our `CvRDT`'s merge function is commutative via:
 //!
 //! ```ignore
@@ -91,30 +92,63 @@
 /// Our two traits defining `CvRDTs`
 pub mod traits;
 
+/// Grow-only accumulators of fixed-point or floating-point amounts
+pub mod accumulator;
+/// Causal delivery of operations for operation-based CRDTs
+pub mod broadcast;
+/// Causality tracking: dots, version vectors, and dotted version vectors
+pub mod causality;
+/// Unsigned integer types for the counts in our counters
+pub mod count;
+/// Reconciling sets via Merkle digests, shipping only the elements that differ
+pub mod digest;
 /// Grow-Only Counter
 pub mod g_counter;
 /// Grow-Only Set
 pub mod g_set;
+/// Histograms with fixed buckets, for estimating quantiles
+pub mod histogram;
 /// Last-Writer-Wins Register
 pub mod lww_register;
+/// Mean (average) of observations, composed from an accumulator and a counter
+pub mod mean_counter;
+/// Push-pull anti-entropy over TCP
+#[cfg(feature = "net")]
+pub mod net;
 /// The simplest `CvRDT` example: a boolean flag that, once true, can never revert to false
 pub mod one_way_boolean;
+/// Operation-based counterparts of our `CvRDTs`
+pub mod operation;
+/// Durable replicas, via a write-ahead log and snapshots
+pub mod persist;
 /// Positive-Negative Counter
 pub mod pn_counter;
+/// Replica handles for the vectorized counters, with stable ids and retirement
+pub mod replica;
+/// Backing stores for the set `CvRDTs`
+pub mod set;
+/// Deterministic simulation of replicas over an unreliable network
+pub mod simulation;
 /// Two-Phase Set
 pub mod two_phase_set;
+/// Compact, versioned binary encoding
+pub mod wire;
 
 /// Top-level re-exports for CRDT structures and traits
 pub use crate::{
-    g_counter::GCounter,
-    g_set::GSet,
-    lww_register::LWWRegister,
+    accumulator::Accumulator,
+    g_counter::{GCounter, GCounterN, GCounterOp, OpGCounter},
+    g_set::{GSet, OpGSet},
+    histogram::Histogram,
+    lww_register::{LWWRegister, LWWRegisterOp, OpLWWRegister},
+    mean_counter::MeanCounter,
     one_way_boolean::OneWayBoolean,
-    pn_counter::PNCounter,
+    operation::Operation,
+    pn_counter::{OpPNCounter, PNCounter, PNCounterN, PNCounterOp, PNCounterUpdate},
     traits::{Grow, Shrink},
-    two_phase_set::TwoPhaseSet,
+    two_phase_set::{CollectableTwoPhaseSet, OpTwoPhaseSet, TwoPhaseSet, TwoPhaseSetOp},
 };
 
 /// PBT for `CvRDT` properties
-#[cfg(test)]
-pub(crate) mod properties;
+#[cfg(any(test, feature = "testing"))]
+pub mod properties;
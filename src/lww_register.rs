@@ -1,18 +1,19 @@
+use crate::operation::Operation;
 use crate::traits::Grow;
-use std::time::Instant;
+use std::time::SystemTime;
 
 /// A last-write-wins register
 ///
 /// # Panics
 ///
 /// Any attempt to `add` a new element to this register will panic if the register's `timestamp` is
-/// greater than `Instant::now()` (no time-traveling allowed) at the time of calling `add`:
+/// greater than `SystemTime::now()` (no time-traveling allowed) at the time of calling `add`:
 ///
 /// ```should_panic
 /// // this will panic
-/// use std::time::{Duration, Instant};
+/// use std::time::{Duration, SystemTime};
 /// use cvrdt_exposition::{Grow, LWWRegister};
-/// let mut x = LWWRegister::new(('a', Instant::now() + Duration::from_secs(1729)));
+/// let mut x = LWWRegister::new(('a', SystemTime::now() + Duration::from_secs(1729)));
 /// x.add('b');
 /// ```
 ///
@@ -20,36 +21,45 @@ use std::time::Instant;
 ///
 /// In the [comprehensive study paper](https://hal.inria.fr/inria-00555588/), timestamps are
 /// unsigned integers, whereas we use
-/// [`std::time::Instant`s](https://doc.rust-lang.org/std/time/struct.Instant.html).
+/// [`std::time::SystemTime`s](https://doc.rust-lang.org/std/time/struct.SystemTime.html).
+/// Unlike [`std::time::Instant`s](https://doc.rust-lang.org/std/time/struct.Instant.html),
+/// these are meaningful across processes and machines (so they can be serialized and sent to
+/// other replicas), but they are _not_ guaranteed to be monotonic; if the system clock jumps
+/// backwards, `add` will panic as described above.
 ///
 /// # Examples
 ///
 /// Example usage, including demonstrating some properties:
 ///
 /// ```
-/// use std::time::Instant;
+/// use std::time::SystemTime;
 /// use cvrdt_exposition::{Grow, LWWRegister};
-/// let mut x = LWWRegister::new(('a', Instant::now()));
+/// let mut x = LWWRegister::new(('a', SystemTime::now()));
 /// x.add('b');
 /// x.add('c');
 /// assert_eq!(x.query(&()), 'c');
-/// let y = LWWRegister::new(('z', Instant::now()));
+/// let y = LWWRegister::new(('z', SystemTime::now()));
 /// assert!(x.le(&y));
 /// let z = x.merge(&y);
 /// assert_eq!(y.merge(&x).payload(), z.payload());
 /// assert_eq!(z.query(&()), 'z');
 /// assert_eq!(z.payload().0, 'z');
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LWWRegister<X: Clone + Eq> {
     /// The value saved in this register
     pub value: X,
     /// The time when this register was last saved
-    pub timestamp: Instant,
+    pub timestamp: SystemTime,
 }
 
 impl<X: Clone + Eq> Grow for LWWRegister<X> {
-    type Payload = (X, Instant);
+    type Payload = (X, SystemTime);
+    type PayloadRef<'a>
+        = (&'a X, SystemTime)
+    where
+        X: 'a;
     type Update = X;
     type Query = ();
     type Value = X;
@@ -63,8 +73,11 @@ impl<X: Clone + Eq> Grow for LWWRegister<X> {
     fn payload(&self) -> Self::Payload {
         (self.value.clone(), self.timestamp)
     }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (&self.value, self.timestamp)
+    }
     fn add(&mut self, update: Self::Update) {
-        let now = Instant::now();
+        let now = SystemTime::now();
         assert!(self.timestamp <= now, "Time should be monotonic");
         self.value = update;
         self.timestamp = now;
@@ -84,6 +97,113 @@ impl<X: Clone + Eq> Grow for LWWRegister<X> {
     }
 }
 
+/// The operation-based counterpart of an [`LWWRegister`](struct.LWWRegister.html)
+///
+/// # Panics
+///
+/// Like an `LWWRegister`, preparing an update panics if the register's `timestamp` is greater
+/// than `SystemTime::now()`.
+///
+/// # Notes
+///
+/// An operation only takes effect if its timestamp is _strictly_ greater than the register's, so
+/// (as with merging `LWWRegister`s) two writes with the same timestamp resolve according to
+/// delivery order.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::SystemTime;
+/// use cvrdt_exposition::{OpLWWRegister, Operation};
+/// let mut x = OpLWWRegister::new(('a', SystemTime::now()));
+/// let op = x.update('b');
+/// let mut y = OpLWWRegister::new(('z', SystemTime::UNIX_EPOCH));
+/// y.effect(&op);
+/// assert_eq!(y.payload(), x.payload());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpLWWRegister<X: Clone + Eq> {
+    /// The value saved in this register
+    pub value: X,
+    /// The time when this register was last saved
+    pub timestamp: SystemTime,
+}
+
+/// An operation on an [`OpLWWRegister`](struct.OpLWWRegister.html): write `value` at `timestamp`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LWWRegisterOp<X> {
+    /// The value to write
+    pub value: X,
+    /// The time when the value was written at its source replica
+    pub timestamp: SystemTime,
+}
+
+impl<X: Clone + Eq> Operation for OpLWWRegister<X> {
+    type Payload = (X, SystemTime);
+    type Update = X;
+    type Op = LWWRegisterOp<X>;
+
+    fn new(payload: Self::Payload) -> Self {
+        OpLWWRegister {
+            value: payload.0,
+            timestamp: payload.1,
+        }
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.value.clone(), self.timestamp)
+    }
+    fn prepare(&self, update: Self::Update) -> Self::Op {
+        let now = SystemTime::now();
+        assert!(self.timestamp <= now, "Time should be monotonic");
+        LWWRegisterOp {
+            value: update,
+            timestamp: now,
+        }
+    }
+    fn effect(&mut self, op: &Self::Op) {
+        if self.timestamp < op.timestamp {
+            self.value = op.value.clone();
+            self.timestamp = op.timestamp;
+        }
+    }
+}
+
+impl<X: Clone + Eq> From<LWWRegister<X>> for OpLWWRegister<X> {
+    fn from(r: LWWRegister<X>) -> Self {
+        OpLWWRegister {
+            value: r.value,
+            timestamp: r.timestamp,
+        }
+    }
+}
+
+impl<X: Clone + Eq> From<OpLWWRegister<X>> for LWWRegister<X> {
+    fn from(r: OpLWWRegister<X>) -> Self {
+        LWWRegister {
+            value: r.value,
+            timestamp: r.timestamp,
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<X: proptest::arbitrary::Arbitrary + Clone + Eq> proptest::arbitrary::Arbitrary
+    for LWWRegister<X>
+{
+    type Parameters = X::Parameters;
+    type Strategy =
+        proptest::strategy::Map<proptest::arbitrary::StrategyFor<X>, fn(X) -> LWWRegister<X>>;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any_with::<X>(args).prop_map(|value| LWWRegister {
+            value,
+            timestamp: SystemTime::now(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,10 +211,7 @@ mod tests {
     use proptest::prelude::*;
 
     fn cvrdt() -> impl Strategy<Value = LWWRegister<String>> {
-        any::<String>().prop_map(|value| LWWRegister {
-            value,
-            timestamp: Instant::now(),
-        })
+        any::<LWWRegister<String>>()
     }
 
     fn cvrdt_and_update() -> impl Strategy<Value = (LWWRegister<String>, String)> {
@@ -102,4 +219,14 @@ mod tests {
     }
 
     grow!(cvrdt, cvrdt_and_update);
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<LWWRegister<String>>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            let y: LWWRegister<String> = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(x.payload(), y.payload());
+        }
+    }
 }
@@ -0,0 +1,305 @@
+use crate::accumulator::{Accumulator, Amount};
+use crate::traits::Grow;
+use crate::GCounter;
+
+/// A replicated mean, composed of an [`Accumulator`](../accumulator/struct.Accumulator.html) of
+/// observations and a [`GCounter`](../g_counter/struct.GCounter.html) of how many there were
+///
+/// Each update records one observation, adding it to this replica's `sum` slot and incrementing
+/// its `count` slot. Merging merges the `sum`s and the `count`s separately, taking the maximum of
+/// each slot just like a `GCounter`. Since only one replica ever updates a given slot, its sum and
+/// count only grow together, so the merged slots always describe the same observations.
+///
+/// # Panics
+///
+/// Like a [`PNCounter`](../pn_counter/struct.PNCounter.html), `new` panics if the `sum` and
+/// `count` disagree on the `id` or the number of slots, or if the `id` isn't a valid index, and
+/// `le` and `merge` panic if two `MeanCounter`s have different numbers of slots:
+///
+/// ```should_panic
+/// // this will panic
+/// use cvrdt_exposition::accumulator::Decimal;
+/// use cvrdt_exposition::{Grow, MeanCounter};
+/// let x = MeanCounter::<Decimal<2>>::new((0, vec![Decimal::ZERO; 2], vec![0; 3]));
+/// ```
+///
+/// `add` (and `query`) also panic if a sum overflows, as for an `Accumulator`.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::accumulator::Float;
+/// use cvrdt_exposition::{Grow, MeanCounter};
+/// let seconds = |x: f64| Float::try_from(x).unwrap();
+/// let mut x = MeanCounter::new((0, vec![Float::ZERO; 2], vec![0; 2]));
+/// x.add(seconds(0.5));
+/// x.add(seconds(1.5));
+/// let mut y = MeanCounter::new((1, vec![Float::ZERO; 2], vec![0; 2]));
+/// y.add(seconds(4.0));
+/// let mean = x.merge(&y).query(&());
+/// assert_eq!(mean.sum.get(), 6.0);
+/// assert_eq!(mean.count, 3);
+/// assert_eq!(mean.mean(), Some(2.0));
+/// assert_eq!(x.payload(), (0, vec![seconds(2.0), Float::ZERO], vec![2, 0]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedMeanCounter<A>"))]
+pub struct MeanCounter<A: Amount> {
+    /// The per-replica sums of observations
+    pub sum: Accumulator<A>,
+    /// The per-replica numbers of observations
+    pub count: GCounter,
+}
+
+/// The result of querying a [`MeanCounter`](struct.MeanCounter.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mean<A: Amount> {
+    /// The sum of all observations
+    pub sum: A,
+    /// The number of observations
+    pub count: u64,
+}
+
+impl<A: Amount> Mean<A> {
+    /// The mean of all observations, or `None` if there weren't any
+    #[must_use]
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum.to_f64() / self.count as f64)
+    }
+}
+
+impl<A: Amount> MeanCounter<A> {
+    pub(crate) fn check(&self) -> Result<(), &'static str> {
+        if self.sum.totals.len() != self.count.counts.len() {
+            Err("Incompatible sum & count lengths")
+        } else if self.sum.id != self.count.id {
+            Err("Incompatible sum & count IDs")
+        } else if self.sum.id >= self.sum.totals.len() {
+            Err("ID too large")
+        } else {
+            Ok(())
+        }
+    }
+    fn consistent(&self) {
+        if let Err(msg) = self.check() {
+            panic!("{msg}");
+        }
+    }
+}
+
+impl<A: Amount> Grow for MeanCounter<A> {
+    type Payload = (usize, Vec<A>, Vec<u64>);
+    type PayloadRef<'a> = (usize, &'a [A], &'a [u64]);
+    type Update = A;
+    type Query = ();
+    type Value = Mean<A>;
+
+    fn new(payload: Self::Payload) -> Self {
+        let mc = MeanCounter {
+            sum: Accumulator::new((payload.0, payload.1)),
+            count: GCounter::new((payload.0, payload.2)),
+        };
+        mc.consistent();
+        mc
+    }
+    fn payload(&self) -> Self::Payload {
+        (
+            self.sum.id,
+            self.sum.totals.clone(),
+            self.count.counts.clone(),
+        )
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.sum.id, &self.sum.totals, &self.count.counts)
+    }
+    fn add(&mut self, update: Self::Update) {
+        self.sum.add(update);
+        self.count.add(());
+    }
+    fn le(&self, other: &Self) -> bool {
+        self.sum.le(&other.sum) && self.count.le(&other.count)
+    }
+    fn compatible(&self, other: &Self) -> bool {
+        self.sum.compatible(&other.sum) && self.count.compatible(&other.count)
+    }
+    fn merge(&self, other: &Self) -> Self {
+        MeanCounter {
+            sum: self.sum.merge(&other.sum),
+            count: self.count.merge(&other.count),
+        }
+    }
+    fn query(&self, query: &Self::Query) -> Self::Value {
+        Mean {
+            sum: self.sum.query(query),
+            count: self.count.query(query),
+        }
+    }
+}
+
+/// A `MeanCounter` as it comes off the wire, before we've checked that it's consistent
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedMeanCounter<A: Amount> {
+    sum: Accumulator<A>,
+    count: GCounter,
+}
+
+#[cfg(feature = "serde")]
+impl<A: Amount> TryFrom<UncheckedMeanCounter<A>> for MeanCounter<A> {
+    type Error = &'static str;
+
+    fn try_from(unchecked: UncheckedMeanCounter<A>) -> Result<Self, Self::Error> {
+        let mc = MeanCounter {
+            sum: unchecked.sum,
+            count: unchecked.count,
+        };
+        mc.check()?;
+        Ok(mc)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+static MAX_SIZE: usize = 100;
+
+/// A strategy for arbitrary `MeanCounter`s with exactly `n` slots
+///
+/// # Panics
+///
+/// When generating values, if `n` is zero (there must be a valid `id`)
+#[cfg(any(test, feature = "testing"))]
+pub fn sized<A: Amount + proptest::arbitrary::Arbitrary>(
+    n: usize,
+) -> impl proptest::strategy::Strategy<Value = MeanCounter<A>> {
+    use proptest::prelude::*;
+    (
+        prop::collection::vec(any::<A>(), n),
+        prop::collection::vec(0..=u64::from(u32::MAX), n),
+    )
+        .prop_flat_map(|(totals, counts)| {
+            let len = totals.len();
+            (0..len, Just(totals), Just(counts))
+        })
+        .prop_map(|(id, totals, counts)| MeanCounter {
+            sum: Accumulator { id, totals },
+            count: GCounter { id, counts },
+        })
+}
+
+/// A strategy for arbitrary pairs of `MeanCounter`s of the same size, i.e. pairs that can be
+/// merged
+#[cfg(any(test, feature = "testing"))]
+pub fn two<A: Amount + proptest::arbitrary::Arbitrary>(
+) -> impl proptest::strategy::Strategy<Value = (MeanCounter<A>, MeanCounter<A>)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n)))
+}
+
+/// A strategy for arbitrary triples of `MeanCounter`s of the same size, i.e. triples that can be
+/// merged
+#[cfg(any(test, feature = "testing"))]
+pub fn three<A: Amount + proptest::arbitrary::Arbitrary>(
+) -> impl proptest::strategy::Strategy<Value = (MeanCounter<A>, MeanCounter<A>, MeanCounter<A>)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n), sized(n)))
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<A: Amount + proptest::arbitrary::Arbitrary> proptest::arbitrary::Arbitrary for MeanCounter<A> {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (1..MAX_SIZE).prop_flat_map(sized).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accumulator::{Decimal, Float};
+    use crate::properties::grow;
+    use proptest::prelude::*;
+
+    type Cents = MeanCounter<Decimal<2>>;
+
+    fn two_cents() -> impl Strategy<Value = (Cents, Cents)> {
+        two()
+    }
+
+    fn three_cents() -> impl Strategy<Value = (Cents, Cents, Cents)> {
+        three()
+    }
+
+    fn cvrdt_and_update() -> impl Strategy<Value = (Cents, Decimal<2>)> {
+        (any::<Cents>(), any::<Decimal<2>>())
+    }
+
+    grow!(two_cents, three_cents, cvrdt_and_update);
+
+    mod float {
+        use super::*;
+
+        fn two_floats() -> impl Strategy<Value = (MeanCounter<Float>, MeanCounter<Float>)> {
+            two()
+        }
+
+        fn three_floats(
+        ) -> impl Strategy<Value = (MeanCounter<Float>, MeanCounter<Float>, MeanCounter<Float>)>
+        {
+            three()
+        }
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (MeanCounter<Float>, Float)> {
+            (any::<MeanCounter<Float>>(), any::<Float>())
+        }
+
+        grow!(two_floats, three_floats, cvrdt_and_update);
+    }
+
+    proptest! {
+        #[test]
+        fn mean_of_all_observations(
+            observations in prop::collection::vec((0..4_usize, 0..=1_000_000_u64), 0..100),
+        ) {
+            let mut replicas: Vec<Cents> = (0..4)
+                .map(|id| MeanCounter::new((id, vec![Decimal::ZERO; 4], vec![0; 4])))
+                .collect();
+            for &(id, units) in &observations {
+                replicas[id].add(Decimal::from_units(units));
+            }
+            let merged = replicas.iter().fold(replicas[0].clone(), |x, y| x.merge(y));
+            let mean = merged.query(&());
+            let sum: u64 = observations.iter().map(|&(_, units)| units).sum();
+            prop_assert_eq!(mean.sum.units(), sum);
+            prop_assert_eq!(mean.count, observations.len() as u64);
+            if observations.is_empty() {
+                prop_assert_eq!(mean.mean(), None);
+            } else {
+                let expected = sum as f64 / 100.0 / observations.len() as f64;
+                prop_assert!((mean.mean().unwrap() - expected).abs() <= 1e-9 * expected.max(1.0));
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<Cents>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Cents>(&json).unwrap(), x);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_inconsistent() {
+        for json in [
+            r#"{"sum":{"id":0,"totals":[0]},"count":{"id":0,"counts":[0,0]}}"#,
+            r#"{"sum":{"id":0,"totals":[0,0]},"count":{"id":1,"counts":[0,0]}}"#,
+        ] {
+            assert!(serde_json::from_str::<Cents>(json).is_err());
+        }
+    }
+}
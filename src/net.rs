@@ -0,0 +1,302 @@
+//! Push-pull anti-entropy over TCP
+//!
+//! Each [`Node`](struct.Node.html) holds one replica of a `CvRDT`, listens for connections from
+//! its peers, and every so often picks a few peers at random and _exchanges_ states with them: it
+//! sends its own state (encoded with the [`wire` format](../wire/index.html)), the peer merges it
+//! and replies with the result, and the node merges that in turn. As long as the peers form a
+//! connected graph, every update eventually reaches every replica.
+//!
+//! Every message is a frame: a little-endian `u32` length followed by that many bytes of
+//! [`wire::encode`](../wire/fn.encode.html) output.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::net::{Config, Node};
+//! use cvrdt_exposition::{GSet, Grow};
+//! use std::collections::HashSet;
+//! let empty = || GSet::new(HashSet::new());
+//! let x = Node::bind("127.0.0.1:0", empty(), Config::default()).unwrap();
+//! let y = Node::bind("127.0.0.1:0", empty(), Config::default()).unwrap();
+//! x.update(|s| s.add(1u8));
+//! y.update(|s| s.add(2u8));
+//! x.exchange(y.local_addr()).unwrap();
+//! assert_eq!(x.state(), y.state());
+//! assert_eq!(x.shutdown().values, HashSet::from([1, 2]));
+//! let _ = y.shutdown();
+//! ```
+
+use crate::simulation::SplitMix64;
+use crate::traits::Grow;
+use crate::wire::{self, Wire};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The largest frame we're willing to read, so that a bad length can't exhaust our memory
+pub const MAX_FRAME: u32 = 1 << 26;
+
+/// How often a [`Node`](struct.Node.html) gossips, and with whom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// The addresses of other nodes; more can be added with
+    /// [`Node::add_peer`](struct.Node.html#method.add_peer)
+    pub peers: Vec<SocketAddr>,
+    /// How many peers to exchange states with each round
+    pub fanout: usize,
+    /// How long to wait between rounds
+    pub interval: Duration,
+    /// How long to wait on a peer before giving up on an exchange
+    pub timeout: Duration,
+    /// Seed for choosing peers
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            peers: Vec::new(),
+            fanout: 1,
+            interval: Duration::from_millis(100),
+            timeout: Duration::from_secs(1),
+            seed: 0,
+        }
+    }
+}
+
+/// Write `cvrdt` to `stream` as a single frame
+///
+/// # Errors
+///
+/// If writing to `stream` fails, or the encoded state is larger than [`MAX_FRAME`](constant.MAX_FRAME.html)
+pub fn write_frame<T: Wire>(stream: &mut impl Write, cvrdt: &T) -> io::Result<()> {
+    let bytes = wire::encode(cvrdt);
+    let len = u32::try_from(bytes.len())
+        .ok()
+        .filter(|&n| n <= MAX_FRAME)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Frame too large"))?;
+    stream.write_all(&len.to_le_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+/// Read a single frame from `stream`
+///
+/// # Errors
+///
+/// If reading from `stream` fails, the frame is larger than [`MAX_FRAME`](constant.MAX_FRAME.html),
+/// or its contents don't [`decode`](../wire/fn.decode.html) (reported as
+/// [`InvalidData`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData))
+pub fn read_frame<T: Wire>(stream: &mut impl Read) -> io::Result<T> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Frame too large",
+        ));
+    }
+    let mut bytes = vec![0; len as usize];
+    stream.read_exact(&mut bytes)?;
+    wire::decode(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// State shared between a [`Node`](struct.Node.html) and its threads
+#[derive(Debug)]
+struct Shared<G> {
+    state: Mutex<G>,
+    peers: Mutex<Vec<SocketAddr>>,
+    shutdown: AtomicBool,
+    timeout: Duration,
+}
+
+impl<G: Grow + Wire> Shared<G> {
+    fn state(&self) -> MutexGuard<'_, G> {
+        self.state.lock().expect("Poisoned state")
+    }
+
+    /// Merge `other` into our state, returning the result
+    ///
+    /// A peer's state that can't be merged with ours (e.g. a `GCounter` of a different size) is
+    /// rejected as [`InvalidData`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData)
+    /// rather than panicking, which would poison our state for good.
+    fn merge(&self, other: &G) -> io::Result<G> {
+        let mut state = self.state();
+        if !state.compatible(other) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Incompatible state",
+            ));
+        }
+        *state = state.merge(other);
+        Ok(state.clone())
+    }
+
+    /// The initiating side of an exchange
+    fn exchange(&self, peer: SocketAddr) -> io::Result<()> {
+        let mut stream = TcpStream::connect_timeout(&peer, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let ours = self.state().clone();
+        write_frame(&mut stream, &ours)?;
+        let theirs = read_frame(&mut stream)?;
+        self.merge(&theirs).map(|_| ())
+    }
+
+    /// The responding side of an exchange
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let theirs = read_frame(&mut stream)?;
+        let merged = self.merge(&theirs)?;
+        write_frame(&mut stream, &merged)
+    }
+}
+
+/// A replica of a `CvRDT` that gossips with its peers over TCP
+///
+/// Each incoming exchange is handled on its own thread, so a slow peer only holds up itself.
+/// Dropping a `Node` stops it, just like [`shutdown`](#method.shutdown) (but without returning
+/// its final state).
+#[derive(Debug)]
+pub struct Node<G: Grow + Wire + Send + 'static> {
+    shared: Arc<Shared<G>>,
+    addr: SocketAddr,
+    listener: Option<JoinHandle<()>>,
+    gossip: Option<JoinHandle<()>>,
+}
+
+impl<G: Grow + Wire + Send + 'static> Node<G> {
+    /// Start a node holding `initial`, listening on `addr`, and gossiping per `config`
+    ///
+    /// Bind to port 0 to have the operating system pick a free port; see
+    /// [`local_addr`](#method.local_addr).
+    ///
+    /// # Errors
+    ///
+    /// If we can't listen on `addr`
+    pub fn bind(addr: impl ToSocketAddrs, initial: G, config: Config) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            state: Mutex::new(initial),
+            peers: Mutex::new(config.peers),
+            shutdown: AtomicBool::new(false),
+            timeout: config.timeout,
+        });
+        let listener = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shared.shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // a misbehaving peer shouldn't take us down; it'll just miss this exchange
+                    if let Ok(stream) = stream {
+                        let shared = Arc::clone(&shared);
+                        thread::spawn(move || {
+                            let _ = shared.respond(stream);
+                        });
+                    }
+                }
+            })
+        };
+        let gossip = {
+            let shared = Arc::clone(&shared);
+            let (fanout, interval) = (config.fanout, config.interval);
+            let mut rng = SplitMix64::new(config.seed);
+            thread::spawn(move || loop {
+                thread::park_timeout(interval);
+                if shared.shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                let mut peers = shared.peers.lock().expect("Poisoned peers").clone();
+                // a partial Fisher-Yates shuffle picks `fanout` distinct peers
+                for i in 0..fanout.min(peers.len()) {
+                    let j = i + rng.below(peers.len() - i);
+                    peers.swap(i, j);
+                    // unreachable peers are expected; we'll try again next round
+                    let _ = shared.exchange(peers[i]);
+                }
+            })
+        };
+        Ok(Node {
+            shared,
+            addr,
+            listener: Some(listener),
+            gossip: Some(gossip),
+        })
+    }
+
+    /// The address this node listens on
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Gossip with `peer` too, from now on
+    pub fn add_peer(&self, peer: SocketAddr) {
+        let mut peers = self.shared.peers.lock().expect("Poisoned peers");
+        if peer != self.addr && !peers.contains(&peer) {
+            peers.push(peer);
+        }
+    }
+
+    /// A copy of this node's current state
+    #[must_use]
+    pub fn state(&self) -> G {
+        self.shared.state().clone()
+    }
+
+    /// Perform a local update on this node's state
+    ///
+    /// # Parameters
+    ///
+    /// - a borrowed reference to `self`
+    /// - `f`, which updates the state in place (e.g. via [`add`](../traits/trait.Grow.html#tymethod.add))
+    pub fn update(&self, f: impl FnOnce(&mut G)) {
+        f(&mut self.shared.state());
+    }
+
+    /// Exchange states with `peer` right now, rather than waiting for the next round
+    ///
+    /// # Errors
+    ///
+    /// If we can't reach `peer`, or it doesn't reply with a valid state
+    pub fn exchange(&self, peer: SocketAddr) -> io::Result<()> {
+        self.shared.exchange(peer)
+    }
+
+    /// Stop gossiping and listening, and return this node's final state
+    #[must_use]
+    pub fn shutdown(mut self) -> G {
+        self.stop();
+        self.shared.state().clone()
+    }
+
+    /// Signal our threads to stop, and wait for them
+    ///
+    /// Exchanges already in progress finish on their own threads, within the configured timeout.
+    fn stop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        if let Some(gossip) = self.gossip.take() {
+            gossip.thread().unpark();
+            let _ = gossip.join();
+        }
+        if let Some(listener) = self.listener.take() {
+            // wake the listener up, so that it sees the flag
+            let _ = TcpStream::connect_timeout(&self.addr, self.shared.timeout);
+            let _ = listener.join();
+        }
+    }
+}
+
+impl<G: Grow + Wire + Send + 'static> Drop for Node<G> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -18,7 +18,8 @@ use crate::traits::Grow;
 ///     assert!(y.le(&x));
 /// }
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OneWayBoolean {
     /// The internal state of a `OneWayBoolean` is a single boolean flag
     pub flag: bool,
@@ -26,6 +27,7 @@ pub struct OneWayBoolean {
 
 impl Grow for OneWayBoolean {
     type Payload = bool;
+    type PayloadRef<'a> = bool;
     type Update = ();
     type Query = ();
     type Value = bool;
@@ -36,6 +38,9 @@ impl Grow for OneWayBoolean {
     fn payload(&self) -> Self::Payload {
         self.flag
     }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        self.flag
+    }
     fn add(&mut self, _update: Self::Update) {
         self.flag = true;
     }
@@ -52,6 +57,18 @@ impl Grow for OneWayBoolean {
     }
 }
 
+#[cfg(any(test, feature = "testing"))]
+impl proptest::arbitrary::Arbitrary for OneWayBoolean {
+    type Parameters = ();
+    type Strategy =
+        proptest::strategy::Map<proptest::arbitrary::StrategyFor<bool>, fn(bool) -> OneWayBoolean>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any::<bool>().prop_map(|flag| OneWayBoolean { flag })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,7 +76,7 @@ mod tests {
     use proptest::prelude::*;
 
     fn cvrdt() -> impl Strategy<Value = OneWayBoolean> {
-        any::<bool>().prop_map(|flag| OneWayBoolean { flag })
+        any::<OneWayBoolean>()
     }
 
     fn cvrdt_and_update() -> impl Strategy<Value = (OneWayBoolean, ())> {
@@ -67,4 +84,14 @@ mod tests {
     }
 
     grow!(cvrdt, cvrdt_and_update);
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<OneWayBoolean>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            let y: OneWayBoolean = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(x.payload(), y.payload());
+        }
+    }
 }
@@ -0,0 +1,340 @@
+//! Operation-based CRDTs a.k.a. commutative replicated data types a.k.a. `CmRDTs`
+//!
+//! Where a `CvRDT` replica ships its whole state and merges whatever it receives, a `CmRDT`
+//! replica ships individual _operations_. Per the [comprehensive study
+//! paper](https://hal.inria.fr/inria-00555588/), an update happens in two phases:
+//!
+//! 1. _prepare_, which runs only at the replica where the update originates (the _source_),
+//!    has no side effects, and turns the update into an operation to broadcast
+//! 2. _effect_, which runs at every replica (the source included) when the operation is
+//!    delivered, and changes the local state
+//!
+//! Every replica must eventually deliver every operation exactly once, and in causal order (if an
+//! operation was prepared at a replica that had already seen another, every replica delivers that
+//! other one first). Concurrent operations can be delivered in any order, so their effects must
+//! commute.
+//!
+//! Each `CmRDT` in this library is the counterpart of a `CvRDT`, with the same
+//! [`Payload`](trait.Operation.html#associatedtype.Payload), and `From` conversions in both
+//! directions. Delivering the operations from several replicas, in any causal order, yields the
+//! same payload as merging the states of those replicas.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::{GCounter, Grow, OpGCounter, Operation};
+//! let mut x = OpGCounter::new((0, vec![0; 2]));
+//! let mut y = OpGCounter::new((1, vec![0; 2]));
+//! let ops = [x.update(()), y.update(()), y.update(())];
+//! let mut z = OpGCounter::new((0, vec![0; 2]));
+//! for op in ops.iter().rev() {
+//!     z.effect(op);
+//! }
+//! let (x, y) = (GCounter::from(x), GCounter::from(y));
+//! assert_eq!(z.payload(), x.merge(&y).payload());
+//! ```
+
+/// `CmRDTs`, i.e. data types replicated by broadcasting operations
+pub trait Operation {
+    /// The internal state of our `CmRDT`; sufficient to build a new copy via
+    /// [`new`](#tymethod.new)
+    type Payload: Eq;
+
+    /// Message asking the source replica to update our internal state
+    type Update;
+
+    /// The operation broadcast to (and delivered at) every replica
+    type Op: Clone;
+
+    /// Create a new version of our data structure from the given
+    /// [`Payload`](#associatedtype.Payload)
+    ///
+    /// # Parameters
+    ///
+    /// - `payload`: a payload which fully specifies all information needed to instantiate our data
+    ///   structure
+    ///
+    /// # Returns
+    ///
+    /// A new instance of this `CmRDT`
+    #[must_use]
+    fn new(payload: Self::Payload) -> Self;
+
+    /// Retrieve the [`Payload`](#associatedtype.Payload) (internal state) of this `CmRDT`
+    ///
+    /// # Parameters
+    ///
+    /// - a borrowed reference to `self`
+    ///
+    /// # Returns
+    ///
+    /// The payload of this `CmRDT`
+    fn payload(&self) -> Self::Payload;
+
+    /// The _prepare_ phase: turn an update into an operation, at the source replica only
+    ///
+    /// # Parameters
+    ///
+    /// - a borrowed reference to `self`
+    /// - an [`Update`](#associatedtype.Update) message
+    ///
+    /// # Returns
+    ///
+    /// The [`Op`](#associatedtype.Op) to broadcast to every replica
+    fn prepare(&self, update: Self::Update) -> Self::Op;
+
+    /// The _effect_ phase: apply a delivered operation, at every replica
+    ///
+    /// # Parameters
+    ///
+    /// - a mutably borrowed reference to `self`
+    /// - a borrowed reference to the delivered [`Op`](#associatedtype.Op)
+    ///
+    /// # Returns
+    ///
+    /// Nothing; this data structure is updated in-place
+    ///
+    /// # Notes
+    /// Effects of concurrent operations must commute
+    fn effect(&mut self, op: &Self::Op);
+
+    /// Perform an update at the source replica: [`prepare`](#tymethod.prepare) it, then apply its
+    /// [`effect`](#tymethod.effect) locally
+    ///
+    /// # Parameters
+    ///
+    /// - a mutably borrowed reference to `self`
+    /// - an [`Update`](#associatedtype.Update) message
+    ///
+    /// # Returns
+    ///
+    /// The [`Op`](#associatedtype.Op) to broadcast to every other replica
+    fn update(&mut self, update: Self::Update) -> Self::Op {
+        let op = self.prepare(update);
+        self.effect(&op);
+        op
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        g_counter, pn_counter, GCounter, GSet, Grow, LWWRegister, LWWRegisterOp, OpGCounter,
+        OpGSet, OpLWWRegister, OpPNCounter, OpTwoPhaseSet, PNCounter, PNCounterUpdate, Shrink,
+        TwoPhaseSet, TwoPhaseSetOp,
+    };
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+    use std::time::{Duration, SystemTime};
+
+    static MAX_REPLICAS: usize = 8;
+    static MAX_UPDATES: usize = 8;
+
+    /// One delivery order for every replica's operations: an arbitrary interleaving that keeps each
+    /// replica's operations in the order they were prepared. Our replicas never hear from each
+    /// other before delivery, so this is exactly a causal order.
+    fn interleave<T: Clone>(logs: &[Vec<T>], picks: &[prop::sample::Index]) -> Vec<T> {
+        let mut next = vec![0; logs.len()];
+        let mut delivered = Vec::new();
+        for pick in picks.iter().cycle() {
+            let pending: Vec<usize> = (0..logs.len())
+                .filter(|&i| next[i] < logs[i].len())
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            let i = pending[pick.index(pending.len())];
+            delivered.push(logs[i][next[i]].clone());
+            next[i] += 1;
+        }
+        delivered
+    }
+
+    fn picks() -> impl Strategy<Value = Vec<prop::sample::Index>> {
+        prop::collection::vec(any::<prop::sample::Index>(), 1..64)
+    }
+
+    /// Merge all of `replicas` (which must be non-empty)
+    fn merge_all<G: Grow>(replicas: &[G]) -> G {
+        replicas[1..]
+            .iter()
+            .fold(replicas[0].clone(), |acc, r| acc.merge(r))
+    }
+
+    proptest! {
+        #[test]
+        fn g_counter_equivalence(
+            (base, updates) in (1..MAX_REPLICAS).prop_flat_map(|n| (
+                g_counter::sized::<u64>(n),
+                prop::collection::vec(0..MAX_UPDATES, n),
+            )),
+            picks in picks(),
+        ) {
+            let base = GCounter { counts: base.counts.iter().map(|c| c / 2).collect(), ..base };
+            let mut states = Vec::new();
+            let mut logs = Vec::new();
+            for (id, &k) in updates.iter().enumerate() {
+                let mut state = GCounter { id, ..base.clone() };
+                let mut op_based = OpGCounter::from(state.clone());
+                let mut log = Vec::new();
+                for _ in 0..k {
+                    state.add(());
+                    log.push(op_based.update(()));
+                }
+                prop_assert_eq!(&GCounter::from(op_based), &state);
+                states.push(state);
+                logs.push(log);
+            }
+            let mut replayed = OpGCounter::from(GCounter { id: 0, ..base });
+            for op in interleave(&logs, &picks) {
+                replayed.effect(&op);
+            }
+            prop_assert_eq!(replayed.payload(), merge_all(&states).payload());
+        }
+
+        #[test]
+        fn pn_counter_equivalence(
+            (base, updates) in (1..MAX_REPLICAS).prop_flat_map(|n| (
+                pn_counter::sized::<u64>(n),
+                prop::collection::vec(prop::collection::vec(any::<bool>(), 0..MAX_UPDATES), n),
+            )),
+            picks in picks(),
+        ) {
+            let base = PNCounter {
+                positive: base.positive.iter().map(|c| c / 2).collect(),
+                negative: base.negative.iter().map(|c| c / 2).collect(),
+                ..base
+            };
+            let mut states = Vec::new();
+            let mut logs = Vec::new();
+            for (id, increments) in updates.iter().enumerate() {
+                let mut state = PNCounter { id, ..base.clone() };
+                let mut op_based = OpPNCounter::from(state.clone());
+                let mut log = Vec::new();
+                for &increment in increments {
+                    if increment {
+                        state.add(());
+                        log.push(op_based.update(PNCounterUpdate::Increment));
+                    } else {
+                        state.del(());
+                        log.push(op_based.update(PNCounterUpdate::Decrement));
+                    }
+                }
+                prop_assert_eq!(&PNCounter::from(op_based), &state);
+                states.push(state);
+                logs.push(log);
+            }
+            let mut replayed = OpPNCounter::from(PNCounter { id: 0, ..base });
+            for op in interleave(&logs, &picks) {
+                replayed.effect(&op);
+            }
+            prop_assert_eq!(replayed.payload(), merge_all(&states).payload());
+        }
+
+        #[test]
+        fn g_set_equivalence(
+            base in any::<HashSet<u8>>(),
+            updates in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..MAX_UPDATES), 1..MAX_REPLICAS),
+            picks in picks(),
+        ) {
+            let base = GSet::new(base);
+            let mut states = Vec::new();
+            let mut logs = Vec::new();
+            for xs in &updates {
+                let mut state = base.clone();
+                let mut op_based = OpGSet::from(state.clone());
+                let mut log = Vec::new();
+                for &x in xs {
+                    state.add(x);
+                    log.push(op_based.update(x));
+                }
+                prop_assert_eq!(&GSet::from(op_based), &state);
+                states.push(state);
+                logs.push(log);
+            }
+            let mut replayed = OpGSet::from(base);
+            for op in interleave(&logs, &picks) {
+                replayed.effect(&op);
+            }
+            prop_assert_eq!(replayed.payload(), merge_all(&states).payload());
+        }
+
+        #[test]
+        fn two_phase_set_equivalence(
+            base in (
+                prop::collection::hash_set(0..16u8, 0..16),
+                prop::collection::hash_set(0..16u8, 0..16),
+            ),
+            updates in prop::collection::vec(
+                prop::collection::vec((any::<bool>(), 0..16u8), 0..MAX_UPDATES),
+                1..MAX_REPLICAS,
+            ),
+            picks in picks(),
+        ) {
+            let base = TwoPhaseSet::new(base);
+            let mut states = Vec::new();
+            let mut logs = Vec::new();
+            for xs in &updates {
+                let mut state = base.clone();
+                let mut op_based = OpTwoPhaseSet::from(state.clone());
+                let mut log = Vec::new();
+                for &(remove, x) in xs {
+                    if remove && state.query(&x) {
+                        state.del(x);
+                        log.push(op_based.update(TwoPhaseSetOp::Remove(x)));
+                    } else {
+                        state.add(x);
+                        log.push(op_based.update(TwoPhaseSetOp::Add(x)));
+                    }
+                }
+                prop_assert_eq!(&TwoPhaseSet::from(op_based), &state);
+                states.push(state);
+                logs.push(log);
+            }
+            let mut replayed = OpTwoPhaseSet::from(base);
+            for op in interleave(&logs, &picks) {
+                replayed.effect(&op);
+            }
+            prop_assert_eq!(replayed.payload(), merge_all(&states).payload());
+        }
+
+        #[test]
+        fn lww_register_equivalence(
+            (values, seconds) in prop::collection::vec(any::<char>(), 1..MAX_UPDATES * MAX_REPLICAS)
+                .prop_flat_map(|values| {
+                    let n = values.len();
+                    (Just(values), prop::collection::btree_set(1..u32::MAX, n)
+                            .prop_map(|s| s.into_iter().collect::<Vec<_>>())
+                            .prop_shuffle())
+                }),
+            replicas in 1..MAX_REPLICAS,
+            picks in picks(),
+        ) {
+            // Timestamps are distinct, so that ties can't make the outcome depend on delivery order
+            let base = OpLWWRegister::new(('?', SystemTime::UNIX_EPOCH));
+            let mut logs = vec![Vec::new(); replicas];
+            for (i, (value, secs)) in values.into_iter().zip(seconds).enumerate() {
+                logs[i % replicas].push(LWWRegisterOp {
+                    value,
+                    timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(u64::from(secs)),
+                });
+            }
+            let states: Vec<LWWRegister<char>> = logs
+                .iter()
+                .map(|log| {
+                    let mut op_based = base.clone();
+                    for op in log {
+                        op_based.effect(op);
+                    }
+                    op_based.into()
+                })
+                .collect();
+            let mut replayed = base;
+            for op in interleave(&logs, &picks) {
+                replayed.effect(&op);
+            }
+            prop_assert_eq!(replayed.payload(), merge_all(&states).payload());
+        }
+    }
+}
@@ -0,0 +1,564 @@
+//! Durable replicas: a write-ahead log plus periodic snapshots
+//!
+//! A [`Store`](struct.Store.html) keeps a replica in memory, and on disk in a directory holding
+//!
+//! - `snapshot`: a _generation_ number (a little-endian `u64`) followed by the
+//!   [`wire::encode`](../wire/fn.encode.html)-ed state as of that generation, and
+//! - `log.<generation>`: every `add`, `del`, and `merge` since that snapshot, one record each.
+//!
+//! Each record is framed as a little-endian `u32` length, a little-endian `u32` CRC-32 checksum,
+//! then that many bytes: a one-byte record kind and its contents. A crash can leave a partly
+//! written record at the end of the log; on recovery, the log is read up to the first record that's
+//! incomplete or fails its checksum, and truncated there.
+//!
+//! A snapshot is written to a temporary file and renamed into place, so it's never torn. It starts
+//! a new generation (and a new, empty log), so that records already included in a snapshot are
+//! never replayed on top of it, even if we crash before the old log is deleted.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::persist::Store;
+//! use cvrdt_exposition::{GCounter, Grow};
+//! let dir = std::env::temp_dir().join(format!("cvrdt-persist-doc-{}", std::process::id()));
+//! let _ = std::fs::remove_dir_all(&dir);
+//! let mut store = Store::open(&dir, GCounter::new((0, vec![0; 2])), 100).unwrap();
+//! store.add(()).unwrap();
+//! store.merge(&GCounter::new((1, vec![0, 2]))).unwrap();
+//! drop(store);
+//! // ...crash, restart...
+//! let store = Store::open(&dir, GCounter::new((0, vec![0; 2])), 100).unwrap();
+//! assert_eq!(store.state().payload(), (0, vec![1, 2]));
+//! std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use crate::count::Count;
+use crate::set::Set;
+use crate::traits::{Grow, Shrink};
+use crate::wire::{self, crc32, DecodeError, Element, Reader, Wire};
+use crate::{GCounter, GSet, LWWRegister, OneWayBoolean, PNCounter, TwoPhaseSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The kind byte of a record of an `add`
+const ADD: u8 = 1;
+/// The kind byte of a record of a `del`
+const DEL: u8 = 2;
+/// The kind byte of a record of a `merge` with a remote state
+const MERGE: u8 = 3;
+
+/// `CvRDTs` whose updates can be recorded in, and replayed from, a log
+pub trait Persist: Grow + Wire {
+    /// Record an `update` that was just [`add`](../traits/trait.Grow.html#tymethod.add)-ed to (or
+    /// [`del`](../traits/trait.Shrink.html#tymethod.del)-ed from) `self`
+    ///
+    /// Usually this is just the update itself, but e.g. an `LWWRegister` must also record the
+    /// timestamp `add` gave it, so that replaying yields the exact same state.
+    fn encode_update(&self, update: &Self::Update, buf: &mut Vec<u8>);
+
+    /// Replay an `add` recorded by [`encode_update`](#tymethod.encode_update)
+    ///
+    /// # Errors
+    ///
+    /// If the record doesn't describe a valid update
+    fn replay_add(&mut self, reader: &mut Reader<'_>) -> Result<(), DecodeError>;
+
+    /// Replay a `del` recorded by [`encode_update`](#tymethod.encode_update)
+    ///
+    /// # Errors
+    ///
+    /// If the record doesn't describe a valid update, or (by default) always, as grow-only
+    /// `CvRDTs` never log deletions
+    fn replay_del(&mut self, _reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        Err(DecodeError::Invalid("Deletion from a grow-only CvRDT"))
+    }
+}
+
+impl<T: Count + Element> Persist for GCounter<T> {
+    fn encode_update(&self, _update: &Self::Update, _buf: &mut Vec<u8>) {}
+    fn replay_add(&mut self, _reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        self.add(());
+        Ok(())
+    }
+}
+
+impl<T: Count + Element> Persist for PNCounter<T> {
+    fn encode_update(&self, _update: &Self::Update, _buf: &mut Vec<u8>) {}
+    fn replay_add(&mut self, _reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        self.add(());
+        Ok(())
+    }
+    fn replay_del(&mut self, _reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        self.del(());
+        Ok(())
+    }
+}
+
+impl<X: Element + Clone + Eq, S: Set<Element = X>> Persist for GSet<X, S> {
+    fn encode_update(&self, update: &Self::Update, buf: &mut Vec<u8>) {
+        update.encode(buf);
+    }
+    fn replay_add(&mut self, reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        self.add(X::decode(reader)?);
+        Ok(())
+    }
+}
+
+impl<X: Element + Clone + Eq, S: Set<Element = X>> Persist for TwoPhaseSet<X, S> {
+    fn encode_update(&self, update: &Self::Update, buf: &mut Vec<u8>) {
+        update.encode(buf);
+    }
+    fn replay_add(&mut self, reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        self.add(X::decode(reader)?);
+        Ok(())
+    }
+    fn replay_del(&mut self, reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        let x = X::decode(reader)?;
+        if !self.query(&x) {
+            return Err(DecodeError::Invalid("Deleted an element not in the set"));
+        }
+        self.del(x);
+        Ok(())
+    }
+}
+
+impl<X: Element + Clone + Eq> Persist for LWWRegister<X> {
+    fn encode_update(&self, _update: &Self::Update, buf: &mut Vec<u8>) {
+        // `self` now holds the update and the timestamp `add` gave it
+        self.encode_body(buf);
+    }
+    fn replay_add(&mut self, reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        *self = LWWRegister::decode_body(reader)?;
+        Ok(())
+    }
+}
+
+impl Persist for OneWayBoolean {
+    fn encode_update(&self, _update: &Self::Update, _buf: &mut Vec<u8>) {}
+    fn replay_add(&mut self, _reader: &mut Reader<'_>) -> Result<(), DecodeError> {
+        self.add(());
+        Ok(())
+    }
+}
+
+fn invalid(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// A replica backed by a write-ahead log and snapshots in a directory
+///
+/// Every change is applied in memory, then appended to the log and synced to disk before the
+/// method returns; if that fails, the change may not survive a crash.
+#[derive(Debug)]
+pub struct Store<G: Persist> {
+    state: G,
+    dir: PathBuf,
+    generation: u64,
+    log: File,
+    records: usize,
+    snapshot_every: usize,
+}
+
+impl<G: Persist> Store<G> {
+    /// Open (or create) the store in `dir`, recovering whatever state it holds
+    ///
+    /// # Parameters
+    ///
+    /// - `dir`: the directory holding the snapshot and log, created if missing
+    /// - `initial`: the state to start from if `dir` holds no snapshot yet
+    /// - `snapshot_every`: write a snapshot (and start a new, empty log) after this many records;
+    ///   zero means only when [`snapshot`](#method.snapshot) is called
+    ///
+    /// # Errors
+    ///
+    /// If the file system fails us, or the snapshot or a (complete, checksummed) log record is
+    /// invalid, e.g. because `dir` holds a different type of `CvRDT`
+    pub fn open(dir: impl AsRef<Path>, initial: G, snapshot_every: usize) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let (generation, mut state) = match fs::read(dir.join("snapshot")) {
+            Ok(bytes) if bytes.len() >= 8 => {
+                let (generation, encoded) = bytes.split_at(8);
+                let generation = u64::from_le_bytes(generation.try_into().expect("eight bytes"));
+                (generation, wire::decode(encoded).map_err(invalid)?)
+            }
+            Ok(_) => return Err(invalid("Snapshot truncated")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (0, initial),
+            Err(e) => return Err(e),
+        };
+        // logs from older generations are already included in the snapshot
+        for entry in fs::read_dir(&dir)? {
+            let name = entry?.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with("log.") && name != format!("log.{generation}") {
+                fs::remove_file(dir.join(&*name))?;
+            }
+        }
+        let path = dir.join(format!("log.{generation}"));
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+        let (valid, records) = replay(&mut state, &bytes)?;
+        if valid < bytes.len() {
+            // a torn (or corrupted) tail: drop it, so that new records follow the last good one
+            log.set_len(valid as u64)?;
+            log.sync_data()?;
+        }
+        Ok(Store {
+            state,
+            dir,
+            generation,
+            log,
+            records,
+            snapshot_every,
+        })
+    }
+
+    /// The current state
+    #[must_use]
+    pub fn state(&self) -> &G {
+        &self.state
+    }
+
+    /// How many records the log holds since the last snapshot
+    #[must_use]
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    fn append(&mut self, kind: u8, contents: &[u8]) -> io::Result<()> {
+        let mut record = Vec::with_capacity(contents.len() + 1);
+        record.push(kind);
+        record.extend_from_slice(contents);
+        let len = u32::try_from(record.len()).map_err(|_| invalid("Record too large"))?;
+        let mut frame = Vec::with_capacity(record.len() + 8);
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(&crc32(&record).to_le_bytes());
+        frame.extend_from_slice(&record);
+        self.log.write_all(&frame)?;
+        self.log.sync_data()?;
+        self.records += 1;
+        if self.snapshot_every > 0 && self.records >= self.snapshot_every {
+            self.snapshot()?;
+        }
+        Ok(())
+    }
+
+    /// [`add`](../traits/trait.Grow.html#tymethod.add) `update` and log it
+    ///
+    /// # Errors
+    ///
+    /// If writing to the log (or a periodic snapshot) fails
+    pub fn add(&mut self, update: G::Update) -> io::Result<()>
+    where
+        G::Update: Clone,
+    {
+        let mut buf = Vec::new();
+        self.state.add(update.clone());
+        self.state.encode_update(&update, &mut buf);
+        self.append(ADD, &buf)
+    }
+
+    /// Merge a remote state into ours and log it
+    ///
+    /// # Errors
+    ///
+    /// If writing to the log (or a periodic snapshot) fails
+    pub fn merge(&mut self, other: &G) -> io::Result<()> {
+        self.state = self.state.merge(other);
+        self.append(MERGE, &wire::encode(other))
+    }
+
+    /// Write a snapshot of the current state and start a new, empty log
+    ///
+    /// # Errors
+    ///
+    /// If the file system fails us
+    pub fn snapshot(&mut self) -> io::Result<()> {
+        let generation = self.generation + 1;
+        let mut bytes = generation.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&wire::encode(&self.state));
+        let tmp = self.dir.join("snapshot.tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.dir.join("snapshot"))?;
+        // make the rename itself durable
+        File::open(&self.dir)?.sync_all()?;
+        let old = self.dir.join(format!("log.{}", self.generation));
+        self.log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(self.dir.join(format!("log.{generation}")))?;
+        fs::remove_file(old)?;
+        self.generation = generation;
+        self.records = 0;
+        Ok(())
+    }
+}
+
+impl<G: Persist + Shrink> Store<G> {
+    /// [`del`](../traits/trait.Shrink.html#tymethod.del) `update` and log it
+    ///
+    /// # Errors
+    ///
+    /// If writing to the log (or a periodic snapshot) fails
+    pub fn del(&mut self, update: G::Update) -> io::Result<()>
+    where
+        G::Update: Clone,
+    {
+        let mut buf = Vec::new();
+        self.state.del(update.clone());
+        self.state.encode_update(&update, &mut buf);
+        self.append(DEL, &buf)
+    }
+}
+
+/// Replay the complete, intact records at the start of `bytes` onto `state`
+///
+/// # Returns
+///
+/// How many bytes those records took, and how many there were
+fn replay<G: Persist>(state: &mut G, bytes: &[u8]) -> io::Result<(usize, usize)> {
+    let mut offset = 0;
+    let mut records = 0;
+    while bytes.len() - offset >= 8 {
+        let header = &bytes[offset..offset + 8];
+        let len = u32::from_le_bytes(header[..4].try_into().expect("four bytes")) as usize;
+        let checksum = u32::from_le_bytes(header[4..].try_into().expect("four bytes"));
+        let Some(record) = bytes.get(offset + 8..offset + 8 + len) else {
+            break;
+        };
+        if len == 0 || crc32(record) != checksum {
+            break;
+        }
+        let mut reader = Reader::new(&record[1..]);
+        match record[0] {
+            ADD => state.replay_add(&mut reader).map_err(invalid)?,
+            DEL => state.replay_del(&mut reader).map_err(invalid)?,
+            MERGE => {
+                let other = wire::decode(reader.take(reader.remaining()).map_err(invalid)?)
+                    .map_err(invalid)?;
+                *state = state.merge(&other);
+            }
+            _ => return Err(invalid("Unknown record kind")),
+        }
+        if reader.remaining() > 0 {
+            return Err(invalid(DecodeError::TrailingBytes));
+        }
+        offset += 8 + len;
+        records += 1;
+    }
+    Ok((offset, records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    /// A fresh directory for one test, removed afterwards
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("cvrdt-persist-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    static SIZE: usize = 3;
+
+    fn counter() -> GCounter {
+        GCounter::new((0, vec![0; SIZE]))
+    }
+
+    fn set() -> TwoPhaseSet<u8> {
+        TwoPhaseSet::new((HashSet::new(), HashSet::new()))
+    }
+
+    /// Apply `ops` to a two-phase set store: adds, deletions (of elements already added), and
+    /// merges with remote states
+    fn apply(store: &mut Store<TwoPhaseSet<u8>>, ops: &[(u8, u8)]) {
+        for &(kind, x) in ops {
+            match kind % 3 {
+                0 => store.add(x).unwrap(),
+                1 if store.state().query(&x) => store.del(x).unwrap(),
+                _ => store
+                    .merge(&TwoPhaseSet::new((
+                        HashSet::from([x, x / 2]),
+                        HashSet::from([x / 2]),
+                    )))
+                    .unwrap(),
+            }
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn g_counter_recovers(
+            ops in prop::collection::vec(prop::option::of(prop::collection::vec(0..8u64, SIZE)), 0..32),
+            snapshot_every in 0..8usize,
+        ) {
+            let dir = TempDir::new("g-counter");
+            let mut store = Store::open(&dir.0, counter(), snapshot_every).unwrap();
+            for op in ops {
+                match op {
+                    None => store.add(()).unwrap(),
+                    Some(counts) => store.merge(&GCounter::new((1, counts))).unwrap(),
+                }
+            }
+            let expected = store.state().payload();
+            drop(store);
+            let recovered = Store::open(&dir.0, counter(), snapshot_every).unwrap();
+            prop_assert_eq!(recovered.state().payload(), expected);
+        }
+
+        #[test]
+        fn two_phase_set_recovers(
+            ops in prop::collection::vec((any::<u8>(), 0..16u8), 0..32),
+            snapshot_every in 0..8usize,
+        ) {
+            let dir = TempDir::new("two-phase-set");
+            let mut store = Store::open(&dir.0, set(), snapshot_every).unwrap();
+            apply(&mut store, &ops);
+            let expected = store.state().payload();
+            drop(store);
+            let recovered = Store::open(&dir.0, set(), snapshot_every).unwrap();
+            prop_assert_eq!(recovered.state().payload(), expected);
+        }
+    }
+
+    #[test]
+    fn narrow_counters_recover() {
+        let dir = TempDir::new("narrow");
+        let initial = || PNCounter::<u32>::new((0, vec![0; 2], vec![0; 2]));
+        let mut store = Store::open(&dir.0, initial(), 2).unwrap();
+        store.add(()).unwrap();
+        store.add(()).unwrap();
+        store.del(()).unwrap();
+        store
+            .merge(&PNCounter::<u32>::new((1, vec![0, 5], vec![0, 1])))
+            .unwrap();
+        store.add(()).unwrap();
+        let expected = store.state().payload();
+        drop(store);
+        let recovered = Store::open(&dir.0, initial(), 2).unwrap();
+        assert_eq!(recovered.state().payload(), expected);
+    }
+
+    #[test]
+    fn lww_register_recovers_exact_timestamps() {
+        let dir = TempDir::new("lww-register");
+        let initial = || LWWRegister::new((String::new(), std::time::UNIX_EPOCH));
+        let mut store = Store::open(&dir.0, initial(), 0).unwrap();
+        for s in ["a", "bb", "ccc"] {
+            store.add(s.to_string()).unwrap();
+        }
+        let expected = store.state().payload();
+        drop(store);
+        let recovered = Store::open(&dir.0, initial(), 0).unwrap();
+        assert_eq!(recovered.state().payload(), expected);
+    }
+
+    #[test]
+    fn torn_writes_lose_only_the_torn_record() {
+        let dir = TempDir::new("torn");
+        let ops: Vec<(u8, u8)> = (0..12).map(|i| (i % 3, i % 5)).collect();
+        // the payload and log length after each complete record
+        let mut prefixes = vec![(set().payload(), 0)];
+        let mut store = Store::open(&dir.0, set(), 0).unwrap();
+        for op in &ops {
+            apply(&mut store, std::slice::from_ref(op));
+            let len = fs::metadata(dir.0.join("log.0")).unwrap().len();
+            if len > prefixes.last().unwrap().1 {
+                prefixes.push((store.state().payload(), len));
+            }
+        }
+        drop(store);
+        let log = fs::read(dir.0.join("log.0")).unwrap();
+        for cut in 0..=log.len() {
+            fs::write(dir.0.join("log.0"), &log[..cut]).unwrap();
+            let (payload, len) = prefixes
+                .iter()
+                .rev()
+                .find(|(_, len)| *len <= cut as u64)
+                .unwrap()
+                .clone();
+            let mut store = Store::open(&dir.0, set(), 0).unwrap();
+            assert_eq!(store.state().payload(), payload);
+            assert_eq!(fs::metadata(dir.0.join("log.0")).unwrap().len(), len);
+            // new records follow the last good one
+            store.add(42).unwrap();
+            let expected = store.state().payload();
+            drop(store);
+            assert_eq!(
+                Store::open(&dir.0, set(), 0).unwrap().state().payload(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn corrupted_records_are_dropped() {
+        let dir = TempDir::new("corrupted");
+        let mut store = Store::open(&dir.0, counter(), 0).unwrap();
+        store.add(()).unwrap();
+        store.add(()).unwrap();
+        drop(store);
+        let mut log = fs::read(dir.0.join("log.0")).unwrap();
+        let last = log.len() - 1;
+        log[last] ^= 0xff;
+        fs::write(dir.0.join("log.0"), &log).unwrap();
+        let store = Store::open(&dir.0, counter(), 0).unwrap();
+        assert_eq!(store.state().counts, vec![1, 0, 0]);
+        assert_eq!(store.records(), 1);
+    }
+
+    #[test]
+    fn snapshots_start_new_generations() {
+        let dir = TempDir::new("snapshots");
+        let mut store = Store::open(&dir.0, counter(), 3).unwrap();
+        for _ in 0..7 {
+            store.add(()).unwrap();
+        }
+        assert_eq!(store.records(), 1);
+        drop(store);
+        assert!(dir.0.join("snapshot").exists());
+        assert!(dir.0.join("log.2").exists());
+        assert!(!dir.0.join("log.1").exists());
+        // as if we crashed after writing the snapshot but before removing the old log
+        fs::write(dir.0.join("log.1"), b"stale records").unwrap();
+        let store = Store::open(&dir.0, counter(), 3).unwrap();
+        assert_eq!(store.state().counts, vec![7, 0, 0]);
+        assert!(!dir.0.join("log.1").exists());
+    }
+
+    #[test]
+    fn wrong_type_is_rejected() {
+        let dir = TempDir::new("wrong-type");
+        let mut store = Store::open(&dir.0, counter(), 0).unwrap();
+        store.add(()).unwrap();
+        store.snapshot().unwrap();
+        drop(store);
+        let err = Store::open(&dir.0, GSet::<u8>::new(HashSet::new()), 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,20 +1,23 @@
+use crate::count::{increment, sum, Count};
+use crate::operation::Operation;
 use crate::traits::{Grow, Shrink};
 
 /// A vectorized counter than can grow or shrink
 ///
+/// Counts are `u64`s by default; any other [`Count`](../count/trait.Count.html) works too.
+///
 /// # Panics
 ///
+/// Incrementing a count that's already at its maximum (via `add` or `del`) panics, rather than
+/// wrapping around, as does a `query` whose sums overflow or that would be negative.
+///
 /// Like [`GCounter`s](../g_counter/struct.GCounter.html), any function involving two or more
 /// `PNCounter`s (viz. `le` and `merge`) will panic (via `assert_eq!`) if their counts vectors are
 /// not the same length. What's more, since `PNCounter`s involve _two_ vectorized counts, any
 /// instantiation (via `new`) will also panic if the lengths of the positive and negative count
-/// vectors differ. I'd prefer to check this at compile time (as much as possible) instead, but
-///
-/// - avoiding C++'s template mess is part of what makes Rust great
-/// - Rust doesn't have [const generics](https://rust-lang.github.io/rfcs/2000-const-generics.html)
-/// yet
-/// - this library is meant to be as simple and expository as possible, so I'd like to avoid
-/// fancier things like [`generic_array`](https://docs.rs/generic-array/0.14.4/generic_array/)
+/// vectors differ. When the number of replicas is known at compile time, a
+/// [`PNCounterN`](struct.PNCounterN.html) checks all of this at compile time instead, via [const
+/// generics](https://rust-lang.github.io/rfcs/2000-const-generics.html).
 ///
 /// As mentioned above, operations panic when trying dealing with two or more `PNCounter`s of
 /// incompatible sizes:
@@ -55,6 +58,9 @@ use crate::traits::{Grow, Shrink};
 /// choice that when merging two `PNCounter`s, we take the minimum of their two `id`s as the new
 /// one.
 ///
+/// Wrap a `PNCounter` in a [`Replica`](../replica/struct.Replica.html) handle to keep the local
+/// `id` stable across merges instead.
+///
 /// # Examples
 ///
 /// Example usage, including demonstrating some properties:
@@ -74,25 +80,47 @@ use crate::traits::{Grow, Shrink};
 /// assert_eq!(z.payload(), y.merge(&x).payload());
 /// assert_eq!(z.query(&()), 5);
 /// ```
-#[derive(Debug, Clone)]
-pub struct PNCounter {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedPNCounter<T>"))]
+pub struct PNCounter<T: Count = u64> {
     /// The index for this local `PNCounter` where all updates occur
     pub id: usize,
     /// The vector of positive counts (additions)
-    pub positive: Vec<u64>,
+    pub positive: Vec<T>,
     /// The vector of negative counts (deletions)
-    pub negative: Vec<u64>,
+    pub negative: Vec<T>,
 }
 
 impl PNCounter {
+    /// Create a `PNCounter` of `u64` counts, as with [`Grow::new`](../traits/trait.Grow.html#tymethod.new)
+    ///
+    /// Integer literals in `payload` would otherwise need their type spelled out; for other count
+    /// types, name the type instead, e.g. `PNCounter::<u8>::new((0, vec![0; 3], vec![0; 3]))`.
+    ///
+    /// # Panics
+    ///
+    /// Like `Grow::new`, if the lengths of `positive` and `negative` differ or `id` is too large.
+    #[must_use]
+    pub fn new(payload: (usize, Vec<u64>, Vec<u64>)) -> Self {
+        <Self as Grow>::new(payload)
+    }
+}
+
+impl<T: Count> PNCounter<T> {
+    fn check(&self) -> Result<(), &'static str> {
+        if self.positive.len() != self.negative.len() {
+            Err("Incompatible positive & negative lengths")
+        } else if self.id >= self.positive.len() || self.id >= self.negative.len() {
+            Err("ID too large")
+        } else {
+            Ok(())
+        }
+    }
     fn consistent(&self) {
-        assert_eq!(
-            self.positive.len(),
-            self.negative.len(),
-            "Incompatible positive & negative lengths"
-        );
-        assert!(self.id < self.positive.len(), "ID too large");
-        assert!(self.id < self.negative.len(), "ID too large");
+        if let Err(msg) = self.check() {
+            panic!("{msg}");
+        }
     }
     fn compatible_len(&self, other: &Self) -> usize {
         self.consistent();
@@ -109,13 +137,41 @@ impl PNCounter {
         );
         self.positive.len()
     }
+
+    /// Like [`query`](../traits/trait.Grow.html#tymethod.query), but without panicking
+    ///
+    /// # Returns
+    ///
+    /// The value of this counter, or `None` if it would be negative or its sums overflow
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cvrdt_exposition::{Grow, PNCounter, Shrink};
+    /// let mut x = PNCounter::new((0, vec![0; 2], vec![0; 2]));
+    /// x.add(());
+    /// assert_eq!(x.checked_query(), Some(1));
+    /// x.del(());
+    /// x.del(());
+    /// assert_eq!(x.checked_query(), None);
+    /// ```
+    #[must_use]
+    pub fn checked_query(&self) -> Option<T> {
+        let total = |counts: &[T]| {
+            counts
+                .iter()
+                .try_fold(T::ZERO, |sum, &c| sum.checked_add(c))
+        };
+        total(&self.positive)?.checked_sub(total(&self.negative)?)
+    }
 }
 
-impl Grow for PNCounter {
-    type Payload = (usize, Vec<u64>, Vec<u64>);
+impl<T: Count> Grow for PNCounter<T> {
+    type Payload = (usize, Vec<T>, Vec<T>);
+    type PayloadRef<'a> = (usize, &'a [T], &'a [T]);
     type Update = ();
     type Query = ();
-    type Value = u64;
+    type Value = T;
 
     fn new(payload: Self::Payload) -> Self {
         let pn = PNCounter {
@@ -129,14 +185,20 @@ impl Grow for PNCounter {
     fn payload(&self) -> Self::Payload {
         (self.id, self.positive.clone(), self.negative.clone())
     }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.id, &self.positive, &self.negative)
+    }
     fn add(&mut self, _update: Self::Update) {
-        self.positive[self.id] += 1;
+        increment(&mut self.positive[self.id]);
     }
     fn le(&self, other: &Self) -> bool {
         let n = self.compatible_len(other);
         (0..n)
             .all(|i| self.positive[i] <= other.positive[i] && self.negative[i] <= other.negative[i])
     }
+    fn compatible(&self, other: &Self) -> bool {
+        self.check().is_ok() && other.check().is_ok() && self.positive.len() == other.positive.len()
+    }
     fn merge(&self, other: &Self) -> Self {
         let n = self.compatible_len(other);
         PNCounter {
@@ -150,13 +212,356 @@ impl Grow for PNCounter {
         }
     }
     fn query(&self, _query: &Self::Query) -> Self::Value {
-        self.positive.iter().sum::<u64>() - self.negative.iter().sum::<u64>()
+        difference(sum(&self.positive), sum(&self.negative))
     }
 }
 
-impl Shrink for PNCounter {
+impl<T: Count> Shrink for PNCounter<T> {
     fn del(&mut self, _update: Self::Update) {
-        self.negative[self.id] += 1;
+        increment(&mut self.negative[self.id]);
+    }
+}
+
+/// The value of a counter with `positive` additions and `negative` deletions
+///
+/// # Panics
+///
+/// If there were more deletions than additions
+fn difference<T: Count>(positive: T, negative: T) -> T {
+    positive.checked_sub(negative).expect("Negative count")
+}
+
+/// A [`PNCounter`](struct.PNCounter.html) for a fixed number `N` of replicas
+///
+/// The number of replicas is part of the type, so the positive and negative counts always have
+/// the same length, and comparing or merging counters for different numbers of replicas doesn't
+/// compile.
+///
+/// ```compile_fail
+/// use cvrdt_exposition::{Grow, PNCounterN};
+/// let x = PNCounterN::<2>::new((0, [0; 2], [0; 2]));
+/// let y = PNCounterN::<3>::new((1, [0; 3], [0; 3]));
+/// x.merge(&y);
+/// ```
+///
+/// # Panics
+///
+/// `new` panics if `id` isn't a valid index, i.e. if it isn't less than `N`:
+///
+/// ```should_panic
+/// // this will panic
+/// use cvrdt_exposition::{Grow, PNCounterN};
+/// let x = PNCounterN::<1>::new((1, [0], [0]));
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::{Grow, PNCounter, PNCounterN, Shrink};
+/// let mut x = PNCounterN::<2>::new((0, [0; 2], [0; 2]));
+/// x.add(());
+/// x.add(());
+/// x.del(());
+/// let y = PNCounterN::new((1, [0, 3], [0, 0]));
+/// assert_eq!(x.merge(&y).payload(), (0, [2, 3], [1, 0]));
+/// assert_eq!(x.merge(&y).query(&()), 4);
+/// // converting to and from a `PNCounter` checks the lengths
+/// assert_eq!(PNCounter::from(y).payload(), (1, vec![0, 3], vec![0, 0]));
+/// assert!(PNCounterN::<3>::try_from(PNCounter::new((0, vec![0; 2], vec![0; 2]))).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "PNCounter<T>", try_from = "PNCounter<T>")
+)]
+pub struct PNCounterN<const N: usize, T: Count = u64> {
+    /// The index for this local `PNCounterN` where all updates occur
+    pub id: usize,
+    /// The array of positive counts (additions)
+    pub positive: [T; N],
+    /// The array of negative counts (deletions)
+    pub negative: [T; N],
+}
+
+impl<const N: usize, T: Count> Grow for PNCounterN<N, T> {
+    type Payload = (usize, [T; N], [T; N]);
+    type PayloadRef<'a> = (usize, &'a [T; N], &'a [T; N]);
+    type Update = ();
+    type Query = ();
+    type Value = T;
+
+    fn new(payload: Self::Payload) -> Self {
+        assert!(payload.0 < N, "ID too large");
+        PNCounterN {
+            id: payload.0,
+            positive: payload.1,
+            negative: payload.2,
+        }
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.id, self.positive, self.negative)
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.id, &self.positive, &self.negative)
+    }
+    fn add(&mut self, _update: Self::Update) {
+        increment(&mut self.positive[self.id]);
+    }
+    fn le(&self, other: &Self) -> bool {
+        (0..N)
+            .all(|i| self.positive[i] <= other.positive[i] && self.negative[i] <= other.negative[i])
+    }
+    fn merge(&self, other: &Self) -> Self {
+        PNCounterN {
+            id: self.id.min(other.id), // arbitrary, as for `PNCounter`
+            positive: std::array::from_fn(|i| self.positive[i].max(other.positive[i])),
+            negative: std::array::from_fn(|i| self.negative[i].max(other.negative[i])),
+        }
+    }
+    fn query(&self, _query: &Self::Query) -> Self::Value {
+        difference(sum(&self.positive), sum(&self.negative))
+    }
+}
+
+impl<const N: usize, T: Count> Shrink for PNCounterN<N, T> {
+    fn del(&mut self, _update: Self::Update) {
+        increment(&mut self.negative[self.id]);
+    }
+}
+
+impl<const N: usize, T: Count> From<PNCounterN<N, T>> for PNCounter<T> {
+    fn from(pn: PNCounterN<N, T>) -> Self {
+        PNCounter {
+            id: pn.id,
+            positive: pn.positive.to_vec(),
+            negative: pn.negative.to_vec(),
+        }
+    }
+}
+
+impl<const N: usize, T: Count> TryFrom<PNCounter<T>> for PNCounterN<N, T> {
+    type Error = &'static str;
+
+    fn try_from(pn: PNCounter<T>) -> Result<Self, Self::Error> {
+        pn.check()?;
+        let positive = pn.positive.try_into().map_err(|_| "Incompatible lengths")?;
+        let negative = pn.negative.try_into().map_err(|_| "Incompatible lengths")?;
+        Ok(PNCounterN {
+            id: pn.id,
+            positive,
+            negative,
+        })
+    }
+}
+
+/// The operation-based counterpart of a [`PNCounter`](struct.PNCounter.html)
+///
+/// # Panics
+///
+/// Like a `PNCounter`, `new` (and converting into a `PNCounter`) panics if the `positive` and
+/// `negative` lengths differ or the `id` isn't a valid index, and `effect` panics if an
+/// operation's `replica` isn't a valid index.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::{OpPNCounter, Operation, PNCounterOp, PNCounterUpdate};
+/// let mut x = OpPNCounter::new((1, vec![0; 2], vec![0; 2]));
+/// assert_eq!(
+///     x.update(PNCounterUpdate::Decrement),
+///     PNCounterOp::Decrement { replica: 1 }
+/// );
+/// x.effect(&PNCounterOp::Increment { replica: 0 });
+/// assert_eq!(x.payload(), (1, vec![1, 0], vec![0, 1]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpPNCounter<T: Count = u64> {
+    /// The index for this local `OpPNCounter` where all updates originate
+    pub id: usize,
+    /// The vector of positive counts (increments)
+    pub positive: Vec<T>,
+    /// The vector of negative counts (decrements)
+    pub negative: Vec<T>,
+}
+
+/// An update to an [`OpPNCounter`](struct.OpPNCounter.html) at its source replica
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PNCounterUpdate {
+    /// Count up by one
+    Increment,
+    /// Count down by one
+    Decrement,
+}
+
+/// An operation on an [`OpPNCounter`](struct.OpPNCounter.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PNCounterOp {
+    /// Increment the positive count for `replica`
+    Increment {
+        /// The index of the replica where this increment originated
+        replica: usize,
+    },
+    /// Increment the negative count for `replica`
+    Decrement {
+        /// The index of the replica where this decrement originated
+        replica: usize,
+    },
+}
+
+impl OpPNCounter {
+    /// Create an `OpPNCounter` of `u64` counts, as with
+    /// [`Operation::new`](../operation/trait.Operation.html#tymethod.new)
+    ///
+    /// As with [`PNCounter::new`](struct.PNCounter.html#method.new), name the type for other
+    /// counts.
+    ///
+    /// # Panics
+    ///
+    /// If the lengths of `positive` and `negative` differ or `id` is too large.
+    #[must_use]
+    pub fn new(payload: (usize, Vec<u64>, Vec<u64>)) -> Self {
+        <Self as Operation>::new(payload)
+    }
+}
+
+impl<T: Count> Operation for OpPNCounter<T> {
+    type Payload = (usize, Vec<T>, Vec<T>);
+    type Update = PNCounterUpdate;
+    type Op = PNCounterOp;
+
+    fn new(payload: Self::Payload) -> Self {
+        <PNCounter<T> as Grow>::new(payload).into()
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.id, self.positive.clone(), self.negative.clone())
+    }
+    fn prepare(&self, update: Self::Update) -> Self::Op {
+        match update {
+            PNCounterUpdate::Increment => PNCounterOp::Increment { replica: self.id },
+            PNCounterUpdate::Decrement => PNCounterOp::Decrement { replica: self.id },
+        }
+    }
+    fn effect(&mut self, op: &Self::Op) {
+        match *op {
+            PNCounterOp::Increment { replica } => increment(&mut self.positive[replica]),
+            PNCounterOp::Decrement { replica } => increment(&mut self.negative[replica]),
+        }
+    }
+}
+
+impl<T: Count> From<PNCounter<T>> for OpPNCounter<T> {
+    fn from(pn: PNCounter<T>) -> Self {
+        OpPNCounter {
+            id: pn.id,
+            positive: pn.positive,
+            negative: pn.negative,
+        }
+    }
+}
+
+impl<T: Count> From<OpPNCounter<T>> for PNCounter<T> {
+    fn from(pn: OpPNCounter<T>) -> Self {
+        Grow::new((pn.id, pn.positive, pn.negative))
+    }
+}
+
+/// A `PNCounter` as it comes off the wire, before we've checked that it's consistent
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedPNCounter<T> {
+    id: usize,
+    positive: Vec<T>,
+    negative: Vec<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Count> TryFrom<UncheckedPNCounter<T>> for PNCounter<T> {
+    type Error = &'static str;
+
+    fn try_from(unchecked: UncheckedPNCounter<T>) -> Result<Self, Self::Error> {
+        let pn = PNCounter {
+            id: unchecked.id,
+            positive: unchecked.positive,
+            negative: unchecked.negative,
+        };
+        pn.check()?;
+        Ok(pn)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+static MAX_SIZE: usize = 100;
+
+/// A strategy for arbitrary `PNCounter`s with exactly `n` positive and `n` negative counts
+///
+/// # Panics
+///
+/// When generating values, if `n` is zero (there must be a valid `id`)
+#[cfg(any(test, feature = "testing"))]
+pub fn sized<T: Count + proptest::arbitrary::Arbitrary>(
+    n: usize,
+) -> impl proptest::strategy::Strategy<Value = PNCounter<T>> {
+    use proptest::prelude::*;
+    (
+        prop::collection::vec(any::<T>(), n),
+        prop::collection::vec(any::<T>(), n),
+    )
+        .prop_flat_map(|(positive, negative)| {
+            let len = positive.len();
+            (0..len, Just(positive), Just(negative))
+        })
+        .prop_map(|(id, positive, negative)| PNCounter {
+            id,
+            positive,
+            negative,
+        })
+}
+
+/// A strategy for arbitrary pairs of `PNCounter`s of the same size, i.e. pairs that can be merged
+#[cfg(any(test, feature = "testing"))]
+pub fn two() -> impl proptest::strategy::Strategy<Value = (PNCounter, PNCounter)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n)))
+}
+
+/// A strategy for arbitrary triples of `PNCounter`s of the same size, i.e. triples that can be
+/// merged
+#[cfg(any(test, feature = "testing"))]
+pub fn three() -> impl proptest::strategy::Strategy<Value = (PNCounter, PNCounter, PNCounter)> {
+    use proptest::prelude::*;
+    (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n), sized(n)))
+}
+
+/// Arbitrary `PNCounterN`s
+///
+/// # Panics
+///
+/// When generating values, if `N` is zero (there must be a valid `id`)
+#[cfg(any(test, feature = "testing"))]
+impl<const N: usize, T: Count + proptest::arbitrary::Arbitrary> proptest::arbitrary::Arbitrary
+    for PNCounterN<N, T>
+{
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        sized(N)
+            .prop_map(|pn| PNCounterN::try_from(pn).unwrap())
+            .boxed()
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<T: Count + proptest::arbitrary::Arbitrary> proptest::arbitrary::Arbitrary for PNCounter<T> {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        (1..MAX_SIZE).prop_flat_map(sized).boxed()
     }
 }
 
@@ -166,34 +571,96 @@ mod tests {
     use crate::properties::{grow, shrink};
     use proptest::prelude::*;
 
-    static MAX_SIZE: usize = 100;
+    fn cvrdt_and_update() -> impl Strategy<Value = (PNCounter, ())> {
+        (any::<PNCounter>(), Just(()))
+    }
 
-    fn sized(n: usize) -> impl Strategy<Value = PNCounter> {
-        (
-            prop::collection::vec(any::<u64>(), n),
-            prop::collection::vec(any::<u64>(), n),
-        )
-            .prop_flat_map(|(positive, negative)| {
-                let len = positive.len();
-                (0..len, Just(positive), Just(negative))
-            })
-            .prop_map(|(id, positive, negative)| PNCounter {
-                id,
-                positive,
-                negative,
-            })
+    grow!(two, three, cvrdt_and_update);
+    shrink!(cvrdt_and_update);
+
+    mod wide {
+        use super::*;
+
+        fn any_wide() -> impl Strategy<Value = PNCounterN<4, u128>> {
+            any::<PNCounterN<4, u128>>()
+        }
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (PNCounterN<4, u128>, ())> {
+            (any_wide(), Just(()))
+        }
+
+        grow!(any_wide, cvrdt_and_update);
+        shrink!(cvrdt_and_update);
     }
 
-    fn two() -> impl Strategy<Value = (PNCounter, PNCounter)> {
-        (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n)))
+    #[test]
+    #[should_panic(expected = "Negative count")]
+    fn query_panics_when_negative() {
+        let mut x = PNCounter::<u32>::new((0, vec![0; 2], vec![0; 2]));
+        x.del(());
+        x.query(&());
     }
-    fn three() -> impl Strategy<Value = (PNCounter, PNCounter, PNCounter)> {
-        (1..MAX_SIZE).prop_flat_map(|n| (sized(n), sized(n), sized(n)))
+
+    mod fixed {
+        use super::*;
+
+        fn any_fixed() -> impl Strategy<Value = PNCounterN<4>> {
+            any::<PNCounterN<4>>()
+        }
+
+        fn cvrdt_and_update() -> impl Strategy<Value = (PNCounterN<4>, ())> {
+            (any_fixed(), Just(()))
+        }
+
+        grow!(any_fixed, cvrdt_and_update);
+        shrink!(cvrdt_and_update);
+
+        proptest! {
+            #[test]
+            fn agrees_with_pn_counter(x in any_fixed(), y in any_fixed()) {
+                let (px, py) = (PNCounter::from(x.clone()), PNCounter::from(y.clone()));
+                prop_assert_eq!(PNCounter::from(x.merge(&y)), px.merge(&py));
+                prop_assert_eq!(x.le(&y), px.le(&py));
+                prop_assert_eq!(PNCounterN::try_from(px), Ok(x));
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        proptest! {
+            #[test]
+            fn serde_round_trip(x in any_fixed()) {
+                let json = serde_json::to_string(&x).unwrap();
+                prop_assert_eq!(&json, &serde_json::to_string(&PNCounter::from(x.clone())).unwrap());
+                prop_assert_eq!(serde_json::from_str::<PNCounterN<4>>(&json).unwrap(), x);
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn serde_rejects_wrong_length() {
+            let json = r#"{"id":0,"positive":[0,0,0],"negative":[0,0,0]}"#;
+            assert!(serde_json::from_str::<PNCounterN<2>>(json).is_err());
+        }
     }
-    fn cvrdt_and_update() -> impl Strategy<Value = (PNCounter, ())> {
-        (1..MAX_SIZE).prop_flat_map(sized).prop_map(|p| (p, ()))
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<PNCounter>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            let y: PNCounter = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(x.payload(), y.payload());
+        }
     }
 
-    grow!(two, three, cvrdt_and_update);
-    shrink!(cvrdt_and_update);
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_inconsistent() {
+        for json in [
+            r#"{"id":0,"positive":[0],"negative":[0,0]}"#,
+            r#"{"id":1,"positive":[0],"negative":[0]}"#,
+        ] {
+            assert!(serde_json::from_str::<PNCounter>(json).is_err());
+        }
+    }
 }
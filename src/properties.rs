@@ -1,83 +1,153 @@
+//! Property-based tests that every `CvRDT` must pass
+//!
+//! Enable the `testing` feature to use these macros for your own [`Grow`](../traits/trait.Grow.html)
+//! and [`Shrink`](../traits/trait.Shrink.html) implementations. Each macro expands to a
+//! [`proptest!`](https://docs.rs/proptest/latest/proptest/macro.proptest.html) block of `#[test]`
+//! functions, so call it at most once per module, and only from test code.
+//!
+//! The macros take the _names_ of functions returning `proptest` strategies:
+//!
+//! - [`grow!`](macro.grow.html) takes either
+//!     - a strategy for arbitrary `CvRDTs` and a strategy for a `CvRDT` with an update, or
+//!     - strategies for compatible pairs and triples of `CvRDTs` (e.g. `GCounter`s of the same
+//!       size) and a strategy for a `CvRDT` with an update
+//! - [`shrink!`](macro.shrink.html) takes a strategy for a `CvRDT` with an update that may be
+//!   deleted from it
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use cvrdt_exposition::{properties::{grow, shrink}, Grow, Shrink};
+//! use proptest::prelude::*;
+//!
+//! fn cvrdt() -> impl Strategy<Value = MyCvRDT> { /* ... */ }
+//! fn cvrdt_and_update() -> impl Strategy<Value = (MyCvRDT, MyUpdate)> { /* ... */ }
+//!
+//! grow!(cvrdt, cvrdt_and_update);
+//! shrink!(cvrdt_and_update);
+//! ```
+
+/// Re-exported so that the macros below work without a direct dependency on `proptest`
+pub use proptest;
+
+/// Rebuild a `CvRDT` from its payload, for the macros below
+#[doc(hidden)]
+pub fn rebuild<G: crate::Grow>(x: &G) -> G {
+    G::new(x.payload())
+}
+
 /// Properties that `Grow` implementations must satisfy
-macro_rules! grow {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __grow {
     ($arb_cvrdt:ident, $arb_cvrdt_and_addend:ident) => {
-        proptest! {
+        $crate::properties::proptest::proptest! {
             #[test]
             fn merge_associative(x in $arb_cvrdt(), y in $arb_cvrdt(), z in $arb_cvrdt()) {
-                prop_assert_eq!(
-                    Grow::payload(&Grow::merge(&x, &Grow::merge(&y, &z))),
-                    Grow::payload(&Grow::merge(&Grow::merge(&x, &y), &z))
-                );
+                let (yz, xy) = ($crate::Grow::merge(&y, &z), $crate::Grow::merge(&x, &y));
+                let (x_yz, xy_z) = ($crate::Grow::merge(&x, &yz), $crate::Grow::merge(&xy, &z));
+                $crate::properties::proptest::prop_assert_eq!($crate::Grow::payload_ref(&x_yz), $crate::Grow::payload_ref(&xy_z));
             }
             #[test]
             fn merge_commutative(x in $arb_cvrdt(), y in $arb_cvrdt()) {
-                prop_assert_eq!(
-                    Grow::payload(&Grow::merge(&x, &y)),
-                    Grow::payload(&Grow::merge(&y, &x))
-                );
+                let (xy, yx) = ($crate::Grow::merge(&x, &y), $crate::Grow::merge(&y, &x));
+                $crate::properties::proptest::prop_assert_eq!($crate::Grow::payload_ref(&xy), $crate::Grow::payload_ref(&yx));
             }
             #[test]
             fn merge_idempotent(x in $arb_cvrdt(), y in $arb_cvrdt()) {
-                prop_assert_eq!(
-                    Grow::payload(&Grow::merge(&Grow::merge(&x, &y), &y)),
-                    Grow::payload(&Grow::merge(&x, &y))
+                let xy = $crate::Grow::merge(&x, &y);
+                let xy_y = $crate::Grow::merge(&xy, &y);
+                $crate::properties::proptest::prop_assert_eq!($crate::Grow::payload_ref(&xy_y), $crate::Grow::payload_ref(&xy));
+            }
+            #[test]
+            fn partial_cmp_agrees_with_le(x in $arb_cvrdt(), y in $arb_cvrdt()) {
+                let xy = $crate::Grow::merge(&x, &y);
+                for (a, b) in [(&x, &y), (&x, &xy)] {
+                    let (a, b) = ($crate::traits::Lattice(a), $crate::traits::Lattice(b));
+                    $crate::properties::proptest::prop_assert_eq!(a <= b, $crate::Grow::le(a.0, b.0));
+                    $crate::properties::proptest::prop_assert_eq!(a >= b, $crate::Grow::le(b.0, a.0));
+                }
+            }
+            #[test]
+            fn payload_ref_round_trips((x, _u) in $arb_cvrdt_and_addend()) {
+                let y = $crate::properties::rebuild(&x);
+                $crate::properties::proptest::prop_assert_eq!(
+                    $crate::Grow::payload_ref(&x),
+                    $crate::Grow::payload_ref(&y)
                 );
             }
             #[test]
             fn add_monotonic((x, u) in $arb_cvrdt_and_addend()) {
                 let mut y = x.clone();
-                Grow::add(&mut y, u);
-                prop_assert!(Grow::le(&x, &y));
+                $crate::Grow::add(&mut y, u);
+                $crate::properties::proptest::prop_assert!($crate::Grow::le(&x, &y));
             }
         }
     };
     ($arb_cvrdt2:ident, $arb_cvrdt3:ident, $arb_cvrdt_and_addend:ident) => {
-        proptest! {
+        $crate::properties::proptest::proptest! {
             #[test]
             fn merge_associative((x, y, z) in $arb_cvrdt3()) {
-                prop_assert_eq!(
-                    Grow::payload(&Grow::merge(&x, &Grow::merge(&y, &z))),
-                    Grow::payload(&Grow::merge(&Grow::merge(&x, &y), &z))
-                );
+                let (yz, xy) = ($crate::Grow::merge(&y, &z), $crate::Grow::merge(&x, &y));
+                let (x_yz, xy_z) = ($crate::Grow::merge(&x, &yz), $crate::Grow::merge(&xy, &z));
+                $crate::properties::proptest::prop_assert_eq!($crate::Grow::payload_ref(&x_yz), $crate::Grow::payload_ref(&xy_z));
             }
             #[test]
             fn merge_commutative((x, y) in $arb_cvrdt2()) {
-                prop_assert_eq!(
-                    Grow::payload(&Grow::merge(&x, &y)),
-                    Grow::payload(&Grow::merge(&y, &x))
-                );
+                let (xy, yx) = ($crate::Grow::merge(&x, &y), $crate::Grow::merge(&y, &x));
+                $crate::properties::proptest::prop_assert_eq!($crate::Grow::payload_ref(&xy), $crate::Grow::payload_ref(&yx));
             }
             #[test]
             fn merge_idempotent((x, y) in $arb_cvrdt2()) {
-                prop_assert_eq!(
-                    Grow::payload(&Grow::merge(&Grow::merge(&x, &y), &y)),
-                    Grow::payload(&Grow::merge(&x, &y))
+                let xy = $crate::Grow::merge(&x, &y);
+                let xy_y = $crate::Grow::merge(&xy, &y);
+                $crate::properties::proptest::prop_assert_eq!($crate::Grow::payload_ref(&xy_y), $crate::Grow::payload_ref(&xy));
+            }
+            #[test]
+            fn partial_cmp_agrees_with_le((x, y) in $arb_cvrdt2()) {
+                let xy = $crate::Grow::merge(&x, &y);
+                for (a, b) in [(&x, &y), (&x, &xy)] {
+                    let (a, b) = ($crate::traits::Lattice(a), $crate::traits::Lattice(b));
+                    $crate::properties::proptest::prop_assert_eq!(a <= b, $crate::Grow::le(a.0, b.0));
+                    $crate::properties::proptest::prop_assert_eq!(a >= b, $crate::Grow::le(b.0, a.0));
+                }
+            }
+            #[test]
+            fn payload_ref_round_trips((x, _u) in $arb_cvrdt_and_addend()) {
+                let y = $crate::properties::rebuild(&x);
+                $crate::properties::proptest::prop_assert_eq!(
+                    $crate::Grow::payload_ref(&x),
+                    $crate::Grow::payload_ref(&y)
                 );
             }
             #[test]
             fn add_monotonic((x, u) in $arb_cvrdt_and_addend()) {
                 let mut y = x.clone();
-                Grow::add(&mut y, u);
-                prop_assert!(Grow::le(&x, &y));
+                $crate::Grow::add(&mut y, u);
+                $crate::properties::proptest::prop_assert!($crate::Grow::le(&x, &y));
             }
         }
     };
 }
 
-pub(crate) use grow;
+#[doc(inline)]
+pub use __grow as grow;
 
 /// Properties that `Shrink` implementations must satisfy
-macro_rules! shrink {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __shrink {
     ($arb_cvrdt_and_subtrahend:ident) => {
-        proptest! {
+        $crate::properties::proptest::proptest! {
             #[test]
             fn del_monotonic((x, u) in $arb_cvrdt_and_subtrahend()) {
                 let mut y = x.clone();
-                Shrink::del(&mut y, u);
-                prop_assert!(Grow::le(&x, &y));
+                $crate::Shrink::del(&mut y, u);
+                $crate::properties::proptest::prop_assert!($crate::Grow::le(&x, &y));
             }
         }
     };
 }
 
-pub(crate) use shrink;
+#[doc(inline)]
+pub use __shrink as shrink;
@@ -0,0 +1,521 @@
+//! Replica handles for the vectorized counters (and accumulators)
+//!
+//! A vectorized counter like a [`GCounter`](../g_counter/struct.GCounter.html) stores its local
+//! replica's `id` alongside its counts, but merging two counters has to pick _some_ `id`, and
+//! takes the minimum. Merging a remote state into a local one can therefore silently change which
+//! slot the local replica increments. A [`Replica`](struct.Replica.html) keeps the local `id`
+//! outside of the merged state, so it stays put no matter what we merge in.
+//!
+//! A `Replica` also tracks _retired_ slots: once a replica leaves the cluster for good, any other
+//! replica can retire its slot, folding that slot's counts into a shared `base` that is merged like
+//! any other state. Counts for a retired slot that arrive late (from replicas that hadn't yet heard
+//! about the retirement) are folded into the `base` too, so retiring never changes a query.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::replica::Replica;
+//! use cvrdt_exposition::{GCounter, Grow};
+//! let mut x = Replica::new(1, GCounter::new((0, vec![0; 3])));
+//! let mut y = x.fork(2);
+//! x.add(());
+//! y.add(());
+//! y.add(());
+//! x.merge(&y);
+//! // a plain `GCounter` would now have `id` 0
+//! assert_eq!(x.id(), 1);
+//! assert_eq!(x.query(&()), 3);
+//! // replica 2 leaves, and replica 1 retires its slot
+//! x.retire(2);
+//! assert_eq!(x.state().counts, [0, 1, 0]);
+//! assert_eq!(x.base().counts, [0, 0, 2]);
+//! assert_eq!(x.query(&()), 3);
+//! // merging replica 2's stale state doesn't double count
+//! x.merge(&y);
+//! assert_eq!(x.query(&()), 3);
+//! ```
+
+use crate::accumulator::{Accumulator, Amount};
+use crate::count::Count;
+use crate::g_counter::{GCounter, GCounterN};
+use crate::histogram::Histogram;
+use crate::mean_counter::MeanCounter;
+use crate::pn_counter::{PNCounter, PNCounterN};
+use crate::traits::{Grow, Shrink};
+use std::collections::BTreeSet;
+
+/// A vectorized counter, with one slot of counts per replica
+pub trait Slotted: Grow {
+    /// The number of replica slots
+    fn slots(&self) -> usize;
+
+    /// The slot where local updates occur
+    fn id(&self) -> usize;
+
+    /// Change the slot where local updates occur
+    ///
+    /// # Panics
+    ///
+    /// If `id` isn't a valid slot
+    fn set_id(&mut self, id: usize);
+
+    /// A counter with the same slots and `id`, but every count zero
+    #[must_use]
+    fn zero(&self) -> Self;
+
+    /// Remove the counts in `slot`, returning them as a counter that's zero in every other slot
+    ///
+    /// # Panics
+    ///
+    /// If `slot` isn't a valid slot
+    fn take_slot(&mut self, slot: usize) -> Self;
+}
+
+impl<T: Count> Slotted for GCounter<T> {
+    fn slots(&self) -> usize {
+        self.counts.len()
+    }
+    fn id(&self) -> usize {
+        self.id
+    }
+    fn set_id(&mut self, id: usize) {
+        assert!(id < self.slots(), "ID too large");
+        self.id = id;
+    }
+    fn zero(&self) -> Self {
+        GCounter {
+            id: self.id,
+            counts: vec![T::ZERO; self.slots()],
+        }
+    }
+    fn take_slot(&mut self, slot: usize) -> Self {
+        let mut taken = self.zero();
+        taken.counts[slot] = std::mem::take(&mut self.counts[slot]);
+        taken
+    }
+}
+
+impl<const N: usize, T: Count> Slotted for GCounterN<N, T> {
+    fn slots(&self) -> usize {
+        N
+    }
+    fn id(&self) -> usize {
+        self.id
+    }
+    fn set_id(&mut self, id: usize) {
+        assert!(id < N, "ID too large");
+        self.id = id;
+    }
+    fn zero(&self) -> Self {
+        GCounterN {
+            id: self.id,
+            counts: [T::ZERO; N],
+        }
+    }
+    fn take_slot(&mut self, slot: usize) -> Self {
+        let mut taken = self.zero();
+        taken.counts[slot] = std::mem::take(&mut self.counts[slot]);
+        taken
+    }
+}
+
+impl<T: Count> Slotted for PNCounter<T> {
+    fn slots(&self) -> usize {
+        self.positive.len()
+    }
+    fn id(&self) -> usize {
+        self.id
+    }
+    fn set_id(&mut self, id: usize) {
+        assert!(id < self.slots(), "ID too large");
+        self.id = id;
+    }
+    fn zero(&self) -> Self {
+        PNCounter {
+            id: self.id,
+            positive: vec![T::ZERO; self.slots()],
+            negative: vec![T::ZERO; self.slots()],
+        }
+    }
+    fn take_slot(&mut self, slot: usize) -> Self {
+        let mut taken = self.zero();
+        taken.positive[slot] = std::mem::take(&mut self.positive[slot]);
+        taken.negative[slot] = std::mem::take(&mut self.negative[slot]);
+        taken
+    }
+}
+
+impl<const N: usize, T: Count> Slotted for PNCounterN<N, T> {
+    fn slots(&self) -> usize {
+        N
+    }
+    fn id(&self) -> usize {
+        self.id
+    }
+    fn set_id(&mut self, id: usize) {
+        assert!(id < N, "ID too large");
+        self.id = id;
+    }
+    fn zero(&self) -> Self {
+        PNCounterN {
+            id: self.id,
+            positive: [T::ZERO; N],
+            negative: [T::ZERO; N],
+        }
+    }
+    fn take_slot(&mut self, slot: usize) -> Self {
+        let mut taken = self.zero();
+        taken.positive[slot] = std::mem::take(&mut self.positive[slot]);
+        taken.negative[slot] = std::mem::take(&mut self.negative[slot]);
+        taken
+    }
+}
+
+impl<A: Amount> Slotted for Accumulator<A> {
+    fn slots(&self) -> usize {
+        self.totals.len()
+    }
+    fn id(&self) -> usize {
+        self.id
+    }
+    fn set_id(&mut self, id: usize) {
+        assert!(id < self.slots(), "ID too large");
+        self.id = id;
+    }
+    fn zero(&self) -> Self {
+        Accumulator {
+            id: self.id,
+            totals: vec![A::ZERO; self.slots()],
+        }
+    }
+    fn take_slot(&mut self, slot: usize) -> Self {
+        let mut taken = self.zero();
+        taken.totals[slot] = std::mem::replace(&mut self.totals[slot], A::ZERO);
+        taken
+    }
+}
+
+impl<A: Amount> Slotted for MeanCounter<A> {
+    fn slots(&self) -> usize {
+        self.count.slots()
+    }
+    fn id(&self) -> usize {
+        self.count.id
+    }
+    fn set_id(&mut self, id: usize) {
+        self.sum.set_id(id);
+        self.count.set_id(id);
+    }
+    fn zero(&self) -> Self {
+        MeanCounter {
+            sum: self.sum.zero(),
+            count: self.count.zero(),
+        }
+    }
+    fn take_slot(&mut self, slot: usize) -> Self {
+        MeanCounter {
+            sum: self.sum.take_slot(slot),
+            count: self.count.take_slot(slot),
+        }
+    }
+}
+
+impl<X: Clone + Ord> Slotted for Histogram<X> {
+    fn slots(&self) -> usize {
+        self.buckets[0].slots()
+    }
+    fn id(&self) -> usize {
+        self.buckets[0].id
+    }
+    fn set_id(&mut self, id: usize) {
+        for b in &mut self.buckets {
+            b.set_id(id);
+        }
+    }
+    fn zero(&self) -> Self {
+        Histogram {
+            bounds: self.bounds.clone(),
+            buckets: self.buckets.iter().map(Slotted::zero).collect(),
+        }
+    }
+    fn take_slot(&mut self, slot: usize) -> Self {
+        Histogram {
+            bounds: self.bounds.clone(),
+            buckets: self.buckets.iter_mut().map(|b| b.take_slot(slot)).collect(),
+        }
+    }
+}
+
+/// A handle on a local replica of a vectorized counter, whose `id` is stable across merges
+///
+/// The shared state is the triple of `state` (counts for live slots), `base` (counts for retired
+/// slots), and the set of `retired` slots; merging two handles merges each of these, then folds
+/// any counts for retired slots from `state` into `base`. Only the `id` is local.
+///
+/// # Panics
+///
+/// `new` and `fork` panic if the `id` isn't a valid slot, or (for `fork`) if it's been retired:
+///
+/// ```should_panic
+/// // this will panic
+/// use cvrdt_exposition::replica::Replica;
+/// use cvrdt_exposition::{GCounter, Grow};
+/// let x = Replica::new(3, GCounter::new((0, vec![0; 3])));
+/// ```
+///
+/// `retire` panics if asked to retire the local replica's own slot, since it could then no longer
+/// be updated. Like their counters, merging handles with different numbers of slots panics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replica<C: Slotted> {
+    id: usize,
+    state: C,
+    base: C,
+    retired: BTreeSet<usize>,
+}
+
+impl<C: Slotted> Replica<C> {
+    /// Start a handle for replica `id`, with no retired slots
+    ///
+    /// # Panics
+    ///
+    /// If `id` isn't a valid slot of `state`
+    pub fn new(id: usize, mut state: C) -> Self {
+        state.set_id(id);
+        let base = state.zero();
+        Replica {
+            id,
+            state,
+            base,
+            retired: BTreeSet::new(),
+        }
+    }
+
+    /// A handle for a new replica `id`, starting from this one's shared state
+    ///
+    /// # Panics
+    ///
+    /// If `id` isn't a valid slot, or if it's been retired
+    #[must_use]
+    pub fn fork(&self, id: usize) -> Self {
+        assert!(!self.retired.contains(&id), "ID retired");
+        let mut forked = self.clone();
+        forked.state.set_id(id);
+        forked.id = id;
+        forked
+    }
+
+    /// The slot where this replica's updates occur
+    #[must_use]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The counts for live slots
+    #[must_use]
+    pub fn state(&self) -> &C {
+        &self.state
+    }
+
+    /// The counts folded in from retired slots
+    #[must_use]
+    pub fn base(&self) -> &C {
+        &self.base
+    }
+
+    /// The retired slots
+    #[must_use]
+    pub fn retired(&self) -> &BTreeSet<usize> {
+        &self.retired
+    }
+
+    /// Apply a local update at this replica's slot
+    pub fn add(&mut self, update: C::Update) {
+        self.state.add(update);
+    }
+
+    /// Query the counter, counting both live and retired slots
+    #[must_use]
+    pub fn query(&self, query: &C::Query) -> C::Value {
+        self.state.merge(&self.base).query(query)
+    }
+
+    /// Merge another handle's shared state into this one, keeping our `id`
+    pub fn merge(&mut self, other: &Self) {
+        self.base = self.base.merge(&other.base);
+        self.retired.extend(other.retired.iter().copied());
+        self.merge_state(&other.state);
+    }
+
+    /// Merge a remote counter into this handle's live state, keeping our `id`
+    ///
+    /// Any counts `other` has for retired slots are folded into `base`.
+    pub fn merge_state(&mut self, other: &C) {
+        self.state = self.state.merge(other);
+        self.state.set_id(self.id);
+        self.fold();
+    }
+
+    /// Retire `slot`, folding its counts into `base`, and returning whether it was newly retired
+    ///
+    /// Only retire the slot of a replica that has left for good: its counts are frozen from here
+    /// on, and its `id` can't be reused.
+    ///
+    /// # Panics
+    ///
+    /// If `slot` is this replica's `id`, or isn't a valid slot
+    pub fn retire(&mut self, slot: usize) -> bool {
+        assert!(slot != self.id, "Cannot retire the local replica");
+        assert!(slot < self.state.slots(), "ID too large");
+        let retired = self.retired.insert(slot);
+        self.fold();
+        retired
+    }
+
+    fn fold(&mut self) {
+        for &slot in &self.retired {
+            let taken = self.state.take_slot(slot);
+            self.base = self.base.merge(&taken);
+        }
+    }
+}
+
+impl<C: Slotted + Shrink> Replica<C> {
+    /// Apply a local deletion at this replica's slot
+    pub fn del(&mut self, update: C::Update) {
+        self.state.del(update);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const SLOTS: usize = 4;
+
+    /// Replicas of a `PNCounterN` receiving updates, merges, and retirements
+    #[derive(Debug, Clone)]
+    enum Step {
+        Add(usize),
+        Del(usize),
+        Merge(usize, usize),
+        MergeStale(usize, usize),
+        Retire(usize, usize),
+    }
+
+    fn step() -> impl Strategy<Value = Step> {
+        prop_oneof![
+            (0..SLOTS).prop_map(Step::Add),
+            (0..SLOTS).prop_map(Step::Del),
+            (0..SLOTS, 0..SLOTS).prop_map(|(i, j)| Step::Merge(i, j)),
+            (0..SLOTS, 0..SLOTS).prop_map(|(i, j)| Step::MergeStale(i, j)),
+            (0..SLOTS, 0..SLOTS).prop_map(|(i, j)| Step::Retire(i, j)),
+        ]
+    }
+
+    /// Drop any steps that would update or retire from a replica after its slot was retired
+    fn normalize(steps: &[Step]) -> Vec<Step> {
+        let mut gone = BTreeSet::new();
+        let mut kept = Vec::new();
+        for step in steps {
+            match *step {
+                Step::Add(i) | Step::Del(i) | Step::Retire(i, _) if gone.contains(&i) => continue,
+                Step::Retire(i, j) if i == j => continue,
+                Step::Retire(_, j) => {
+                    gone.insert(j);
+                }
+                _ => {}
+            }
+            kept.push(step.clone());
+        }
+        kept
+    }
+
+    /// Run (normalized) `steps` from scratch, returning the final handles
+    fn run(steps: &[Step]) -> Vec<Replica<PNCounterN<SLOTS>>> {
+        let start = Replica::new(0, PNCounterN::new((0, [0; SLOTS], [0; SLOTS])));
+        let mut replicas: Vec<_> = (0..SLOTS).map(|id| start.fork(id)).collect();
+        // every state each replica has had, for merging in stale states
+        let mut history: Vec<_> = replicas.iter().map(|r| r.state().clone()).collect();
+        for step in steps {
+            match *step {
+                Step::Add(i) => replicas[i].add(()),
+                Step::Del(i) => replicas[i].del(()),
+                Step::Merge(i, j) => {
+                    let other = replicas[j].clone();
+                    replicas[i].merge(&other);
+                }
+                Step::MergeStale(i, j) => replicas[i].merge_state(&history[j]),
+                Step::Retire(i, j) => {
+                    replicas[i].retire(j);
+                }
+            }
+            for (h, r) in history.iter_mut().zip(&replicas) {
+                *h = h.merge(r.state());
+            }
+        }
+        replicas
+    }
+
+    proptest! {
+        #[test]
+        fn ids_survive_merges(steps in prop::collection::vec(step(), 0..50)) {
+            for (id, r) in run(&normalize(&steps)).iter().enumerate() {
+                prop_assert_eq!(r.id(), id);
+                prop_assert_eq!(r.state().id, id);
+            }
+        }
+
+        #[test]
+        fn retiring_never_changes_counts(steps in prop::collection::vec(step(), 0..50)) {
+            let steps = normalize(&steps);
+            let replicas = run(&steps);
+            let mut all = replicas[0].clone();
+            for r in &replicas {
+                all.merge(r);
+            }
+            // the same steps, minus retirements
+            let kept: Vec<_> = steps
+                .iter()
+                .filter(|s| !matches!(s, Step::Retire(..)))
+                .cloned()
+                .collect();
+            let reference = run(&kept);
+            let mut expected = reference[0].state().clone();
+            for r in &reference {
+                expected = expected.merge(r.state());
+            }
+            prop_assert_eq!(all.state().merge(all.base()), expected);
+            for &slot in all.retired() {
+                prop_assert_eq!(all.state().positive[slot], 0);
+                prop_assert_eq!(all.state().negative[slot], 0);
+            }
+        }
+
+        #[test]
+        fn merges_commute(steps in prop::collection::vec(step(), 0..50), i in 0..SLOTS, j in 0..SLOTS) {
+            let replicas = run(&normalize(&steps));
+            let (mut x, mut y) = (replicas[i].clone(), replicas[j].clone());
+            x.merge(&replicas[j]);
+            y.merge(&replicas[i]);
+            prop_assert_eq!(x.state().merge(x.base()), y.state().merge(y.base()));
+            prop_assert_eq!(x.base(), y.base());
+            prop_assert_eq!(x.retired(), y.retired());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ID retired")]
+    fn cannot_fork_retired() {
+        let mut x = Replica::new(0, GCounter::new((0, vec![0; 2])));
+        x.retire(1);
+        let _ = x.fork(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot retire the local replica")]
+    fn cannot_retire_self() {
+        let mut x = Replica::new(0, GCounter::new((0, vec![0; 2])));
+        x.retire(0);
+    }
+}
@@ -0,0 +1,339 @@
+//! Backing stores for [`GSet`](../g_set/struct.GSet.html) and
+//! [`TwoPhaseSet`](../two_phase_set/struct.TwoPhaseSet.html)
+//!
+//! Both set `CvRDTs` default to a [`HashSet`], which needs `Hash` elements and iterates in an
+//! arbitrary order. Swap in a [`BTreeSet`] or a [`SortedVec`](struct.SortedVec.html) to use
+//! elements that are only `Ord`, or to iterate, compare, and serialize payloads in a
+//! deterministic order.
+//!
+//! # Examples
+//!
+//! ```
+//! use cvrdt_exposition::set::SortedVec;
+//! use cvrdt_exposition::{GSet, Grow};
+//! let x: GSet<char, SortedVec<char>> = GSet::new("cab".chars().collect());
+//! let y = GSet::new("dcb".chars().collect());
+//! assert_eq!(x.merge(&y).values.as_slice(), ['a', 'b', 'c', 'd']);
+//! ```
+
+use std::collections::{BTreeSet, HashSet};
+use std::hash::Hash;
+
+/// A set of elements, as stored by the set `CvRDTs`
+///
+/// Equal sets must compare equal with `==`, whatever order their elements were inserted in.
+pub trait Set:
+    Clone + Eq + Default + FromIterator<Self::Element> + IntoIterator<Item = Self::Element>
+{
+    /// The elements of this set
+    type Element: Clone + Eq;
+
+    /// An iterator over borrowed elements
+    type Iter<'a>: Iterator<Item = &'a Self::Element>
+    where
+        Self: 'a;
+
+    /// Add an element, returning whether it was new
+    fn insert(&mut self, x: Self::Element) -> bool;
+
+    /// Remove an element, returning whether it was present
+    fn remove(&mut self, x: &Self::Element) -> bool;
+
+    /// Is `x` in this set?
+    fn contains(&self, x: &Self::Element) -> bool;
+
+    /// The number of elements in this set
+    fn len(&self) -> usize;
+
+    /// Is this set empty?
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the elements of this set
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Is every element of this set also in `other`?
+    fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|x| other.contains(x))
+    }
+
+    /// The elements in either this set or `other`
+    #[must_use]
+    fn union(&self, other: &Self) -> Self {
+        let mut u = self.clone();
+        for x in other.iter() {
+            u.insert(x.clone());
+        }
+        u
+    }
+}
+
+/// Names the [`Set`](trait.Set.html) backend `S` that stores elements of this type
+///
+/// The set `CvRDTs` are generic over both their element type `X` and their backend `S`, but only
+/// store `S`s. Their fields are typed `<X as Backed<S>>::Set`, which is just `S`, so that they
+/// still mention `X` and can be built with plain struct literals.
+pub trait Backed<S> {
+    /// The backend, i.e. `S` itself
+    type Set;
+}
+
+impl<X, S: Set<Element = X>> Backed<S> for X {
+    type Set = S;
+}
+
+impl<X: Clone + Eq + Hash> Set for HashSet<X> {
+    type Element = X;
+    type Iter<'a>
+        = std::collections::hash_set::Iter<'a, X>
+    where
+        X: 'a;
+
+    fn insert(&mut self, x: X) -> bool {
+        HashSet::insert(self, x)
+    }
+    fn remove(&mut self, x: &X) -> bool {
+        HashSet::remove(self, x)
+    }
+    fn contains(&self, x: &X) -> bool {
+        HashSet::contains(self, x)
+    }
+    fn len(&self) -> usize {
+        HashSet::len(self)
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        HashSet::iter(self)
+    }
+    fn is_subset(&self, other: &Self) -> bool {
+        HashSet::is_subset(self, other)
+    }
+}
+
+impl<X: Clone + Ord> Set for BTreeSet<X> {
+    type Element = X;
+    type Iter<'a>
+        = std::collections::btree_set::Iter<'a, X>
+    where
+        X: 'a;
+
+    fn insert(&mut self, x: X) -> bool {
+        BTreeSet::insert(self, x)
+    }
+    fn remove(&mut self, x: &X) -> bool {
+        BTreeSet::remove(self, x)
+    }
+    fn contains(&self, x: &X) -> bool {
+        BTreeSet::contains(self, x)
+    }
+    fn len(&self) -> usize {
+        BTreeSet::len(self)
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        BTreeSet::iter(self)
+    }
+    fn is_subset(&self, other: &Self) -> bool {
+        BTreeSet::is_subset(self, other)
+    }
+}
+
+/// A set stored as a sorted, deduplicated `Vec`
+///
+/// Compact, and fast to iterate, compare, and merge, at the cost of `O(n)` insertions and
+/// removals; a good fit for sets that are mostly merged rather than updated one element at a time.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::set::{Set, SortedVec};
+/// let mut xs = SortedVec::from(vec![3, 1, 2, 3]);
+/// assert_eq!(xs.as_slice(), [1, 2, 3]);
+/// assert!(xs.insert(0));
+/// assert!(!xs.insert(2));
+/// assert!(xs.remove(&3));
+/// assert_eq!(xs.as_slice(), [0, 1, 2]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<X>", into = "Vec<X>")
+)]
+pub struct SortedVec<X: Clone + Ord> {
+    items: Vec<X>,
+}
+
+impl<X: Clone + Ord> SortedVec<X> {
+    /// The elements of this set, in ascending order
+    #[must_use]
+    pub fn as_slice(&self) -> &[X] {
+        &self.items
+    }
+}
+
+impl<X: Clone + Ord> Default for SortedVec<X> {
+    fn default() -> Self {
+        SortedVec { items: Vec::new() }
+    }
+}
+
+impl<X: Clone + Ord> From<Vec<X>> for SortedVec<X> {
+    fn from(mut items: Vec<X>) -> Self {
+        items.sort_unstable();
+        items.dedup();
+        SortedVec { items }
+    }
+}
+
+impl<X: Clone + Ord> From<SortedVec<X>> for Vec<X> {
+    fn from(xs: SortedVec<X>) -> Self {
+        xs.items
+    }
+}
+
+impl<X: Clone + Ord> IntoIterator for SortedVec<X> {
+    type Item = X;
+    type IntoIter = std::vec::IntoIter<X>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<X: Clone + Ord> FromIterator<X> for SortedVec<X> {
+    fn from_iter<I: IntoIterator<Item = X>>(iter: I) -> Self {
+        SortedVec::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<X: Clone + Ord> Set for SortedVec<X> {
+    type Element = X;
+    type Iter<'a>
+        = std::slice::Iter<'a, X>
+    where
+        X: 'a;
+
+    fn insert(&mut self, x: X) -> bool {
+        match self.items.binary_search(&x) {
+            Ok(_) => false,
+            Err(i) => {
+                self.items.insert(i, x);
+                true
+            }
+        }
+    }
+    fn remove(&mut self, x: &X) -> bool {
+        match self.items.binary_search(x) {
+            Ok(i) => {
+                self.items.remove(i);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+    fn contains(&self, x: &X) -> bool {
+        self.items.binary_search(x).is_ok()
+    }
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+    fn iter(&self) -> Self::Iter<'_> {
+        self.items.iter()
+    }
+    fn is_subset(&self, other: &Self) -> bool {
+        let mut others = other.items.iter();
+        self.len() <= other.len() && self.items.iter().all(|x| others.any(|y| y == x))
+    }
+    fn union(&self, other: &Self) -> Self {
+        let (mut xs, mut ys) = (self.items.iter().peekable(), other.items.iter().peekable());
+        let mut items = Vec::with_capacity(self.len().max(other.len()));
+        while let (Some(x), Some(y)) = (xs.peek(), ys.peek()) {
+            match x.cmp(y) {
+                std::cmp::Ordering::Less => items.extend(xs.next().cloned()),
+                std::cmp::Ordering::Greater => items.extend(ys.next().cloned()),
+                std::cmp::Ordering::Equal => {
+                    items.extend(xs.next().cloned());
+                    ys.next();
+                }
+            }
+        }
+        items.extend(xs.cloned());
+        items.extend(ys.cloned());
+        SortedVec { items }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<X: proptest::arbitrary::Arbitrary + Clone + Ord> proptest::arbitrary::Arbitrary
+    for SortedVec<X>
+{
+    type Parameters = <BTreeSet<X> as proptest::arbitrary::Arbitrary>::Parameters;
+    type Strategy = proptest::strategy::Map<
+        proptest::arbitrary::StrategyFor<BTreeSet<X>>,
+        fn(BTreeSet<X>) -> SortedVec<X>,
+    >;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any_with::<BTreeSet<X>>(args).prop_map(|xs| SortedVec {
+            items: xs.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Compare a `SortedVec` to a `BTreeSet` model
+    fn agrees(xs: &SortedVec<i8>, model: &BTreeSet<i8>) -> bool {
+        xs.iter().eq(model.iter())
+    }
+
+    proptest! {
+        #[test]
+        fn sorted_vec_matches_b_tree_set(
+            xs in any::<Vec<i8>>(),
+            ys in any::<Vec<i8>>(),
+            ops in any::<Vec<(bool, i8)>>(),
+        ) {
+            let (mut x, y) = (SortedVec::from(xs.clone()), SortedVec::from(ys.clone()));
+            let (mut mx, my): (BTreeSet<_>, BTreeSet<_>) =
+                (xs.into_iter().collect(), ys.into_iter().collect());
+            prop_assert!(agrees(&x, &mx));
+            prop_assert!(agrees(&x.union(&y), &mx.union(&my).copied().collect()));
+            prop_assert_eq!(Set::is_subset(&x, &y), mx.is_subset(&my));
+            prop_assert!(Set::is_subset(&x, &x.union(&y)));
+            for (insert, v) in ops {
+                if insert {
+                    prop_assert_eq!(Set::insert(&mut x, v), mx.insert(v));
+                } else {
+                    prop_assert_eq!(Set::remove(&mut x, &v), mx.remove(&v));
+                }
+                prop_assert_eq!(Set::contains(&x, &v), mx.contains(&v));
+            }
+            prop_assert!(agrees(&x, &mx));
+            prop_assert_eq!(Set::len(&x), mx.len());
+        }
+
+        #[test]
+        fn backends_agree(xs in any::<Vec<i8>>(), ys in any::<Vec<i8>>()) {
+            fn check<S: Set<Element = i8>>(xs: &[i8], ys: &[i8]) -> (BTreeSet<i8>, bool) {
+                let (x, y): (S, S) = (xs.iter().copied().collect(), ys.iter().copied().collect());
+                (x.union(&y).iter().copied().collect(), x.is_subset(&y))
+            }
+            let expected = check::<BTreeSet<i8>>(&xs, &ys);
+            prop_assert_eq!(check::<HashSet<i8>>(&xs, &ys), expected.clone());
+            prop_assert_eq!(check::<SortedVec<i8>>(&xs, &ys), expected);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_sorts_and_dedups() {
+        let xs: SortedVec<u8> = serde_json::from_str("[3, 1, 3, 2]").unwrap();
+        assert_eq!(xs.as_slice(), [1, 2, 3]);
+        assert_eq!(serde_json::to_string(&xs).unwrap(), "[1,2,3]");
+    }
+}
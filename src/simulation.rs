@@ -0,0 +1,488 @@
+use crate::traits::Grow;
+
+/// A small, fast, seedable pseudorandom number generator
+///
+/// This is [SplitMix64](https://prng.di.unimi.it/splitmix64.c): not cryptographically secure, but
+/// plenty for scheduling simulated events, and it means we needn't depend on `rand`.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::simulation::SplitMix64;
+/// let mut x = SplitMix64::new(1729);
+/// let mut y = SplitMix64::new(1729);
+/// assert_eq!(x.next_u64(), y.next_u64());
+/// assert!(x.below(10) < 10);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Create a new generator from a `seed`
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    /// The next pseudorandom `u64`
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A pseudorandom index in `0..n`
+    ///
+    /// # Panics
+    ///
+    /// If `n` is zero
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Empty range");
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// `true` with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        // the top 53 bits give a uniformly distributed f64 in [0, 1)
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+/// How often things happen in a [`Simulation`](struct.Simulation.html), and how badly the
+/// network behaves
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Probability that some replica performs a local update on each tick
+    pub update: f64,
+    /// Probability that some replica sends its state to another on each tick
+    pub exchange: f64,
+    /// Probability that a message is lost
+    pub drop: f64,
+    /// Probability that a message is delivered twice
+    pub duplicate: f64,
+    /// The most ticks a message can spend in flight
+    pub max_delay: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            update: 0.5,
+            exchange: 0.5,
+            drop: 0.1,
+            duplicate: 0.1,
+            max_delay: 8,
+        }
+    }
+}
+
+/// What happened to the messages in a [`Simulation`](struct.Simulation.html)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Local updates performed
+    pub updates: u64,
+    /// Messages sent
+    pub sent: u64,
+    /// Messages lost, either at random or to a partition
+    pub dropped: u64,
+    /// Extra copies of messages sent
+    pub duplicated: u64,
+    /// Messages delivered and merged
+    pub delivered: u64,
+}
+
+/// A replica's full state, in flight between two replicas
+#[derive(Debug, Clone)]
+struct Envelope<G> {
+    deliver_at: u64,
+    to: usize,
+    from: usize,
+    state: G,
+}
+
+/// A deterministic simulation of replicas of a `CvRDT` exchanging their states over an unreliable
+/// network
+///
+/// On each tick, some replica may perform a local update, some replica may send its full state to
+/// another, and every message that's due is delivered (and merged into its recipient). Messages
+/// can be dropped, duplicated, delayed (and hence reordered), or cut off by a partition. Given
+/// the same seed, configuration, and updates, a simulation always plays out the same way.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::simulation::{Config, Simulation};
+/// use cvrdt_exposition::{GCounter, Grow};
+/// let replicas = (0..4).map(|id| GCounter::new((id, vec![0; 4]))).collect();
+/// let mut sim = Simulation::new(replicas, Config::default(), 1729);
+/// sim.partition(&[0, 0, 1, 1]);
+/// // merging picks an arbitrary `id`, so each replica restores its own before updating
+/// sim.run(100, |i, g, _| {
+///     g.id = i;
+///     g.add(());
+/// });
+/// assert!(!sim.converged());
+/// assert!(sim.heal_and_converge(1_000).is_some());
+/// assert_eq!(sim.replicas[3].query(&()), sim.stats.updates);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Simulation<G: Grow> {
+    /// The replicas being simulated
+    pub replicas: Vec<G>,
+    /// What happened so far
+    pub stats: Stats,
+    config: Config,
+    rng: SplitMix64,
+    tick: u64,
+    groups: Option<Vec<usize>>,
+    in_flight: Vec<Envelope<G>>,
+    equivalent: fn(&G, &G) -> bool,
+}
+
+impl<G: Grow> Simulation<G> {
+    /// Create a new simulation of `replicas`, seeded by `seed`
+    #[must_use]
+    pub fn new(replicas: Vec<G>, config: Config, seed: u64) -> Self {
+        Simulation {
+            replicas,
+            stats: Stats::default(),
+            config,
+            rng: SplitMix64::new(seed),
+            tick: 0,
+            groups: None,
+            in_flight: Vec::new(),
+            equivalent: |x, y| x.le(y) && y.le(x),
+        }
+    }
+
+    /// Decide whether two replicas hold the same state with `equivalent`, rather than by
+    /// comparing them with [`le`](../traits/trait.Grow.html#tymethod.le) in both directions
+    ///
+    /// `le` can't tell every pair of states apart: e.g. two `LWWRegister`s with the same
+    /// timestamp but different values are each `le` the other. Where a type has no
+    /// replica-specific fields, checking that payloads are equal catches such divergence.
+    #[must_use]
+    pub fn with_equivalence(mut self, equivalent: fn(&G, &G) -> bool) -> Self {
+        self.equivalent = equivalent;
+        self
+    }
+
+    /// How many ticks have passed
+    #[must_use]
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Split the network into groups: replica `i` can only reach replicas in group `groups[i]`
+    ///
+    /// # Panics
+    ///
+    /// If `groups` doesn't have exactly one entry per replica
+    pub fn partition(&mut self, groups: &[usize]) {
+        assert_eq!(groups.len(), self.replicas.len(), "One group per replica");
+        self.groups = Some(groups.to_vec());
+    }
+
+    /// Remove any partition
+    pub fn heal(&mut self) {
+        self.groups = None;
+    }
+
+    fn reachable(&self, from: usize, to: usize) -> bool {
+        self.groups.as_ref().is_none_or(|g| g[from] == g[to])
+    }
+
+    fn send(&mut self, from: usize, to: usize) {
+        self.stats.sent += 1;
+        let copies = if self.rng.chance(self.config.duplicate) {
+            self.stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            if !self.reachable(from, to) || self.rng.chance(self.config.drop) {
+                self.stats.dropped += 1;
+                continue;
+            }
+            let delay = self.rng.next_u64() % (self.config.max_delay + 1);
+            self.in_flight.push(Envelope {
+                deliver_at: self.tick + delay,
+                to,
+                from,
+                state: self.replicas[from].clone(),
+            });
+        }
+    }
+
+    fn deliver(&mut self) {
+        let (due, later) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|e| e.deliver_at <= self.tick);
+        self.in_flight = later;
+        for e in due {
+            if self.reachable(e.from, e.to) {
+                self.stats.delivered += 1;
+                self.replicas[e.to] = self.replicas[e.to].merge(&e.state);
+            } else {
+                self.stats.dropped += 1;
+            }
+        }
+    }
+
+    /// Advance the simulation by one tick
+    ///
+    /// # Parameters
+    ///
+    /// - a mutably borrowed reference to `self`
+    /// - `update`, called with a replica's index, the replica, and the simulation's random number
+    ///   generator, to perform a local update on that replica
+    pub fn step(&mut self, mut update: impl FnMut(usize, &mut G, &mut SplitMix64)) {
+        let n = self.replicas.len();
+        if n > 0 && self.rng.chance(self.config.update) {
+            let i = self.rng.below(n);
+            update(i, &mut self.replicas[i], &mut self.rng);
+            self.stats.updates += 1;
+        }
+        self.gossip();
+    }
+
+    /// Advance the simulation by one tick without any local update: maybe send one state, and
+    /// deliver every message that's due
+    fn gossip(&mut self) {
+        let n = self.replicas.len();
+        if n > 1 && self.rng.chance(self.config.exchange) {
+            let from = self.rng.below(n);
+            let to = (from + 1 + self.rng.below(n - 1)) % n;
+            self.send(from, to);
+        }
+        self.deliver();
+        self.tick += 1;
+    }
+
+    /// Advance the simulation by `ticks` ticks, performing local updates via `update` (see
+    /// [`step`](#method.step))
+    pub fn run(&mut self, ticks: u64, mut update: impl FnMut(usize, &mut G, &mut SplitMix64)) {
+        for _ in 0..ticks {
+            self.step(&mut update);
+        }
+    }
+
+    /// Do all replicas hold the same state?
+    ///
+    /// By default, replicas are compared with [`le`](../traits/trait.Grow.html#tymethod.le) in
+    /// both directions, so parts of a payload that identify the replica rather than its state
+    /// (e.g. a `GCounter`'s `id`) needn't agree. See
+    /// [`with_equivalence`](#method.with_equivalence) for a stricter comparison.
+    #[must_use]
+    pub fn converged(&self) -> bool {
+        self.replicas
+            .windows(2)
+            .all(|w| (self.equivalent)(&w[0], &w[1]))
+    }
+
+    /// Heal any partition, stop performing updates, and keep exchanging states until all replicas
+    /// converge
+    ///
+    /// # Returns
+    ///
+    /// `Some(ticks)` if the replicas converged after `ticks` more ticks, or `None` if they hadn't
+    /// converged after `max_ticks`
+    pub fn heal_and_converge(&mut self, max_ticks: u64) -> Option<u64> {
+        self.heal();
+        for t in 0..=max_ticks {
+            if self.converged() {
+                return Some(t);
+            }
+            self.gossip();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GCounter, GSet, LWWRegister, OneWayBoolean, PNCounter, Shrink, TwoPhaseSet};
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+    use std::time::SystemTime;
+
+    static REPLICAS: usize = 5;
+    static TICKS: u64 = 200;
+    static MAX_TICKS: u64 = 100_000;
+
+    fn config() -> impl Strategy<Value = Config> {
+        (0.0..0.9, 0.0..0.5, 0..16u64).prop_map(|(drop, duplicate, max_delay)| Config {
+            drop,
+            duplicate,
+            max_delay,
+            ..Config::default()
+        })
+    }
+
+    fn groups() -> impl Strategy<Value = Vec<usize>> {
+        prop::collection::vec(0..3usize, REPLICAS)
+    }
+
+    /// Partition the network, run with updates, then heal and check that every replica converges
+    fn converges<G: Grow>(
+        replicas: Vec<G>,
+        config: Config,
+        seed: u64,
+        groups: &[usize],
+        update: impl FnMut(usize, &mut G, &mut SplitMix64),
+    ) -> Simulation<G> {
+        let mut sim = Simulation::new(replicas, config, seed);
+        sim.partition(groups);
+        sim.run(TICKS, update);
+        assert!(sim.heal_and_converge(MAX_TICKS).is_some());
+        sim
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn g_counter_converges(config in config(), seed in any::<u64>(), groups in groups()) {
+            let replicas = (0..REPLICAS).map(|id| GCounter::new((id, vec![0; REPLICAS]))).collect();
+            let sim = converges(replicas, config, seed, &groups, |i, g, _| {
+                g.id = i;
+                g.add(());
+            });
+            // no update was lost along the way
+            for g in &sim.replicas {
+                prop_assert_eq!(g.query(&()), sim.stats.updates);
+            }
+        }
+
+        #[test]
+        fn pn_counter_converges(config in config(), seed in any::<u64>(), groups in groups()) {
+            let replicas = (0..REPLICAS)
+                .map(|id| PNCounter::new((id, vec![0; REPLICAS], vec![0; REPLICAS])))
+                .collect();
+            let (mut adds, mut dels) = (0u64, 0u64);
+            let sim = converges(replicas, config, seed, &groups, |i, p, rng| {
+                p.id = i;
+                if rng.chance(0.5) {
+                    p.add(());
+                    adds += 1;
+                } else {
+                    p.del(());
+                    dels += 1;
+                }
+            });
+            prop_assert_eq!(adds + dels, sim.stats.updates);
+            // no update was lost along the way (`query` panics if there were more dels than adds)
+            for p in &sim.replicas {
+                prop_assert_eq!(p.positive.iter().sum::<u64>(), adds);
+                prop_assert_eq!(p.negative.iter().sum::<u64>(), dels);
+                if adds >= dels {
+                    prop_assert_eq!(p.query(&()), adds - dels);
+                }
+            }
+        }
+
+        #[test]
+        fn g_set_converges(config in config(), seed in any::<u64>(), groups in groups()) {
+            let replicas = vec![GSet::new(HashSet::new()); REPLICAS];
+            let sim = converges(replicas, config, seed, &groups, |_, g, rng| g.add(rng.below(100)));
+            // every update happened somewhere, so the converged set can't be empty
+            prop_assert_eq!(sim.replicas[0].values.is_empty(), sim.stats.updates == 0);
+        }
+
+        #[test]
+        fn two_phase_set_converges(config in config(), seed in any::<u64>(), groups in groups()) {
+            let replicas = vec![TwoPhaseSet::new((HashSet::new(), HashSet::new())); REPLICAS];
+            converges(replicas, config, seed, &groups, |_, t, rng| {
+                let x = rng.below(20);
+                if t.query(&x) && rng.chance(0.5) {
+                    t.del(x);
+                } else {
+                    t.add(x);
+                }
+            });
+        }
+
+        #[test]
+        fn lww_register_converges(config in config(), seed in any::<u64>(), groups in groups()) {
+            let replicas = vec![LWWRegister::new((0, SystemTime::now())); REPLICAS];
+            // registers have no replica-specific fields, so converged ones must be identical
+            let mut sim = Simulation::new(replicas, config, seed).with_equivalence(|x, y| x == y);
+            sim.partition(&groups);
+            sim.run(TICKS, |i, r, _| r.add(i));
+            prop_assert!(sim.heal_and_converge(MAX_TICKS).is_some());
+            for r in &sim.replicas {
+                prop_assert_eq!(r.query(&()), sim.replicas[0].query(&()));
+            }
+        }
+
+        #[test]
+        fn one_way_boolean_converges(config in config(), seed in any::<u64>(), groups in groups()) {
+            let replicas = vec![OneWayBoolean::new(false); REPLICAS];
+            converges(replicas, config, seed, &groups, |_, b, rng| {
+                if rng.chance(0.01) {
+                    b.add(());
+                }
+            });
+        }
+
+        #[test]
+        fn deterministic(config in config(), seed in any::<u64>(), groups in groups()) {
+            let run = || {
+                let replicas = vec![GSet::new(HashSet::new()); REPLICAS];
+                let mut sim = Simulation::new(replicas, config.clone(), seed);
+                sim.partition(&groups);
+                sim.run(TICKS, |_, g, rng| g.add(rng.below(1_000)));
+                sim
+            };
+            let (x, y) = (run(), run());
+            prop_assert_eq!(x.stats, y.stats);
+            prop_assert_eq!(
+                x.replicas.iter().map(Grow::payload).collect::<Vec<_>>(),
+                y.replicas.iter().map(Grow::payload).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn partitions_block_convergence() {
+        let replicas = (0..REPLICAS)
+            .map(|id| GCounter::new((id, vec![0; REPLICAS])))
+            .collect();
+        let config = Config {
+            drop: 0.0,
+            ..Config::default()
+        };
+        let mut sim = Simulation::new(replicas, config, 1729);
+        sim.partition(&[0, 0, 0, 1, 1]);
+        sim.run(TICKS, |_, g, _| g.add(()));
+        for _ in 0..MAX_TICKS / 10 {
+            sim.step(|_, _, _| {});
+        }
+        assert!(!sim.converged());
+        assert!(sim.stats.dropped > 0);
+        assert!(sim.heal_and_converge(MAX_TICKS).is_some());
+    }
+
+    #[test]
+    fn convergence_ignores_ids() {
+        let replicas = (0..REPLICAS)
+            .map(|id| GCounter::new((id, vec![1; REPLICAS])))
+            .collect();
+        assert!(Simulation::new(replicas, Config::default(), 1729).converged());
+    }
+
+    #[test]
+    fn equivalence_catches_ties() {
+        let now = SystemTime::now();
+        let replicas = vec![LWWRegister::new((0, now)), LWWRegister::new((1, now))];
+        let sim = Simulation::new(replicas, Config::default(), 1729);
+        assert!(sim.converged());
+        assert!(!sim.with_equivalence(|x, y| x == y).converged());
+    }
+}
@@ -1,9 +1,17 @@
+use std::cmp::Ordering;
+
 /// `CvRDTs` that can only grow, i.e. only add items
 pub trait Grow: Clone {
     /// The internal state of our `CvRDT`; sufficient to build a new copy via [`new`](#tymethod.new).
     /// Required to implement `Eq` for testing and verification.
     type Payload: Eq;
 
+    /// A borrowed view of the [`Payload`](#associatedtype.Payload), for comparing payloads without
+    /// cloning them
+    type PayloadRef<'a>: Eq
+    where
+        Self: 'a;
+
     /// Message to update our internal state
     type Update;
 
@@ -19,7 +27,7 @@ pub trait Grow: Clone {
     /// # Parameters
     ///
     /// - `payload`: a payload which fully specifies all information needed to instantiate our data
-    /// structure
+    ///   structure
     ///
     /// # Returns
     ///
@@ -38,6 +46,18 @@ pub trait Grow: Clone {
     /// The payload of this `CvRDT`
     fn payload(&self) -> Self::Payload;
 
+    /// Borrow the [`Payload`](#associatedtype.Payload) (internal state) of this `CvRDT`
+    ///
+    /// # Parameters
+    ///
+    /// - a borrowed reference to `self`
+    ///
+    /// # Returns
+    ///
+    /// A view of the payload of this `CvRDT`, equal for two `CvRDTs` exactly when their
+    /// [`payload`](#tymethod.payload)s are
+    fn payload_ref(&self) -> Self::PayloadRef<'_>;
+
     /// Add an item to the data structure, mutating this `CvRDT` in place
     ///
     /// # Parameters
@@ -63,6 +83,24 @@ pub trait Grow: Clone {
     /// the semilattice induced by [`merge`](#tymethod.merge))
     fn le(&self, other: &Self) -> bool;
 
+    /// Can this `CvRDT` be compared to or merged with another?
+    ///
+    /// # Parameters
+    ///
+    /// - a borrowed reference to `self`
+    /// - a borrowed reference to the other structure
+    ///
+    /// # Returns
+    ///
+    /// `true` unless [`le`](#tymethod.le) or [`merge`](#tymethod.merge) would panic on this pair,
+    /// e.g. for `GCounter`s of different sizes
+    ///
+    /// # Notes
+    /// The default, for `CvRDTs` where any two values can be merged, is always `true`
+    fn compatible(&self, _other: &Self) -> bool {
+        true
+    }
+
     /// Merge this data structure and another into a new `CvRDT`
     ///
     /// # Parameters
@@ -105,3 +143,69 @@ pub trait Shrink: Grow {
     /// Nothing; this data structure is updated in-place
     fn del(&mut self, update: Self::Update);
 }
+
+/// Compare two `CvRDTs` in the semilattice's partial order
+///
+/// # Parameters
+///
+/// - a borrowed reference to one `CvRDT`
+/// - a borrowed reference to the other `CvRDT` to compare
+///
+/// # Returns
+///
+/// - `Some(Equal)` if each of `x` and `y` is [`le`](trait.Grow.html#tymethod.le) the other, even
+///   if they still differ (e.g. two `GCounter`s with the same counts but different `id`s)
+/// - `Some(Less)` if `x` [`le`](trait.Grow.html#tymethod.le) `y` but not vice versa
+/// - `Some(Greater)` if `y` [`le`](trait.Grow.html#tymethod.le) `x` but not vice versa
+/// - `None` otherwise, i.e. if `x` and `y` are concurrent, or if they aren't
+///   [`compatible`](trait.Grow.html#method.compatible)
+pub fn partial_cmp<G: Grow>(x: &G, y: &G) -> Option<Ordering> {
+    if !x.compatible(y) {
+        return None;
+    }
+    match (x.le(y), y.le(x)) {
+        (true, true) => Some(Ordering::Equal),
+        (true, false) => Some(Ordering::Less),
+        (false, true) => Some(Ordering::Greater),
+        (false, false) => None,
+    }
+}
+
+/// A borrowed `CvRDT`, ordered by the semilattice's partial order
+///
+/// None of our `CvRDTs` implement `PartialOrd` themselves: its `le` method would clash with
+/// [`Grow::le`](trait.Grow.html#tymethod.le), and its `==` would have to disagree with the derived
+/// one (which compares `id`s too). Wrap them in a `Lattice` to use the standard comparison
+/// operators instead; `==` here means each is `le` the other, as for
+/// [`partial_cmp`](fn.partial_cmp.html).
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::traits::{partial_cmp, Lattice};
+/// use cvrdt_exposition::{GCounter, Grow};
+/// let x: GCounter = GCounter::new((0, vec![0, 0]));
+/// let mut y: GCounter = GCounter::new((1, vec![0, 0]));
+/// assert!(Lattice(&x) == Lattice(&y));
+/// y.add(());
+/// assert!(Lattice(&x) < Lattice(&y));
+/// assert!(x.le(&y));
+/// // different sizes can't be compared at all
+/// let z: GCounter = GCounter::new((0, vec![0, 0, 0]));
+/// assert_eq!(partial_cmp(&x, &z), None);
+/// assert!(!(Lattice(&x) <= Lattice(&z)) && !(Lattice(&x) >= Lattice(&z)));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Lattice<'a, G>(pub &'a G);
+
+impl<G: Grow> PartialEq for Lattice<'_, G> {
+    fn eq(&self, other: &Self) -> bool {
+        partial_cmp(self.0, other.0) == Some(Ordering::Equal)
+    }
+}
+
+impl<G: Grow> PartialOrd for Lattice<'_, G> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        partial_cmp(self.0, other.0)
+    }
+}
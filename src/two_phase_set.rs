@@ -1,5 +1,8 @@
+use crate::causality::{Dot, Stability, VersionVector};
+use crate::operation::Operation;
+use crate::set::{Backed, Set};
 use crate::traits::{Grow, Shrink};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::hash::Hash;
 
 /// A set that can add or delete values
@@ -36,16 +39,36 @@ use std::hash::Hash;
 /// let z = TwoPhaseSet::new(("8675309abcdefg".chars().collect(), "toremove".chars().collect()));
 /// assert_eq!(x.merge(&y.merge(&z)).payload(), x.merge(&y).merge(&z).payload());
 /// ```
-#[derive(Debug, Clone)]
-pub struct TwoPhaseSet<X: Clone + Eq + Hash> {
+///
+/// Like a [`GSet`](../g_set/struct.GSet.html), the backing [`Set`](../set/trait.Set.html)
+/// defaults to a [`HashSet`]:
+///
+/// ```
+/// use cvrdt_exposition::set::SortedVec;
+/// use cvrdt_exposition::{Grow, Shrink, TwoPhaseSet};
+/// let mut x = TwoPhaseSet::<u8, SortedVec<_>>::new((vec![3, 1].into(), vec![].into()));
+/// x.del(3);
+/// assert_eq!(x.added.as_slice(), [1, 3]);
+/// assert_eq!(x.removed.as_slice(), [3]);
+/// // struct literals work too
+/// let y = TwoPhaseSet { added: vec![1].into(), removed: vec![].into() };
+/// assert!(y.le(&x));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TwoPhaseSet<X: Clone + Eq, S: Set<Element = X> = HashSet<X>> {
     /// The elements that have been added to this set
-    pub added: HashSet<X>,
+    pub added: <X as Backed<S>>::Set,
     /// The elements that have been removed from this set
-    pub removed: HashSet<X>,
+    pub removed: <X as Backed<S>>::Set,
 }
 
-impl<X: Clone + Eq + Hash> Grow for TwoPhaseSet<X> {
-    type Payload = (HashSet<X>, HashSet<X>);
+impl<X: Clone + Eq, S: Set<Element = X>> Grow for TwoPhaseSet<X, S> {
+    type Payload = (S, S);
+    type PayloadRef<'a>
+        = (&'a S, &'a S)
+    where
+        Self: 'a;
     type Update = X;
     type Query = X;
     type Value = bool;
@@ -59,6 +82,9 @@ impl<X: Clone + Eq + Hash> Grow for TwoPhaseSet<X> {
     fn payload(&self) -> Self::Payload {
         (self.added.clone(), self.removed.clone())
     }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (&self.added, &self.removed)
+    }
     fn add(&mut self, update: Self::Update) {
         self.added.insert(update);
     }
@@ -66,17 +92,121 @@ impl<X: Clone + Eq + Hash> Grow for TwoPhaseSet<X> {
         self.added.is_subset(&other.added) && self.removed.is_subset(&other.removed)
     }
     fn merge(&self, other: &Self) -> Self {
-        TwoPhaseSet {
-            added: self.added.union(&other.added).cloned().collect(),
-            removed: self.removed.union(&other.removed).cloned().collect(),
-        }
+        TwoPhaseSet::new((
+            self.added.union(&other.added),
+            self.removed.union(&other.removed),
+        ))
     }
     fn query(&self, query: &Self::Query) -> Self::Value {
         self.added.contains(query) && !self.removed.contains(query)
     }
 }
 
-impl<X: Clone + Eq + Hash> Shrink for TwoPhaseSet<X> {
+impl<X: Clone + Eq, S: Set<Element = X>> TwoPhaseSet<X, S> {
+    /// Iterate over the elements currently in this set, i.e. those added but not removed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cvrdt_exposition::{Shrink, TwoPhaseSet};
+    /// let mut x: TwoPhaseSet<u8> = [1, 2, 3].into_iter().collect();
+    /// x.del(2);
+    /// assert_eq!(x.iter().sum::<u8>(), 4);
+    /// assert_eq!(x.len(), 2);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, X, S> {
+        Iter {
+            added: self.added.iter(),
+            removed: &self.removed,
+        }
+    }
+
+    /// The number of elements currently in this set
+    ///
+    /// # Notes
+    ///
+    /// Takes time linear in the number of elements ever added
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Is this set currently empty?
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// An iterator over the elements currently in a [`TwoPhaseSet`](struct.TwoPhaseSet.html)
+#[derive(Debug)]
+pub struct Iter<'a, X: Clone + Eq + 'a, S: Set<Element = X> + 'a> {
+    added: S::Iter<'a>,
+    removed: &'a S,
+}
+
+impl<'a, X: Clone + Eq + 'a, S: Set<Element = X> + 'a> Iterator for Iter<'a, X, S> {
+    type Item = &'a X;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let removed = self.removed;
+        self.added.by_ref().find(|x| !removed.contains(x))
+    }
+}
+
+/// An iterator that moves out the elements currently in a
+/// [`TwoPhaseSet`](struct.TwoPhaseSet.html)
+#[derive(Debug)]
+pub struct IntoIter<X: Clone + Eq, S: Set<Element = X>> {
+    added: S::IntoIter,
+    removed: S,
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> Iterator for IntoIter<X, S> {
+    type Item = X;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let removed = &self.removed;
+        self.added.by_ref().find(|x| !removed.contains(x))
+    }
+}
+
+impl<'a, X: Clone + Eq, S: Set<Element = X>> IntoIterator for &'a TwoPhaseSet<X, S> {
+    type Item = &'a X;
+    type IntoIter = Iter<'a, X, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> IntoIterator for TwoPhaseSet<X, S> {
+    type Item = X;
+    type IntoIter = IntoIter<X, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            added: self.added.into_iter(),
+            removed: self.removed,
+        }
+    }
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> FromIterator<X> for TwoPhaseSet<X, S> {
+    fn from_iter<I: IntoIterator<Item = X>>(iter: I) -> Self {
+        TwoPhaseSet::new((iter.into_iter().collect(), S::default()))
+    }
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> Extend<X> for TwoPhaseSet<X, S> {
+    fn extend<I: IntoIterator<Item = X>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+impl<X: Clone + Eq, S: Set<Element = X>> Shrink for TwoPhaseSet<X, S> {
     fn del(&mut self, x: X) {
         assert!(
             self.query(&x),
@@ -86,20 +216,404 @@ impl<X: Clone + Eq + Hash> Shrink for TwoPhaseSet<X> {
     }
 }
 
+/// A [`TwoPhaseSet`](struct.TwoPhaseSet.html) whose tombstones can be collected once every
+/// replica has seen them
+///
+/// A `TwoPhaseSet` never forgets: a removed element stays in both `added` and `removed`, so that
+/// merging with a replica that hasn't seen the removal can't bring it back. Once every replica
+/// _has_ seen the removal, no such replica remains, and the element can be purged from both.
+/// This set tags each update with a [`Dot`](../causality/struct.Dot.html) from its `clock`,
+/// remembers the dot of each removal, and [`collect`](#method.collect)s the removals that a
+/// [`Stability`](../causality/struct.Stability.html) oracle reports every replica has seen.
+///
+/// Collecting isn't an update: it shrinks the state. Merging is still a union, though, so still
+/// commutative, associative, and idempotent. Merging a collected replica with an uncollected one
+/// that has seen the removal just brings a tombstone back, for the next `collect` to purge.
+///
+/// Merging with a state that _hasn't_ seen the removal brings the element itself back. That
+/// isn't only a newcomer's state, which
+/// [`Stability::join`](../causality/struct.Stability.html#method.join) turns away: a known
+/// replica's snapshot from before its last ack, or a message it sent then that's still in
+/// flight, is just as stale. Once anything has been collected, merge states that might be stale
+/// with [`checked_merge`](#method.checked_merge), which refuses them.
+///
+/// # Panics
+///
+/// Like a `TwoPhaseSet`, any attempt to `del` an element that isn't in the set will panic.
+///
+/// # Notes
+///
+/// As with any `TwoPhaseSet`, a removed element must never be added again; once its tombstone is
+/// collected, nothing is left to stop it.
+///
+/// # Examples
+///
+/// ```
+/// use cvrdt_exposition::causality::Stability;
+/// use cvrdt_exposition::{CollectableTwoPhaseSet, Grow, Shrink};
+/// let mut a = CollectableTwoPhaseSet::<char, &str>::default();
+/// a.add(('a', "x"));
+/// a.del(('a', "x"));
+/// let b = CollectableTwoPhaseSet::default().merge(&a);
+/// let mut oracle = Stability::new(['a', 'b']);
+/// oracle.ack(&'a', &a.clock);
+/// assert_eq!(a.collect(&oracle), 0);
+/// oracle.ack(&'b', &b.clock);
+/// assert_eq!(a.collect(&oracle), 1);
+/// assert!(a.set.added.is_empty() && a.set.removed.is_empty());
+/// assert_eq!(a.merge(&b).query(&"x"), false);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        into = "UncheckedCollectableTwoPhaseSet<R, X, S>",
+        try_from = "UncheckedCollectableTwoPhaseSet<R, X, S>",
+        bound(
+            serialize = "R: serde::Serialize, X: serde::Serialize, S: serde::Serialize",
+            deserialize = "R: serde::Deserialize<'de>, X: serde::Deserialize<'de>, S: serde::Deserialize<'de>"
+        )
+    )
+)]
+pub struct CollectableTwoPhaseSet<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X> = HashSet<X>> {
+    /// The set itself
+    pub set: TwoPhaseSet<X, S>,
+    /// The dot of each removal that hasn't been collected
+    pub removals: BTreeMap<Dot<R>, X>,
+    /// Every update this replica has seen
+    pub clock: VersionVector<R>,
+}
+
+impl<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X>> CollectableTwoPhaseSet<R, X, S> {
+    /// Rebuild a set that came off the wire, checking that each removal is covered by the
+    /// `clock`, removed from the `set`, and unique
+    pub(crate) fn checked(
+        set: TwoPhaseSet<X, S>,
+        removals: impl IntoIterator<Item = (Dot<R>, X)>,
+        clock: VersionVector<R>,
+    ) -> Result<Self, &'static str> {
+        let mut checked = BTreeMap::new();
+        for (dot, x) in removals {
+            if !clock.contains(&dot) {
+                return Err("Removal not in clock");
+            }
+            if !set.removed.contains(&x) {
+                return Err("Removal not in set");
+            }
+            if checked.insert(dot, x).is_some() {
+                return Err("Duplicate removal");
+            }
+        }
+        Ok(CollectableTwoPhaseSet {
+            set,
+            removals: checked,
+            clock,
+        })
+    }
+
+    /// Merge with `other`, unless it might not have seen a removal that's been collected
+    ///
+    /// Any removal `oracle` reports as stable may already have been collected, so `other` is only
+    /// safe to merge if its `clock` has seen every stable event.
+    ///
+    /// # Returns
+    ///
+    /// The merged state, or `None` if `other` is stale
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cvrdt_exposition::causality::Stability;
+    /// use cvrdt_exposition::{CollectableTwoPhaseSet, Grow, Shrink};
+    /// let mut a = CollectableTwoPhaseSet::<char, &str>::default();
+    /// a.add(('a', "x"));
+    /// let snapshot = a.clone();
+    /// a.del(('a', "x"));
+    /// let mut oracle = Stability::new(['a']);
+    /// oracle.ack(&'a', &a.clock);
+    /// a.collect(&oracle);
+    /// assert!(a.merge(&snapshot).query(&"x"));
+    /// assert_eq!(a.checked_merge(&snapshot, &oracle), None);
+    /// ```
+    #[must_use]
+    pub fn checked_merge(&self, other: &Self, oracle: &Stability<R>) -> Option<Self> {
+        oracle.stable().le(&other.clock).then(|| self.merge(other))
+    }
+
+    /// Purge every element whose removal `oracle` reports as stable
+    ///
+    /// # Returns
+    ///
+    /// The number of elements purged
+    pub fn collect(&mut self, oracle: &Stability<R>) -> usize {
+        let stable: Vec<Dot<R>> = self
+            .removals
+            .keys()
+            .filter(|dot| oracle.is_stable(dot))
+            .cloned()
+            .collect();
+        for dot in &stable {
+            if let Some(x) = self.removals.remove(dot) {
+                self.set.added.remove(&x);
+                self.set.removed.remove(&x);
+            }
+        }
+        stable.len()
+    }
+}
+
+impl<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X>> Default
+    for CollectableTwoPhaseSet<R, X, S>
+{
+    fn default() -> Self {
+        CollectableTwoPhaseSet {
+            set: TwoPhaseSet::new((S::default(), S::default())),
+            removals: BTreeMap::new(),
+            clock: VersionVector::default(),
+        }
+    }
+}
+
+impl<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X>> Grow for CollectableTwoPhaseSet<R, X, S> {
+    type Payload = ((S, S), BTreeMap<Dot<R>, X>, BTreeMap<R, u64>);
+    type PayloadRef<'a>
+        = (
+        (&'a S, &'a S),
+        &'a BTreeMap<Dot<R>, X>,
+        &'a BTreeMap<R, u64>,
+    )
+    where
+        Self: 'a;
+    type Update = (R, X);
+    type Query = X;
+    type Value = bool;
+
+    fn new(payload: Self::Payload) -> Self {
+        CollectableTwoPhaseSet {
+            set: TwoPhaseSet::new(payload.0),
+            removals: payload.1,
+            clock: VersionVector::new(payload.2),
+        }
+    }
+    fn payload(&self) -> Self::Payload {
+        (
+            self.set.payload(),
+            self.removals.clone(),
+            self.clock.payload(),
+        )
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        (self.set.payload_ref(), &self.removals, &self.clock.counts)
+    }
+    fn add(&mut self, update: Self::Update) {
+        self.clock.increment(update.0);
+        self.set.add(update.1);
+    }
+    fn le(&self, other: &Self) -> bool {
+        self.set.le(&other.set)
+            && self
+                .removals
+                .keys()
+                .all(|dot| other.removals.contains_key(dot))
+            && self.clock.le(&other.clock)
+    }
+    fn merge(&self, other: &Self) -> Self {
+        let mut removals = self.removals.clone();
+        removals.extend(other.removals.iter().map(|(d, x)| (d.clone(), x.clone())));
+        CollectableTwoPhaseSet {
+            set: self.set.merge(&other.set),
+            removals,
+            clock: self.clock.merge(&other.clock),
+        }
+    }
+    fn query(&self, query: &Self::Query) -> Self::Value {
+        self.set.query(query)
+    }
+}
+
+impl<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X>> Shrink
+    for CollectableTwoPhaseSet<R, X, S>
+{
+    fn del(&mut self, update: Self::Update) {
+        let (replica, x) = update;
+        self.set.del(x.clone());
+        let dot = self.clock.increment(replica);
+        self.removals.insert(dot, x);
+    }
+}
+
+/// A `CollectableTwoPhaseSet` as it goes over the wire: its `removals` are a sequence of pairs,
+/// since formats like JSON only allow strings as map keys, and aren't yet checked against the
+/// `set` and `clock`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "R: serde::Serialize, X: serde::Serialize, S: serde::Serialize",
+    deserialize = "R: serde::Deserialize<'de>, X: serde::Deserialize<'de>, S: serde::Deserialize<'de>"
+))]
+struct UncheckedCollectableTwoPhaseSet<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X>> {
+    set: TwoPhaseSet<X, S>,
+    removals: Vec<(Dot<R>, X)>,
+    clock: VersionVector<R>,
+}
+
+#[cfg(feature = "serde")]
+impl<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X>> From<CollectableTwoPhaseSet<R, X, S>>
+    for UncheckedCollectableTwoPhaseSet<R, X, S>
+{
+    fn from(c: CollectableTwoPhaseSet<R, X, S>) -> Self {
+        UncheckedCollectableTwoPhaseSet {
+            set: c.set,
+            removals: c.removals.into_iter().collect(),
+            clock: c.clock,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<R: Clone + Ord, X: Clone + Eq, S: Set<Element = X>>
+    TryFrom<UncheckedCollectableTwoPhaseSet<R, X, S>> for CollectableTwoPhaseSet<R, X, S>
+{
+    type Error = &'static str;
+
+    fn try_from(unchecked: UncheckedCollectableTwoPhaseSet<R, X, S>) -> Result<Self, Self::Error> {
+        CollectableTwoPhaseSet::checked(unchecked.set, unchecked.removals, unchecked.clock)
+    }
+}
+
+/// The operation-based counterpart of a [`TwoPhaseSet`](struct.TwoPhaseSet.html)
+///
+/// # Panics
+///
+/// Like a `TwoPhaseSet`, preparing to remove an element that isn't currently in the set will
+/// panic:
+///
+/// ```should_panic
+/// // this will panic
+/// use std::collections::HashSet;
+/// use cvrdt_exposition::{OpTwoPhaseSet, Operation, TwoPhaseSetOp};
+/// let x = OpTwoPhaseSet::new((HashSet::new(), HashSet::new()));
+/// x.prepare(TwoPhaseSetOp::Remove("this will panic"));
+/// ```
+///
+/// # Notes
+///
+/// With causal delivery, a `Remove` is always delivered after the `Add` it observed at its source,
+/// so `effect` needn't check anything.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use cvrdt_exposition::{OpTwoPhaseSet, Operation, TwoPhaseSetOp};
+/// let mut x = OpTwoPhaseSet::new((HashSet::new(), HashSet::new()));
+/// let add = x.update(TwoPhaseSetOp::Add('a'));
+/// let remove = x.update(TwoPhaseSetOp::Remove('a'));
+/// let mut y = OpTwoPhaseSet::new((HashSet::new(), HashSet::new()));
+/// y.effect(&add);
+/// y.effect(&remove);
+/// assert_eq!(x.payload(), y.payload());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpTwoPhaseSet<X: Clone + Eq + Hash> {
+    /// The elements that have been added to this set
+    pub added: HashSet<X>,
+    /// The elements that have been removed from this set
+    pub removed: HashSet<X>,
+}
+
+/// An update to, and operation on, an [`OpTwoPhaseSet`](struct.OpTwoPhaseSet.html)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TwoPhaseSetOp<X> {
+    /// Add an element
+    Add(X),
+    /// Remove an element (which must currently be in the set at the source replica)
+    Remove(X),
+}
+
+impl<X: Clone + Eq + Hash> Operation for OpTwoPhaseSet<X> {
+    type Payload = (HashSet<X>, HashSet<X>);
+    type Update = TwoPhaseSetOp<X>;
+    type Op = TwoPhaseSetOp<X>;
+
+    fn new(payload: Self::Payload) -> Self {
+        OpTwoPhaseSet {
+            added: payload.0,
+            removed: payload.1,
+        }
+    }
+    fn payload(&self) -> Self::Payload {
+        (self.added.clone(), self.removed.clone())
+    }
+    fn prepare(&self, update: Self::Update) -> Self::Op {
+        if let TwoPhaseSetOp::Remove(x) = &update {
+            assert!(
+                self.added.contains(x) && !self.removed.contains(x),
+                "Only allowed for elements contained in 2PSet"
+            );
+        }
+        update
+    }
+    fn effect(&mut self, op: &Self::Op) {
+        match op {
+            TwoPhaseSetOp::Add(x) => self.added.insert(x.clone()),
+            TwoPhaseSetOp::Remove(x) => self.removed.insert(x.clone()),
+        };
+    }
+}
+
+impl<X: Clone + Eq + Hash> From<TwoPhaseSet<X>> for OpTwoPhaseSet<X> {
+    fn from(t: TwoPhaseSet<X>) -> Self {
+        OpTwoPhaseSet {
+            added: t.added,
+            removed: t.removed,
+        }
+    }
+}
+
+impl<X: Clone + Eq + Hash> From<OpTwoPhaseSet<X>> for TwoPhaseSet<X> {
+    fn from(t: OpTwoPhaseSet<X>) -> Self {
+        TwoPhaseSet::new((t.added, t.removed))
+    }
+}
+
+/// A strategy for an arbitrary `TwoPhaseSet` along with an element that may be deleted from it
+/// (i.e. one that is currently in the set)
+#[cfg(any(test, feature = "testing"))]
+pub fn cvrdt_and_subtrahend<X: proptest::arbitrary::Arbitrary + Clone + Eq + Hash>(
+) -> impl proptest::strategy::Strategy<Value = (TwoPhaseSet<X>, X)> {
+    use proptest::prelude::*;
+    (any::<TwoPhaseSet<X>>(), any::<X>()).prop_map(|(mut t, x)| {
+        t.added.insert(x.clone());
+        t.removed.remove(&x);
+        (t, x)
+    })
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl<X: std::fmt::Debug + Clone + Eq, S: Set<Element = X> + proptest::arbitrary::Arbitrary>
+    proptest::arbitrary::Arbitrary for TwoPhaseSet<X, S>
+{
+    type Parameters = <(S, S) as proptest::arbitrary::Arbitrary>::Parameters;
+    type Strategy =
+        proptest::strategy::Map<proptest::arbitrary::StrategyFor<(S, S)>, fn((S, S)) -> Self>;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+        any_with::<(S, S)>(args).prop_map(TwoPhaseSet::new)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::properties::{grow, shrink};
     use proptest::prelude::*;
 
-    static MAX_SIZE: usize = 100;
-
     fn cvrdt() -> impl Strategy<Value = TwoPhaseSet<String>> {
-        (
-            prop::collection::hash_set(any::<String>(), 0..MAX_SIZE),
-            prop::collection::hash_set(any::<String>(), 0..MAX_SIZE),
-        )
-            .prop_map(|(added, removed)| TwoPhaseSet { added, removed })
+        any::<TwoPhaseSet<String>>()
     }
 
     fn cvrdt_and_addend() -> impl Strategy<Value = (TwoPhaseSet<String>, String)> {
@@ -107,19 +621,327 @@ mod tests {
     }
 
     fn cvrdt_and_subtrahend() -> impl Strategy<Value = (TwoPhaseSet<i8>, i8)> {
-        (
-            prop::collection::hash_set(any::<i8>(), 0..MAX_SIZE),
-            prop::collection::hash_set(any::<i8>(), 0..MAX_SIZE),
-            any::<i8>(),
-        )
-            .prop_flat_map(|(mut added, mut removed, x)| {
-                added.insert(x);
-                removed.remove(&x);
-                let t = TwoPhaseSet { added, removed };
-                (Just(t), Just(x))
-            })
+        super::cvrdt_and_subtrahend()
     }
 
     grow!(cvrdt, cvrdt_and_addend);
     shrink!(cvrdt_and_subtrahend);
+
+    /// The same laws, for each of the other backends
+    macro_rules! backend {
+        ($name:ident, $($set:ident)::+) => {
+            mod $name {
+                use super::*;
+                use $($set)::+ as Backend;
+
+                fn cvrdt() -> impl Strategy<Value = TwoPhaseSet<i8, Backend<i8>>> {
+                    any::<TwoPhaseSet<i8, Backend<i8>>>()
+                }
+
+                fn cvrdt_and_addend(
+                ) -> impl Strategy<Value = (TwoPhaseSet<i8, Backend<i8>>, i8)> {
+                    (cvrdt(), any::<i8>())
+                }
+
+                fn cvrdt_and_subtrahend(
+                ) -> impl Strategy<Value = (TwoPhaseSet<i8, Backend<i8>>, i8)> {
+                    (cvrdt(), any::<i8>()).prop_map(|(mut t, x)| {
+                        t.added.insert(x);
+                        t.removed.remove(&x);
+                        (t, x)
+                    })
+                }
+
+                grow!(cvrdt, cvrdt_and_addend);
+                shrink!(cvrdt_and_subtrahend);
+            }
+        };
+    }
+
+    backend!(b_tree_set, std::collections::BTreeSet);
+    backend!(sorted_vec, crate::set::SortedVec);
+
+    mod collectable {
+        use super::*;
+
+        type Collectable = CollectableTwoPhaseSet<u8, i8>;
+
+        /// Replica `r` after some adds and removals of its own; replicas that issue their own
+        /// dots never disagree about what a dot means
+        fn cvrdt(r: u8) -> impl Strategy<Value = Collectable> {
+            prop::collection::vec((any::<bool>(), 0..16i8), 0..32).prop_map(move |ops| {
+                let mut c = Collectable::default();
+                for (del, x) in ops {
+                    if del && c.query(&x) {
+                        c.del((r, x));
+                    } else {
+                        c.add((r, x));
+                    }
+                }
+                c
+            })
+        }
+
+        fn cvrdt2() -> impl Strategy<Value = (Collectable, Collectable)> {
+            (cvrdt(0), cvrdt(1))
+        }
+
+        fn cvrdt3() -> impl Strategy<Value = (Collectable, Collectable, Collectable)> {
+            (cvrdt(0), cvrdt(1), cvrdt(2))
+        }
+
+        fn cvrdt_and_addend() -> impl Strategy<Value = (Collectable, (u8, i8))> {
+            (cvrdt(0), any::<i8>()).prop_map(|(c, x)| (c, (0, x)))
+        }
+
+        fn cvrdt_and_subtrahend() -> impl Strategy<Value = (Collectable, (u8, i8))> {
+            (cvrdt(0), any::<i8>()).prop_map(|(mut c, x)| {
+                let x = c.set.iter().next().copied().unwrap_or_else(|| {
+                    let mut fresh = (x..=i8::MAX).chain(i8::MIN..x);
+                    let x = fresh.find(|x| !c.set.added.contains(x)).unwrap();
+                    c.add((0, x));
+                    x
+                });
+                (c, (0, x))
+            })
+        }
+
+        grow!(cvrdt2, cvrdt3, cvrdt_and_addend);
+        shrink!(cvrdt_and_subtrahend);
+
+        const REPLICAS: usize = 3;
+
+        /// Every element in `c` that `query` sees
+        fn live<S: Set<Element = i8>>(c: &TwoPhaseSet<i8, S>) -> HashSet<i8> {
+            c.iter().copied().collect()
+        }
+
+        proptest! {
+            #[test]
+            fn collection_never_changes_queries(
+                ops in prop::collection::vec((0..5u8, 0..REPLICAS, 0..12i8, 0..REPLICAS), 0..64),
+            ) {
+                let mut oracle = Stability::new(0..REPLICAS as u8);
+                let mut collected = vec![Collectable::default(); REPLICAS];
+                let mut reference = vec![TwoPhaseSet::<i8>::new((HashSet::new(), HashSet::new())); REPLICAS];
+                let mut ever_removed = HashSet::new();
+                for (op, r, x, other) in ops {
+                    match op {
+                        // 2P-sets can't add back an element once it's removed
+                        0 if !ever_removed.contains(&x) => {
+                            collected[r].add((r as u8, x));
+                            reference[r].add(x);
+                        }
+                        1 if reference[r].query(&x) => {
+                            collected[r].del((r as u8, x));
+                            reference[r].del(x);
+                            ever_removed.insert(x);
+                        }
+                        2 => {
+                            collected[r] = collected[r].merge(&collected[other]);
+                            reference[r] = reference[r].merge(&reference[other]);
+                        }
+                        3 => oracle.ack(&(r as u8), &collected[r].clock),
+                        4 => {
+                            collected[r].collect(&oracle);
+                            for dot in collected[r].removals.keys() {
+                                prop_assert!(!oracle.is_stable(dot));
+                            }
+                        }
+                        _ => {}
+                    }
+                    for (c, t) in collected.iter().zip(&reference) {
+                        prop_assert_eq!(live(&c.set), live(t));
+                    }
+                }
+                // once everyone has seen everything, every tombstone can go
+                let everything = collected.iter().fold(Collectable::default(), |x, y| x.merge(y));
+                for (r, c) in collected.iter_mut().enumerate() {
+                    *c = c.merge(&everything);
+                    oracle.ack(&(r as u8), &c.clock);
+                }
+                for c in &mut collected {
+                    c.collect(&oracle);
+                    prop_assert!(c.set.removed.is_empty() && c.removals.is_empty());
+                    prop_assert_eq!(live(&c.set), live(&everything.set));
+                }
+            }
+        }
+
+        #[test]
+        fn stale_replicas_hold_back_collection() {
+            let mut oracle = Stability::new(['a', 'b', 'c']);
+            let mut a = CollectableTwoPhaseSet::<char, &str>::default();
+            a.add(('a', "x"));
+            // c sees the add, then goes quiet
+            let mut c = CollectableTwoPhaseSet::default().merge(&a);
+            oracle.ack(&'c', &c.clock);
+            a.del(('a', "x"));
+            let b = CollectableTwoPhaseSet::default().merge(&a);
+            oracle.ack(&'a', &a.clock);
+            oracle.ack(&'b', &b.clock);
+            assert_eq!(a.collect(&oracle), 0);
+            assert!(a.merge(&c).set.removed.contains("x"));
+            // c catches up
+            c = c.merge(&a);
+            oracle.ack(&'c', &c.clock);
+            assert_eq!(a.collect(&oracle), 1);
+            assert!(!a.merge(&c).query(&"x"));
+            // merging brings the tombstone back, and collecting purges it again
+            a = a.merge(&c);
+            assert_eq!(a.collect(&oracle), 1);
+            assert_eq!(a.set.payload(), (HashSet::new(), HashSet::new()));
+        }
+
+        #[test]
+        fn stale_newcomers_cannot_join() {
+            let mut oracle = Stability::new(['a', 'b']);
+            let mut a = CollectableTwoPhaseSet::<char, &str>::default();
+            a.add(('a', "x"));
+            let stale = a.clone();
+            a.del(('a', "x"));
+            let mut b = CollectableTwoPhaseSet::default().merge(&a);
+            oracle.ack(&'a', &a.clock);
+            oracle.ack(&'b', &b.clock);
+            assert_eq!(a.collect(&oracle), 1);
+            assert_eq!(b.collect(&oracle), 1);
+            // merging the stale state would bring "x" back, so it isn't allowed to join
+            assert!(a.merge(&stale).query(&"x"));
+            assert!(!oracle.join('c', &stale.clock));
+            // starting over from a known replica's state is fine
+            let fresh = b.clone();
+            assert!(oracle.join('c', &fresh.clock));
+            assert!(!a.merge(&fresh).query(&"x"));
+            b.add(('b', "y"));
+            assert_eq!(fresh.merge(&b).set.iter().collect::<Vec<_>>(), [&"y"]);
+        }
+
+        #[test]
+        fn late_pre_removal_states_are_refused() {
+            let mut oracle = Stability::new(['a', 'b']);
+            let mut a = CollectableTwoPhaseSet::<char, &str>::default();
+            a.add(('a', "x"));
+            let mut b = CollectableTwoPhaseSet::default().merge(&a);
+            // b sends its state, which is delayed; meanwhile a removes "x", b catches up, and
+            // both ack
+            let in_flight = b.clone();
+            a.del(('a', "x"));
+            b = b.merge(&a);
+            oracle.ack(&'a', &a.clock);
+            oracle.ack(&'b', &b.clock);
+            assert_eq!(a.collect(&oracle), 1);
+            // the delayed state finally arrives: merging it blindly brings "x" back
+            assert!(a.merge(&in_flight).query(&"x"));
+            assert_eq!(a.checked_merge(&in_flight, &oracle), None);
+            // up-to-date states still merge
+            b.add(('b', "y"));
+            let merged = a.checked_merge(&b, &oracle).unwrap();
+            assert!(!merged.query(&"x") && merged.query(&"y"));
+        }
+
+        #[test]
+        fn stale_collected_states_stay_purged() {
+            let mut oracle = Stability::new(['a', 'b']);
+            let mut a = CollectableTwoPhaseSet::<char, &str>::default();
+            a.add(('a', "x"));
+            a.add(('a', "y"));
+            a.del(('a', "x"));
+            let b = CollectableTwoPhaseSet::default().merge(&a);
+            oracle.ack(&'a', &a.clock);
+            oracle.ack(&'b', &b.clock);
+            // both a's own state from before collecting and b's uncollected one saw the removal
+            let before = a.clone();
+            assert_eq!(a.collect(&oracle), 1);
+            for stale in [&before, &b] {
+                let mut merged = a.merge(stale);
+                assert!(!merged.query(&"x"));
+                assert!(merged.query(&"y"));
+                assert_eq!(merged.collect(&oracle), 1);
+                assert_eq!(merged, a);
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        proptest! {
+            #[test]
+            fn serde_round_trip((x, y) in cvrdt2()) {
+                let xy = x.merge(&y);
+                let json = serde_json::to_string(&xy).unwrap();
+                prop_assert_eq!(serde_json::from_str::<Collectable>(&json).unwrap(), xy);
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn serde_rejects_inconsistent_removals() {
+            let set = r#"{"added":[1],"removed":[1]}"#;
+            for (removals, clock) in [
+                // not in the clock
+                (r#"[[{"replica":0,"counter":3},1]]"#, r#"{"0":2}"#),
+                // not removed from the set
+                (r#"[[{"replica":0,"counter":2},2]]"#, r#"{"0":2}"#),
+                // the same dot twice
+                (
+                    r#"[[{"replica":0,"counter":2},1],[{"replica":0,"counter":2},1]]"#,
+                    r#"{"0":2}"#,
+                ),
+            ] {
+                let json = format!(
+                    r#"{{"set":{set},"removals":{removals},"clock":{{"counts":{clock}}}}}"#
+                );
+                assert!(
+                    serde_json::from_str::<Collectable>(&json).is_err(),
+                    "{json}"
+                );
+            }
+            let json = format!(
+                r#"{{"set":{set},"removals":[[{{"replica":0,"counter":2}},1]],"clock":{{"counts":{{"0":2}}}}}}"#
+            );
+            assert_eq!(
+                serde_json::from_str::<Collectable>(&json)
+                    .unwrap()
+                    .removals
+                    .len(),
+                1
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn iteration_agrees_with_query(x in any::<TwoPhaseSet<i8>>(), ys in any::<Vec<i8>>()) {
+            let live: HashSet<i8> = x.iter().copied().collect();
+            prop_assert_eq!(live.len(), x.len());
+            prop_assert_eq!(live.is_empty(), x.is_empty());
+            for y in x.added.iter().chain(&x.removed).chain(&ys) {
+                prop_assert_eq!(live.contains(y), x.query(y));
+            }
+            for y in &x {
+                prop_assert!(x.query(y));
+            }
+            prop_assert_eq!(x.into_iter().collect::<HashSet<_>>(), live);
+        }
+        #[test]
+        fn bulk_updates_agree_with_add(x in any::<TwoPhaseSet<i8>>(), ys in any::<Vec<i8>>()) {
+            let mut extended = x.clone();
+            extended.extend(ys.iter().copied());
+            let mut added = x;
+            for &y in &ys {
+                added.add(y);
+            }
+            prop_assert_eq!(extended, added);
+            let collected: TwoPhaseSet<i8> = ys.iter().copied().collect();
+            prop_assert_eq!(collected, TwoPhaseSet::new((ys.into_iter().collect(), HashSet::new())));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    proptest! {
+        #[test]
+        fn serde_round_trip(x in any::<TwoPhaseSet<String>>()) {
+            let json = serde_json::to_string(&x).unwrap();
+            let y: TwoPhaseSet<String> = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(x.payload(), y.payload());
+        }
+    }
 }
@@ -0,0 +1,1050 @@
+use crate::accumulator::{Amount, Decimal, Float};
+use crate::causality::{Dot, DottedVersionVector, VersionVector};
+use crate::count::Count;
+use crate::set::Set;
+use crate::{
+    Accumulator, CollectableTwoPhaseSet, GCounter, GSet, Grow, Histogram, LWWRegister, MeanCounter,
+    OneWayBoolean, PNCounter, TwoPhaseSet,
+};
+use std::fmt;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The first two bytes of every encoded `CvRDT`
+pub const MAGIC: [u8; 2] = *b"CV";
+
+/// The current version of the wire format
+pub const VERSION: u8 = 1;
+
+/// Everything that can go wrong when [`decode`](fn.decode.html)-ing bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before a complete value could be read
+    Truncated,
+    /// The input doesn't begin with [`MAGIC`](constant.MAGIC.html)
+    BadMagic,
+    /// The input was encoded with a different version of the wire format
+    VersionMismatch {
+        /// The version we know how to decode
+        expected: u8,
+        /// The version found in the header
+        found: u8,
+    },
+    /// The input encodes a different `CvRDT` (or the same one over different elements)
+    TypeMismatch {
+        /// The `(kind, element)` tags we were asked to decode
+        expected: (u8, u8),
+        /// The `(kind, element)` tags found in the header
+        found: (u8, u8),
+    },
+    /// The body doesn't match the checksum in the header
+    ChecksumMismatch {
+        /// The checksum found in the header
+        expected: u32,
+        /// The checksum of the body we actually read
+        found: u32,
+    },
+    /// The input has bytes left over after a complete value
+    TrailingBytes,
+    /// The body passed its checksum but doesn't describe a valid value
+    Invalid(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "input truncated"),
+            DecodeError::BadMagic => write!(f, "missing magic bytes"),
+            DecodeError::VersionMismatch { expected, found } => {
+                write!(f, "expected format version {expected}, found {found}")
+            }
+            DecodeError::TypeMismatch { expected, found } => {
+                write!(f, "expected type tags {expected:?}, found {found:?}")
+            }
+            DecodeError::ChecksumMismatch { expected, found } => {
+                write!(f, "expected checksum {expected:#010x}, found {found:#010x}")
+            }
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after value"),
+            DecodeError::Invalid(msg) => write!(f, "invalid value: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// A cursor over bytes being decoded
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Start reading from the beginning of `bytes`
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    /// How many bytes are left to read
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Read exactly `n` bytes
+    ///
+    /// # Errors
+    ///
+    /// [`DecodeError::Truncated`](enum.DecodeError.html) if fewer than `n` bytes remain
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if n > self.bytes.len() {
+            return Err(DecodeError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Read a single byte
+    ///
+    /// # Errors
+    ///
+    /// [`DecodeError::Truncated`](enum.DecodeError.html) if no bytes remain
+    pub fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read an unsigned [LEB128](https://en.wikipedia.org/wiki/LEB128) variable-length integer
+    ///
+    /// # Errors
+    ///
+    /// [`DecodeError::Truncated`](enum.DecodeError.html) if the input ends mid-integer, or
+    /// [`DecodeError::Invalid`](enum.DecodeError.html) if the integer doesn't fit in a `u64`
+    pub fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            let bits = u64::from(b & 0x7f);
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError::Invalid("varint overflow"));
+            }
+            value |= bits << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::Invalid("varint overflow"))
+    }
+
+    /// Read a varint-encoded length or index
+    ///
+    /// # Errors
+    ///
+    /// As for [`varint`](#method.varint), or [`DecodeError::Invalid`](enum.DecodeError.html)
+    /// if the value doesn't fit in a `usize`
+    pub fn usize(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.varint()?).map_err(|_| DecodeError::Invalid("length too large"))
+    }
+}
+
+/// Append `value` to `buf` as an unsigned [LEB128](https://en.wikipedia.org/wiki/LEB128)
+/// variable-length integer
+pub fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Values that can be stored inside an encoded `CvRDT`, e.g. the elements of a `GSet`
+pub trait Element: Sized {
+    /// Identifies this element type in the header of an encoded `CvRDT`
+    const TAG: u8;
+
+    /// Append this value to `buf`
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Read a value from `reader`
+    ///
+    /// # Errors
+    ///
+    /// If the bytes in `reader` don't describe a valid value
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError>;
+}
+
+impl Element for () {
+    const TAG: u8 = 0;
+    fn encode(&self, _buf: &mut Vec<u8>) {}
+    fn decode(_reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl Element for bool {
+    const TAG: u8 = 1;
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(u8::from(*self));
+    }
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        match reader.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Invalid("bool out of range")),
+        }
+    }
+}
+
+macro_rules! unsigned {
+    ($($t:ty => $tag:expr),*) => {
+        $(
+            impl Element for $t {
+                const TAG: u8 = $tag;
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    write_varint(buf, u64::from(*self));
+                }
+                fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+                    <$t>::try_from(reader.varint()?)
+                        .map_err(|_| DecodeError::Invalid("integer out of range"))
+                }
+            }
+        )*
+    };
+}
+
+unsigned!(u8 => 2, u16 => 3, u32 => 4, u64 => 5);
+
+/// The same varint as the narrower integers, just with up to 19 bytes instead of 10
+impl Element for u128 {
+    const TAG: u8 = 13;
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let mut value = *self;
+        while value >= 0x80 {
+            buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        buf.push(value as u8);
+    }
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let mut value = 0u128;
+        for shift in (0..128).step_by(7) {
+            let b = reader.byte()?;
+            let bits = u128::from(b & 0x7f);
+            if shift == 126 && bits > 3 {
+                return Err(DecodeError::Invalid("varint overflow"));
+            }
+            value |= bits << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::Invalid("varint overflow"))
+    }
+}
+
+macro_rules! signed {
+    ($($t:ty => $tag:expr),*) => {
+        $(
+            impl Element for $t {
+                const TAG: u8 = $tag;
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    let x = i64::from(*self);
+                    write_varint(buf, ((x << 1) ^ (x >> 63)) as u64); // zigzag
+                }
+                fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+                    let z = reader.varint()?;
+                    let x = ((z >> 1) as i64) ^ -((z & 1) as i64);
+                    <$t>::try_from(x).map_err(|_| DecodeError::Invalid("integer out of range"))
+                }
+            }
+        )*
+    };
+}
+
+signed!(i8 => 6, i16 => 7, i32 => 8, i64 => 9);
+
+impl Element for char {
+    const TAG: u8 = 10;
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, u64::from(u32::from(*self)));
+    }
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        u32::try_from(reader.varint()?)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(DecodeError::Invalid("invalid char"))
+    }
+}
+
+impl Element for String {
+    const TAG: u8 = 11;
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.len() as u64);
+        buf.extend_from_slice(self.as_bytes());
+    }
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let n = reader.usize()?;
+        let bytes = reader.take(n)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::Invalid("invalid UTF-8"))
+    }
+}
+
+impl Element for Float {
+    const TAG: u8 = 12;
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.get().to_le_bytes());
+    }
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let bytes = reader.take(8)?.try_into().expect("took 8 bytes");
+        Float::try_from(f64::from_le_bytes(bytes)).map_err(DecodeError::Invalid)
+    }
+}
+
+/// `Decimal`s with different numbers of places get different tags, from 32 up
+impl<const PLACES: u32> Element for Decimal<PLACES> {
+    const TAG: u8 = {
+        assert!(PLACES <= 19, "Too many decimal places");
+        32 + PLACES as u8
+    };
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.units());
+    }
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(Decimal::from_units(reader.varint()?))
+    }
+}
+
+/// `CvRDTs` with a binary encoding
+pub trait Wire: Sized {
+    /// Identifies this kind of `CvRDT` in the header
+    const KIND: u8;
+    /// Identifies the [`Element`](trait.Element.html) type (if any) this `CvRDT` holds
+    const ELEMENT: u8;
+
+    /// Append the body (everything but the header) of this `CvRDT` to `buf`
+    fn encode_body(&self, buf: &mut Vec<u8>);
+
+    /// Read a body written by [`encode_body`](#tymethod.encode_body)
+    ///
+    /// # Errors
+    ///
+    /// If the bytes in `reader` don't describe a valid `CvRDT`
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError>;
+}
+
+fn encode_vec<T: Element>(buf: &mut Vec<u8>, xs: &[T]) {
+    write_varint(buf, xs.len() as u64);
+    for x in xs {
+        x.encode(buf);
+    }
+}
+
+fn decode_vec<T: Element>(reader: &mut Reader<'_>) -> Result<Vec<T>, DecodeError> {
+    let n = reader.usize()?;
+    let mut xs = Vec::with_capacity(n.min(reader.remaining()));
+    for _ in 0..n {
+        xs.push(T::decode(reader)?);
+    }
+    Ok(xs)
+}
+
+fn encode_set<S: Set>(buf: &mut Vec<u8>, xs: &S)
+where
+    S::Element: Element,
+{
+    write_varint(buf, xs.len() as u64);
+    for x in xs.iter() {
+        x.encode(buf);
+    }
+}
+
+fn decode_set<S: Set>(reader: &mut Reader<'_>) -> Result<S, DecodeError>
+where
+    S::Element: Element,
+{
+    let n = reader.usize()?;
+    let mut xs = S::default();
+    for _ in 0..n {
+        xs.insert(S::Element::decode(reader)?);
+    }
+    Ok(xs)
+}
+
+// `usize` counts aren't `Element`s (their width varies by platform), so counters over them have
+// no encoding
+impl<T: Count + Element> Wire for GCounter<T> {
+    const KIND: u8 = 1;
+    const ELEMENT: u8 = T::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.id as u64);
+        encode_vec(buf, &self.counts);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let id = reader.usize()?;
+        let counts = decode_vec(reader)?;
+        if id < counts.len() {
+            Ok(GCounter { id, counts })
+        } else {
+            Err(DecodeError::Invalid("ID too large"))
+        }
+    }
+}
+
+impl<T: Count + Element> Wire for PNCounter<T> {
+    const KIND: u8 = 2;
+    const ELEMENT: u8 = T::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.id as u64);
+        encode_vec(buf, &self.positive);
+        encode_vec(buf, &self.negative);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let id = reader.usize()?;
+        let positive = decode_vec(reader)?;
+        let negative = decode_vec(reader)?;
+        if positive.len() != negative.len() {
+            Err(DecodeError::Invalid(
+                "Incompatible positive & negative lengths",
+            ))
+        } else if id >= positive.len() {
+            Err(DecodeError::Invalid("ID too large"))
+        } else {
+            Ok(PNCounter {
+                id,
+                positive,
+                negative,
+            })
+        }
+    }
+}
+
+impl<X: Element + Clone + Eq, S: Set<Element = X>> Wire for GSet<X, S> {
+    const KIND: u8 = 3;
+    const ELEMENT: u8 = X::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        encode_set(buf, &self.values);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(GSet::new(decode_set(reader)?))
+    }
+}
+
+impl<X: Element + Clone + Eq, S: Set<Element = X>> Wire for TwoPhaseSet<X, S> {
+    const KIND: u8 = 4;
+    const ELEMENT: u8 = X::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        encode_set(buf, &self.added);
+        encode_set(buf, &self.removed);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let added = decode_set(reader)?;
+        Ok(TwoPhaseSet::new((added, decode_set(reader)?)))
+    }
+}
+
+fn decode_dots<R: Element + Clone + Ord>(
+    reader: &mut Reader<'_>,
+) -> Result<Vec<Dot<R>>, DecodeError> {
+    let n = reader.usize()?;
+    let mut dots = Vec::with_capacity(n.min(reader.remaining()));
+    for _ in 0..n {
+        let replica = R::decode(reader)?;
+        let counter = reader.varint()?;
+        dots.push(Dot { replica, counter });
+    }
+    Ok(dots)
+}
+
+impl<R: Element + Clone + Ord> Wire for VersionVector<R> {
+    const KIND: u8 = 7;
+    const ELEMENT: u8 = R::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.counts.len() as u64);
+        for (r, &n) in &self.counts {
+            r.encode(buf);
+            write_varint(buf, n);
+        }
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let counts = decode_dots(reader)?
+            .into_iter()
+            .map(|d| (d.replica, d.counter))
+            .collect();
+        Ok(VersionVector::new(counts))
+    }
+}
+
+impl<R: Element + Clone + Ord> Wire for DottedVersionVector<R> {
+    const KIND: u8 = 8;
+    const ELEMENT: u8 = R::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        self.clock.encode_body(buf);
+        write_varint(buf, self.cloud.len() as u64);
+        for d in &self.cloud {
+            d.replica.encode(buf);
+            write_varint(buf, d.counter);
+        }
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let clock = VersionVector::decode_body(reader)?;
+        let cloud = decode_dots(reader)?.into_iter().collect();
+        Ok(DottedVersionVector::new((clock.counts, cloud)))
+    }
+}
+
+impl<R: Element + Clone + Ord, X: Element + Clone + Eq, S: Set<Element = X>> Wire
+    for CollectableTwoPhaseSet<R, X, S>
+{
+    const KIND: u8 = 9;
+    // the replica type's tag in the high four bits, and the element type's in the low four
+    const ELEMENT: u8 = {
+        assert!(
+            R::TAG < 16 && X::TAG < 16,
+            "Element tags must fit in four bits"
+        );
+        (R::TAG << 4) | X::TAG
+    };
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        self.set.encode_body(buf);
+        write_varint(buf, self.removals.len() as u64);
+        for (dot, x) in &self.removals {
+            dot.replica.encode(buf);
+            write_varint(buf, dot.counter);
+            x.encode(buf);
+        }
+        self.clock.encode_body(buf);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let set = TwoPhaseSet::decode_body(reader)?;
+        let n = reader.usize()?;
+        let mut removals = Vec::with_capacity(n.min(reader.remaining()));
+        for _ in 0..n {
+            let replica = R::decode(reader)?;
+            let counter = reader.varint()?;
+            removals.push((Dot { replica, counter }, X::decode(reader)?));
+        }
+        let clock = VersionVector::decode_body(reader)?;
+        CollectableTwoPhaseSet::checked(set, removals, clock).map_err(DecodeError::Invalid)
+    }
+}
+
+impl<X: Element + Clone + Eq> Wire for LWWRegister<X> {
+    const KIND: u8 = 5;
+    const ELEMENT: u8 = X::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        self.value.encode(buf);
+        // seconds relative to the epoch (possibly negative), then nanoseconds within that second
+        let (secs, nanos) = match self.timestamp.duration_since(UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+            Err(e) => {
+                let d = e.duration();
+                match d.subsec_nanos() {
+                    0 => (-(d.as_secs() as i64), 0),
+                    n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+                }
+            }
+        };
+        secs.encode(buf);
+        nanos.encode(buf);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let value = X::decode(reader)?;
+        let secs = i64::decode(reader)?;
+        let nanos = u32::decode(reader)?;
+        if nanos >= 1_000_000_000 {
+            return Err(DecodeError::Invalid("nanoseconds out of range"));
+        }
+        let timestamp = if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(secs.unsigned_abs(), nanos))
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+                .and_then(|t| t.checked_add(Duration::from_nanos(u64::from(nanos))))
+        }
+        .ok_or(DecodeError::Invalid("timestamp out of range"))?;
+        Ok(LWWRegister { value, timestamp })
+    }
+}
+
+impl Wire for OneWayBoolean {
+    const KIND: u8 = 6;
+    const ELEMENT: u8 = <()>::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        self.flag.encode(buf);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(OneWayBoolean {
+            flag: bool::decode(reader)?,
+        })
+    }
+}
+
+impl<A: Amount + Element> Wire for Accumulator<A> {
+    const KIND: u8 = 10;
+    const ELEMENT: u8 = A::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.id as u64);
+        encode_vec(buf, &self.totals);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let id = reader.usize()?;
+        let totals = decode_vec(reader)?;
+        if id < totals.len() {
+            Ok(Accumulator { id, totals })
+        } else {
+            Err(DecodeError::Invalid("ID too large"))
+        }
+    }
+}
+
+impl<A: Amount + Element> Wire for MeanCounter<A> {
+    const KIND: u8 = 11;
+    const ELEMENT: u8 = A::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.sum.id as u64);
+        encode_vec(buf, &self.sum.totals);
+        encode_vec(buf, &self.count.counts);
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let id = reader.usize()?;
+        let mc = MeanCounter {
+            sum: Accumulator {
+                id,
+                totals: decode_vec(reader)?,
+            },
+            count: GCounter {
+                id,
+                counts: decode_vec(reader)?,
+            },
+        };
+        mc.check().map_err(DecodeError::Invalid)?;
+        Ok(mc)
+    }
+}
+
+impl<X: Element + Clone + Ord> Wire for Histogram<X> {
+    const KIND: u8 = 12;
+    const ELEMENT: u8 = X::TAG;
+
+    fn encode_body(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.buckets.first().map_or(0, |b| b.id) as u64);
+        encode_vec(buf, &self.bounds);
+        write_varint(buf, self.buckets.len() as u64);
+        for bucket in &self.buckets {
+            encode_vec(buf, &bucket.counts);
+        }
+    }
+    fn decode_body(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let id = reader.usize()?;
+        let bounds = decode_vec(reader)?;
+        let n = reader.usize()?;
+        let mut buckets = Vec::with_capacity(n.min(reader.remaining()));
+        for _ in 0..n {
+            buckets.push(GCounter {
+                id,
+                counts: decode_vec(reader)?,
+            });
+        }
+        let h = Histogram { bounds, buckets };
+        h.check().map_err(DecodeError::Invalid)?;
+        Ok(h)
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// The [CRC-32](https://en.wikipedia.org/wiki/Cyclic_redundancy_check) (IEEE) checksum of `bytes`
+#[must_use]
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |c, &b| {
+        CRC_TABLE[((c ^ u32::from(b)) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+/// Encode a `CvRDT` as bytes
+///
+/// # Parameters
+///
+/// - `cvrdt`: a borrowed reference to the `CvRDT` to encode
+///
+/// # Returns
+///
+/// The bytes of a header (the [`MAGIC`](constant.MAGIC.html) bytes, the format
+/// [`VERSION`](constant.VERSION.html), the kind and element tags, the length of the body and its
+/// CRC-32 checksum) followed by the body
+#[must_use]
+pub fn encode<T: Wire>(cvrdt: &T) -> Vec<u8> {
+    let mut body = Vec::new();
+    cvrdt.encode_body(&mut body);
+    let mut buf = Vec::with_capacity(body.len() + 16);
+    buf.extend_from_slice(&MAGIC);
+    buf.extend_from_slice(&[VERSION, T::KIND, T::ELEMENT]);
+    write_varint(&mut buf, body.len() as u64);
+    buf.extend_from_slice(&crc32(&body).to_le_bytes());
+    buf.extend_from_slice(&body);
+    buf
+}
+
+/// Read the kind and element tags from the header of bytes written by [`encode`](fn.encode.html),
+/// without decoding the rest; useful for deciding which type to [`decode`](fn.decode.html)
+///
+/// # Errors
+///
+/// A [`DecodeError`](enum.DecodeError.html) if `bytes` are too short to hold the tags, or were
+/// written by a different format version
+pub fn tags(bytes: &[u8]) -> Result<(u8, u8), DecodeError> {
+    let mut reader = Reader::new(bytes);
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(DecodeError::VersionMismatch {
+            expected: VERSION,
+            found: version,
+        });
+    }
+    Ok((reader.byte()?, reader.byte()?))
+}
+
+/// Decode bytes written by [`encode`](fn.encode.html)
+///
+/// # Parameters
+///
+/// - `bytes`: the encoded header and body
+///
+/// # Returns
+///
+/// The decoded `CvRDT`
+///
+/// # Errors
+///
+/// A [`DecodeError`](enum.DecodeError.html) if `bytes` are truncated, were written by a
+/// different format version or for a different type, have been corrupted, or have anything left
+/// over after the body
+pub fn decode<T: Wire>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let found = tags(bytes)?;
+    if found != (T::KIND, T::ELEMENT) {
+        return Err(DecodeError::TypeMismatch {
+            expected: (T::KIND, T::ELEMENT),
+            found,
+        });
+    }
+    let mut reader = Reader::new(&bytes[MAGIC.len() + 3..]);
+    let n = reader.usize()?;
+    let checksum = u32::from_le_bytes(reader.take(4)?.try_into().expect("took exactly four bytes"));
+    let body = reader.take(n)?;
+    if reader.remaining() > 0 {
+        return Err(DecodeError::TrailingBytes);
+    }
+    let found = crc32(body);
+    if found != checksum {
+        return Err(DecodeError::ChecksumMismatch {
+            expected: checksum,
+            found,
+        });
+    }
+    let mut body = Reader::new(body);
+    let cvrdt = T::decode_body(&mut body)?;
+    if body.remaining() > 0 {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(cvrdt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{Grow, Shrink};
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    /// A `CollectableTwoPhaseSet` after some updates from a few replicas
+    fn collectable() -> impl Strategy<Value = CollectableTwoPhaseSet<u8, i8>> {
+        prop::collection::vec((any::<bool>(), 0..4u8, any::<i8>()), 0..32).prop_map(|ops| {
+            let mut c = CollectableTwoPhaseSet::<u8, i8>::default();
+            for (del, r, x) in ops {
+                if del && c.query(&x) {
+                    c.del((r, x));
+                } else if !c.set.removed.contains(&x) {
+                    c.add((r, x));
+                }
+            }
+            c
+        })
+    }
+
+    /// A `Histogram` over a few replicas, with arbitrary bounds
+    fn histogram() -> impl Strategy<Value = Histogram<i16>> {
+        (prop::collection::btree_set(any::<i16>(), 0..8), 1..4usize)
+            .prop_flat_map(|(bounds, n)| crate::histogram::sized(bounds.into_iter().collect(), n))
+    }
+
+    /// Round trips, plus every truncation and every single-byte corruption must be rejected
+    macro_rules! fuzz {
+        ($name:ident, $t:ty) => {
+            fuzz!($name, $t, any::<$t>());
+        };
+        ($name:ident, $t:ty, $strategy:expr) => {
+            mod $name {
+                use super::*;
+
+                proptest! {
+                    #[test]
+                    fn round_trip(x in $strategy) {
+                        let y: $t = decode(&encode(&x)).unwrap();
+                        prop_assert_eq!(x.payload(), y.payload());
+                    }
+                    #[test]
+                    fn truncation_rejected(x in $strategy, cut in any::<prop::sample::Index>()) {
+                        let bytes = encode(&x);
+                        let n = cut.index(bytes.len());
+                        prop_assert_eq!(decode::<$t>(&bytes[..n]).unwrap_err(), DecodeError::Truncated);
+                    }
+                    #[test]
+                    fn corruption_rejected(
+                        x in $strategy,
+                        at in any::<prop::sample::Index>(),
+                        flip in 1..=u8::MAX,
+                    ) {
+                        let mut bytes = encode(&x);
+                        let i = at.index(bytes.len());
+                        bytes[i] ^= flip;
+                        prop_assert!(decode::<$t>(&bytes).is_err());
+                    }
+                    #[test]
+                    fn garbage_does_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+                        let _ = decode::<$t>(&bytes);
+                    }
+                }
+            }
+        };
+    }
+
+    fuzz!(g_counter, GCounter);
+    fuzz!(g_counter_u8, GCounter<u8>);
+    fuzz!(g_counter_u128, GCounter<u128>);
+    fuzz!(pn_counter, PNCounter);
+    fuzz!(pn_counter_u32, PNCounter<u32>);
+    fuzz!(g_set, GSet<String>);
+    fuzz!(two_phase_set, TwoPhaseSet<i32>);
+    fuzz!(g_set_b_tree_set, GSet<String, std::collections::BTreeSet<String>>);
+    fuzz!(two_phase_set_sorted_vec, TwoPhaseSet<i32, crate::set::SortedVec<i32>>);
+    fuzz!(
+        collectable_two_phase_set,
+        CollectableTwoPhaseSet<u8, i8>,
+        collectable()
+    );
+    fuzz!(lww_register, LWWRegister<char>);
+    fuzz!(one_way_boolean, OneWayBoolean);
+    fuzz!(version_vector, VersionVector<u8>);
+    fuzz!(dotted_version_vector, DottedVersionVector<u8>);
+    fuzz!(accumulator_decimal, Accumulator<Decimal<2>>);
+    fuzz!(accumulator_float, Accumulator<Float>);
+    fuzz!(mean_counter, MeanCounter<Decimal<2>>);
+    fuzz!(histogram, Histogram<i16>, histogram());
+
+    proptest! {
+        #[test]
+        fn u128_round_trip(x in any::<u128>()) {
+            let mut buf = Vec::new();
+            x.encode(&mut buf);
+            let mut reader = Reader::new(&buf);
+            prop_assert_eq!(u128::decode(&mut reader), Ok(x));
+            prop_assert_eq!(reader.remaining(), 0);
+        }
+        #[test]
+        fn varint_round_trip(x in any::<u64>()) {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, x);
+            prop_assert_eq!(Reader::new(&buf).varint(), Ok(x));
+        }
+        #[test]
+        fn ordered_backends_encode_deterministically(xs in any::<Vec<u16>>()) {
+            use crate::set::SortedVec;
+            use std::collections::BTreeSet;
+            let ys: Vec<_> = xs.iter().rev().copied().collect();
+            let bytes = encode(&GSet::<u16, BTreeSet<_>>::new(xs.iter().copied().collect()));
+            prop_assert_eq!(&encode(&GSet::<u16, BTreeSet<_>>::new(ys.iter().copied().collect())), &bytes);
+            prop_assert_eq!(&encode(&GSet::<u16, SortedVec<_>>::new(ys.into())), &bytes);
+            // backends share a wire format
+            let hashed: GSet<u16> = decode(&bytes).unwrap();
+            prop_assert_eq!(hashed.values, xs.into_iter().collect());
+        }
+        #[test]
+        fn zigzag_round_trip(x in any::<i64>()) {
+            let mut buf = Vec::new();
+            x.encode(&mut buf);
+            prop_assert_eq!(i64::decode(&mut Reader::new(&buf)), Ok(x));
+        }
+    }
+
+    #[test]
+    fn u128_overflow_rejected() {
+        let mut bytes = vec![0xff; 18];
+        bytes.push(0x04);
+        assert_eq!(
+            u128::decode(&mut Reader::new(&bytes)),
+            Err(DecodeError::Invalid("varint overflow"))
+        );
+        bytes[18] = 0x03;
+        assert_eq!(u128::decode(&mut Reader::new(&bytes)), Ok(u128::MAX));
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let mut bytes = encode(&OneWayBoolean::new(true));
+        bytes[2] = VERSION + 1;
+        assert_eq!(
+            decode::<OneWayBoolean>(&bytes).unwrap_err(),
+            DecodeError::VersionMismatch {
+                expected: VERSION,
+                found: VERSION + 1
+            }
+        );
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let bytes = encode(&GSet::new(HashSet::from([1u8, 2, 3])));
+        assert!(matches!(
+            decode::<GSet<u16>>(&bytes),
+            Err(DecodeError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            decode::<TwoPhaseSet<u8>>(&bytes),
+            Err(DecodeError::TypeMismatch { .. })
+        ));
+        let bytes = encode(&GCounter::new((0, vec![1, 2])));
+        assert!(matches!(
+            decode::<GCounter<u8>>(&bytes),
+            Err(DecodeError::TypeMismatch { .. })
+        ));
+        let bytes = encode(&Accumulator::new((0, vec![Decimal::<2>::from_units(150)])));
+        assert!(matches!(
+            decode::<Accumulator<Decimal<3>>>(&bytes),
+            Err(DecodeError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_floats_rejected() {
+        for (x, msg) in [(f64::NAN, "NaN amount"), (-1.0, "Negative amount")] {
+            assert_eq!(
+                Float::decode(&mut Reader::new(&x.to_le_bytes())),
+                Err(DecodeError::Invalid(msg))
+            );
+        }
+    }
+
+    #[test]
+    fn tags_identify_the_type() {
+        let bytes = encode(&TwoPhaseSet::new((HashSet::from(['a']), HashSet::new())));
+        assert_eq!(
+            tags(&bytes),
+            Ok((<TwoPhaseSet<char>>::KIND, <TwoPhaseSet<char>>::ELEMENT))
+        );
+        assert_eq!(tags(&bytes[..4]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn timestamps_before_the_epoch() {
+        let timestamp = UNIX_EPOCH - Duration::new(1729, 1);
+        let x = LWWRegister::new(((), timestamp));
+        assert_eq!(
+            decode::<LWWRegister<()>>(&encode(&x)).unwrap().timestamp,
+            timestamp
+        );
+    }
+
+    #[test]
+    fn uncovered_removals_rejected() {
+        let mut c = CollectableTwoPhaseSet::<u8, i8>::default();
+        c.add((0, 1));
+        c.del((0, 1));
+        c.removals.insert(
+            Dot {
+                replica: 1,
+                counter: 1,
+            },
+            1,
+        );
+        assert_eq!(
+            decode::<CollectableTwoPhaseSet<u8, i8>>(&encode(&c)).unwrap_err(),
+            DecodeError::Invalid("Removal not in clock")
+        );
+    }
+
+    #[test]
+    fn inconsistent_counters_rejected() {
+        let pn: PNCounter = PNCounter {
+            id: 0,
+            positive: vec![1, 2],
+            negative: vec![3],
+        };
+        assert_eq!(
+            decode::<PNCounter>(&encode(&pn)).unwrap_err(),
+            DecodeError::Invalid("Incompatible positive & negative lengths")
+        );
+        let g: GCounter = GCounter {
+            id: 2,
+            counts: vec![0, 0],
+        };
+        assert_eq!(
+            decode::<GCounter>(&encode(&g)).unwrap_err(),
+            DecodeError::Invalid("ID too large")
+        );
+        let mc = MeanCounter {
+            sum: Accumulator {
+                id: 0,
+                totals: vec![Float::ZERO; 2],
+            },
+            count: GCounter::new((0, vec![0; 3])),
+        };
+        assert_eq!(
+            decode::<MeanCounter<Float>>(&encode(&mc)).unwrap_err(),
+            DecodeError::Invalid("Incompatible sum & count lengths")
+        );
+        let h = Histogram {
+            bounds: vec![2u8, 1],
+            buckets: vec![GCounter::new((0, vec![0])); 3],
+        };
+        assert_eq!(
+            decode::<Histogram<u8>>(&encode(&h)).unwrap_err(),
+            DecodeError::Invalid("Bounds not strictly increasing")
+        );
+    }
+}
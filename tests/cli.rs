@@ -0,0 +1,212 @@
+//! The `cvrdt` command-line tool, run against state files in a temporary directory
+
+use cvrdt_exposition::accumulator::{Decimal, Float};
+use cvrdt_exposition::causality::{DottedVersionVector, VersionVector};
+use cvrdt_exposition::wire::{self, Wire};
+use cvrdt_exposition::{
+    Accumulator, CollectableTwoPhaseSet, GCounter, GSet, Grow, Histogram, MeanCounter,
+    OneWayBoolean, PNCounter, Shrink, TwoPhaseSet,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// A fresh directory for one test, removed afterwards
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("cvrdt-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// Encode `cvrdt` into the file `name`, returning its path
+    fn save<T: Wire>(&self, name: &str, cvrdt: &T) -> String {
+        let path = self.0.join(name);
+        fs::write(&path, wire::encode(cvrdt)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn cvrdt(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cvrdt"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn show_prints_type_payload_and_value() {
+    let dir = TempDir::new("show");
+    let counter = dir.save("counter", &GCounter::new((1, vec![2, 3])));
+    let output = cvrdt(&["show", &counter]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "type: GCounter<u64>\npayload: (1, [2, 3])\nvalue: 5\n"
+    );
+    let narrow = dir.save("narrow", &GCounter::<u8>::new((0, vec![200, 100])));
+    let output = cvrdt(&["show", &narrow]);
+    assert_eq!(
+        stdout(&output),
+        "type: GCounter<u8>\npayload: (0, [200, 100])\nvalue: 255\n"
+    );
+    let wide = dir.save("wide", &GCounter::<u128>::new((0, vec![u128::MAX - 1, 1])));
+    assert!(stdout(&cvrdt(&["show", &wide])).ends_with(&format!("value: {}\n", u128::MAX)));
+    let set = dir.save("set", &GSet::new(HashSet::from([7u16])));
+    let output = cvrdt(&["show", &set, "7"]);
+    assert_eq!(
+        stdout(&output),
+        "type: GSet<u16>\npayload: {7}\nvalue: true\n"
+    );
+    // sets need an element to query
+    let output = cvrdt(&["show", &set]);
+    assert_eq!(stdout(&output), "type: GSet<u16>\npayload: {7}\n");
+}
+
+#[test]
+fn show_queries_causality_types() {
+    let dir = TempDir::new("causality");
+    let mut vv = VersionVector::default();
+    vv.increment('a');
+    vv.increment('a');
+    let vv = dir.save("vv", &vv);
+    assert!(stdout(&cvrdt(&["show", &vv, "a"])).ends_with("value: 2\n"));
+    let mut dvv = DottedVersionVector::default();
+    dvv.event(3u8);
+    let dvv = dir.save("dvv", &dvv);
+    assert!(stdout(&cvrdt(&["show", &dvv, "3:1"])).ends_with("value: true\n"));
+    assert!(stdout(&cvrdt(&["show", &dvv, "3:2"])).ends_with("value: false\n"));
+}
+
+#[test]
+fn show_queries_later_types() {
+    let dir = TempDir::new("later");
+    let mut c = CollectableTwoPhaseSet::<u8, char>::default();
+    c.add((0, 'x'));
+    c.add((0, 'y'));
+    c.del((0, 'x'));
+    let c = dir.save("collectable", &c);
+    let output = stdout(&cvrdt(&["show", &c, "y"]));
+    assert!(output.starts_with("type: CollectableTwoPhaseSet<u8, char>\n"));
+    assert!(output.ends_with("value: true\n"));
+    assert!(stdout(&cvrdt(&["show", &c, "x"])).ends_with("value: false\n"));
+    let cents = |units| Decimal::<2>::from_units(units);
+    let sum = dir.save("sum", &Accumulator::new((0, vec![cents(150), cents(25)])));
+    let output = stdout(&cvrdt(&["show", &sum]));
+    assert!(output.starts_with("type: Accumulator<Decimal<2>>\n"));
+    assert!(output.ends_with(&format!("value: {:?}\n", cents(175))));
+    let mut mean = MeanCounter::new((0, vec![Float::ZERO; 2], vec![0; 2]));
+    mean.add(Float::try_from(0.5).unwrap());
+    let mean = dir.save("mean", &mean);
+    let output = stdout(&cvrdt(&["show", &mean]));
+    assert!(output.starts_with("type: MeanCounter<Float>\n"));
+    assert!(output.contains("count: 1"));
+    let mut histogram = Histogram::with_bounds(0, 1, vec![10u32, 100]);
+    histogram.add(50);
+    let histogram = dir.save("histogram", &histogram);
+    let output = stdout(&cvrdt(&["show", &histogram, "0.5"]));
+    assert!(output.starts_with("type: Histogram<u32>\n"));
+    assert!(output.ends_with("value: Some(Bucket { lower: Some(10), upper: Some(100) })\n"));
+    let output = cvrdt(&["show", &histogram, "1.5"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn merge_writes_the_merged_state() {
+    let dir = TempDir::new("merge");
+    let x = GCounter::new((0, vec![4, 0, 1]));
+    let y = GCounter::new((1, vec![1, 5, 0]));
+    let z = GCounter::new((2, vec![0, 0, 9]));
+    let inputs = [dir.save("x", &x), dir.save("y", &y), dir.save("z", &z)];
+    let output = dir.0.join("merged").to_string_lossy().into_owned();
+    let mut args = vec!["merge", output.as_str()];
+    args.extend(inputs.iter().map(String::as_str));
+    let result = cvrdt(&args);
+    assert!(result.status.success());
+    let merged: GCounter = wire::decode(&fs::read(&output).unwrap()).unwrap();
+    assert_eq!(merged, x.merge(&y).merge(&z));
+    assert_eq!(stdout(&result), "payload: (0, [4, 5, 9])\n");
+}
+
+#[test]
+fn compare_reports_the_order() {
+    let dir = TempDir::new("compare");
+    let pn = |positive: Vec<u64>| PNCounter::new((0, positive, vec![0, 0]));
+    let small = dir.save("small", &pn(vec![1, 0]));
+    let big = dir.save("big", &pn(vec![1, 1]));
+    let other = dir.save("other", &pn(vec![0, 2]));
+    for (x, y, expected, code) in [
+        (&small, &big, "less\n", 0),
+        (&big, &small, "greater\n", 0),
+        (&big, &big, "equal\n", 0),
+        (&small, &other, "incomparable\n", 1),
+    ] {
+        let output = cvrdt(&["compare", x, y]);
+        assert_eq!(stdout(&output), expected);
+        assert_eq!(output.status.code(), Some(code));
+    }
+}
+
+#[test]
+fn errors_exit_with_two() {
+    let dir = TempDir::new("errors");
+    let set = dir.save(
+        "set",
+        &TwoPhaseSet::new((HashSet::from(['x']), HashSet::new())),
+    );
+    let flag = dir.save("flag", &OneWayBoolean::new(true));
+    let garbage = dir.0.join("garbage");
+    fs::write(&garbage, b"not a cvrdt").unwrap();
+    let garbage = garbage.to_string_lossy().into_owned();
+    for args in [
+        vec![],
+        vec!["frobnicate"],
+        vec!["show"],
+        vec!["show", "/nonexistent"],
+        vec!["show", &garbage],
+        vec!["show", &set, "too long"],
+        vec!["compare", &set, &flag],
+        vec!["merge", "/nonexistent/out", &flag],
+    ] {
+        let output = cvrdt(&args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(!output.stderr.is_empty());
+    }
+    assert!(cvrdt(&["--help"]).status.success());
+}
+
+#[test]
+fn mismatched_and_negative_counters_are_errors() {
+    let dir = TempDir::new("mismatched");
+    let two = dir.save("two", &GCounter::new((0, vec![1u64, 2])));
+    let three = dir.save("three", &GCounter::new((0, vec![1u64, 2, 3])));
+    let output = dir.0.join("merged").to_string_lossy().into_owned();
+    let negative = dir.save("negative", &PNCounter::new((0, vec![1u64, 0], vec![0, 2])));
+    let max = Decimal::<0>::from_units(u64::MAX);
+    let overflowing = dir.save("overflowing", &Accumulator::new((0, vec![max, max])));
+    for args in [
+        vec!["merge", output.as_str(), &two, &three],
+        vec!["compare", &two, &three],
+        vec!["show", &negative],
+        vec!["show", &overflowing],
+    ] {
+        let result = cvrdt(&args);
+        assert_eq!(result.status.code(), Some(2), "{args:?}");
+        assert!(!result.stderr.is_empty());
+    }
+    assert!(!fs::exists(&output).unwrap());
+}
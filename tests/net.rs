@@ -0,0 +1,191 @@
+//! Several replicas gossiping on localhost with the `net` feature
+
+use cvrdt_exposition::net::{read_frame, write_frame, Config, Node};
+use cvrdt_exposition::wire::Wire;
+use cvrdt_exposition::{GCounter, GSet, Grow, Shrink, TwoPhaseSet};
+use std::collections::HashSet;
+use std::io::Write;
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+static NODES: u64 = 4;
+static UPDATES: u64 = 50;
+static DEADLINE: Duration = Duration::from_secs(30);
+
+fn config(seed: u64) -> Config {
+    Config {
+        fanout: 2,
+        interval: Duration::from_millis(10),
+        seed,
+        ..Config::default()
+    }
+}
+
+/// Start `NODES` nodes holding `initial`, where node `i` gossips with every node in `peers(i)`
+fn cluster<G: Grow + Wire + Send + 'static>(
+    initial: &G,
+    peers: impl Fn(usize) -> Vec<usize>,
+) -> Vec<Node<G>> {
+    let nodes: Vec<_> = (0..NODES)
+        .map(|seed| Node::bind("127.0.0.1:0", initial.clone(), config(seed)).unwrap())
+        .collect();
+    for (i, node) in nodes.iter().enumerate() {
+        for j in peers(i) {
+            node.add_peer(nodes[j].local_addr());
+        }
+    }
+    nodes
+}
+
+fn everyone(_: usize) -> Vec<usize> {
+    (0..NODES as usize).collect()
+}
+
+/// Wait until every node has the same payload, then shut them all down
+fn converge<G: Grow + Wire + Send + 'static>(nodes: Vec<Node<G>>) -> G {
+    let start = Instant::now();
+    while !nodes
+        .windows(2)
+        .all(|w| w[0].state().payload() == w[1].state().payload())
+    {
+        assert!(start.elapsed() < DEADLINE, "Nodes failed to converge");
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut states: Vec<_> = nodes.into_iter().map(Node::shutdown).collect();
+    states.pop().unwrap()
+}
+
+#[test]
+fn g_set_converges_after_concurrent_updates() {
+    let nodes = cluster(&GSet::new(HashSet::new()), everyone);
+    thread::scope(|s| {
+        for (i, node) in nodes.iter().enumerate() {
+            s.spawn(move || {
+                for k in 0..UPDATES {
+                    node.update(|g| g.add(i as u64 * UPDATES + k));
+                    thread::sleep(Duration::from_millis(1));
+                }
+            });
+        }
+    });
+    let merged = converge(nodes);
+    assert_eq!(merged.values, (0..NODES * UPDATES).collect());
+}
+
+#[test]
+fn two_phase_set_converges_after_concurrent_updates() {
+    let nodes = cluster(
+        &TwoPhaseSet::new((HashSet::new(), HashSet::new())),
+        everyone,
+    );
+    thread::scope(|s| {
+        for (i, node) in nodes.iter().enumerate() {
+            s.spawn(move || {
+                for k in 0..UPDATES {
+                    let x = i as u64 * UPDATES + k;
+                    node.update(|t| {
+                        t.add(x);
+                        if x.is_multiple_of(3) {
+                            t.del(x);
+                        }
+                    });
+                }
+            });
+        }
+    });
+    let merged = converge(nodes);
+    for x in 0..NODES * UPDATES {
+        assert_eq!(merged.query(&x), !x.is_multiple_of(3));
+    }
+}
+
+#[test]
+fn ring_converges() {
+    // each node only knows the next one, but exchanges are push-pull, so updates spread both ways
+    let nodes = cluster(&GSet::new(HashSet::new()), |i| {
+        vec![(i + 1) % NODES as usize]
+    });
+    for (i, node) in nodes.iter().enumerate() {
+        node.update(|g| g.add(i as u64));
+    }
+    let merged = converge(nodes);
+    assert_eq!(merged.values, (0..NODES).collect());
+}
+
+#[test]
+fn survives_bad_peers() {
+    let nodes = cluster(&GSet::new(HashSet::new()), everyone);
+    // a peer that has gone away
+    let gone = Node::bind(
+        "127.0.0.1:0",
+        GSet::<u64>::new(HashSet::new()),
+        Config::default(),
+    )
+    .unwrap();
+    let gone_addr = gone.local_addr();
+    let _ = gone.shutdown();
+    nodes[0].add_peer(gone_addr);
+    // a peer that speaks nonsense
+    let mut garbage = TcpStream::connect(nodes[1].local_addr()).unwrap();
+    garbage.write_all(&[0xff; 64]).unwrap();
+    drop(garbage);
+    for (i, node) in nodes.iter().enumerate() {
+        node.update(|g| g.add(i as u64));
+    }
+    let merged = converge(nodes);
+    assert_eq!(merged.values, (0..NODES).collect());
+}
+
+#[test]
+fn rejects_incompatible_states() {
+    let node = Node::bind(
+        "127.0.0.1:0",
+        GCounter::new((0, vec![1u64, 0])),
+        Config::default(),
+    )
+    .unwrap();
+    // a counter with a different number of slots can't be merged, so gets no reply
+    let mut stream = TcpStream::connect(node.local_addr()).unwrap();
+    write_frame(&mut stream, &GCounter::new((0, vec![0u64; 3]))).unwrap();
+    assert!(read_frame::<GCounter>(&mut stream).is_err());
+    // but the node is none the worse for it
+    assert_eq!(node.state().payload(), (0, vec![1, 0]));
+    let peer = Node::bind(
+        "127.0.0.1:0",
+        GCounter::new((1, vec![0u64, 2])),
+        Config::default(),
+    )
+    .unwrap();
+    peer.exchange(node.local_addr()).unwrap();
+    assert_eq!(node.shutdown().payload(), (0, vec![1, 2]));
+}
+
+#[test]
+fn silent_peers_do_not_block_exchanges() {
+    let slow = Config {
+        timeout: DEADLINE,
+        ..Config::default()
+    };
+    let x = Node::bind("127.0.0.1:0", GSet::new(HashSet::from([1u8])), slow).unwrap();
+    let y = Node::bind("127.0.0.1:0", GSet::new(HashSet::from([2u8])), config(0)).unwrap();
+    // connects, then never says anything
+    let _silent = TcpStream::connect(x.local_addr()).unwrap();
+    let start = Instant::now();
+    y.exchange(x.local_addr()).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(x.state().values, HashSet::from([1, 2]));
+}
+
+#[test]
+fn dropping_stops_the_node() {
+    let node = Node::bind(
+        "127.0.0.1:0",
+        GSet::<u8>::new(HashSet::new()),
+        Config::default(),
+    )
+    .unwrap();
+    let addr = node.local_addr();
+    drop(node);
+    assert!(TcpStream::connect(addr).is_err());
+}
@@ -0,0 +1,61 @@
+//! Checking a `CvRDT` defined outside of `cvrdt-exposition` with the `testing` feature
+
+use cvrdt_exposition::{
+    properties::{grow, shrink},
+    Grow, Shrink,
+};
+use proptest::prelude::*;
+
+/// A register holding the largest value it has seen; "deleting" a value is a no-op, since a
+/// `MaxRegister` can never decrease
+#[derive(Debug, Clone)]
+struct MaxRegister {
+    value: u64,
+}
+
+impl Grow for MaxRegister {
+    type Payload = u64;
+    type PayloadRef<'a> = u64;
+    type Update = u64;
+    type Query = ();
+    type Value = u64;
+
+    fn new(payload: Self::Payload) -> Self {
+        MaxRegister { value: payload }
+    }
+    fn payload(&self) -> Self::Payload {
+        self.value
+    }
+    fn payload_ref(&self) -> Self::PayloadRef<'_> {
+        self.value
+    }
+    fn add(&mut self, update: Self::Update) {
+        self.value = self.value.max(update);
+    }
+    fn le(&self, other: &Self) -> bool {
+        self.value <= other.value
+    }
+    fn merge(&self, other: &Self) -> Self {
+        MaxRegister {
+            value: self.value.max(other.value),
+        }
+    }
+    fn query(&self, _query: &Self::Query) -> Self::Value {
+        self.value
+    }
+}
+
+impl Shrink for MaxRegister {
+    fn del(&mut self, _update: Self::Update) {}
+}
+
+fn cvrdt() -> impl Strategy<Value = MaxRegister> {
+    any::<u64>().prop_map(MaxRegister::new)
+}
+
+fn cvrdt_and_update() -> impl Strategy<Value = (MaxRegister, u64)> {
+    (cvrdt(), any::<u64>())
+}
+
+grow!(cvrdt, cvrdt_and_update);
+shrink!(cvrdt_and_update);
@@ -0,0 +1,171 @@
+//! The `cvrdt-repl` binary, fed scripts on standard input
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run `script` through the REPL, returning everything it printed
+fn repl(script: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cvrdt-repl"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn counters() {
+    let script = "
+        new x gcounter 0 2
+        new y gcounter 1 2
+        add x
+        add y
+        add y
+        le x y
+        merge x y
+        query x
+        new p pncounter 0 1
+        add p
+        del p
+        del p
+        show p
+    ";
+    let expected = "\
+x = (0, [0, 0])
+y = (1, [0, 0])
+x = (0, [1, 0])
+y = (1, [0, 1])
+y = (1, [0, 2])
+false
+x = (0, [1, 2])
+3
+p = (0, [0], [0])
+p = (0, [1], [0])
+p = (0, [1], [1])
+p = (0, [1], [2])
+p = (0, [1], [2])
+";
+    assert_eq!(repl(script), expected);
+}
+
+#[test]
+fn sets() {
+    let script = "
+        new g gset
+        add g b
+        add g a
+        query g a
+        query g z
+        new t 2pset
+        add t a
+        new u 2pset
+        merge u t
+        del u a
+        merge t u
+        query t a
+        add t a
+        compare t u
+    ";
+    let expected = "\
+g = {}
+g = {\"b\"}
+g = {\"a\", \"b\"}
+true
+false
+t = ({}, {})
+t = ({\"a\"}, {})
+u = ({}, {})
+u = ({\"a\"}, {})
+u = ({\"a\"}, {\"a\"})
+t = ({\"a\"}, {\"a\"})
+false
+t = ({\"a\"}, {\"a\"})
+equal
+";
+    assert_eq!(repl(script), expected);
+}
+
+#[test]
+fn registers_and_flags() {
+    let output = repl(
+        "
+        new r lww first
+        new s lww second
+        add r third
+        merge s r
+        query s
+        new f flag
+        new g flag
+        add g
+        compare f g
+        merge f g
+        query f
+        ",
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[0].starts_with("r = (\"first\", "));
+    assert!(lines[1].starts_with("s = (\"second\", "));
+    assert!(lines[2].starts_with("r = (\"third\", "));
+    assert!(lines[3].starts_with("s = (\"third\", "));
+    assert_eq!(
+        &lines[4..],
+        [
+            "\"third\"",
+            "f = false",
+            "g = false",
+            "g = true",
+            "less",
+            "f = true",
+            "true"
+        ]
+    );
+}
+
+#[test]
+fn errors_are_reported_and_skipped() {
+    let script = "
+        frobnicate
+        add nobody
+        new c gcounter 2 2
+        new c gcounter 0 2
+        new d gcounter 0 3
+        merge c d
+        del c
+        new s 2pset
+        del s missing
+        le s c
+        new p pncounter 0 1
+        del p
+        query p
+        new x widget
+        add c too many
+        quit
+        show c
+    ";
+    let expected = "\
+error: can't parse \"frobnicate\" command; try `help`
+error: no replica named \"nobody\"
+error: ID must be less than SIZE
+c = (0, [0, 0])
+d = (0, [0, 0, 0])
+error: counters have different sizes (2 and 3)
+error: a gcounter can't delete
+s = ({}, {})
+error: \"missing\" isn't in the set
+error: can't mix a 2pset and a gcounter
+p = (0, [0], [0])
+p = (0, [0], [1])
+error: the count is negative
+error: unknown type \"widget\"; try `help`
+error: can't parse \"add\" command; try `help`
+";
+    assert_eq!(repl(script), expected);
+}